@@ -0,0 +1,266 @@
+// HTTP front end for the generation pipeline and terrain storage.
+// Heights travel as flat row-major f32 arrays, images as base64 PNG
+use std::io::Cursor;
+use std::sync::Arc;
+
+use app::core_pipeline::{GenerationParams, run_pipeline, terrain_doc};
+use axum::extract::{Json, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Router};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use storage::Storage2D;
+use storage::models::{TerrainDoc2D, TerrainMetadata, validate_terrain_name};
+use terrain::utils::{HeightMap2D, TerrainPalette, flatten2, to_terrain_image_with_palette};
+
+// Largest side /generate accepts, keeps a single request from taking minutes
+pub const MAX_SIDE: usize = 1025;
+// Same idea for the loops inside the pipeline: erosion iterations (main and
+// per extra pass), octaves, warp iterations and the number of passes/layers
+pub const MAX_ITERATIONS: u32 = 500;
+pub const MAX_OCTAVES: u32 = 16;
+pub const MAX_WARP_ITERATIONS: usize = 8;
+pub const MAX_PASSES: usize = 16;
+
+// None when MongoDB was unreachable at startup, /terrains then answers 503
+pub type SharedStorage = Option<Arc<Storage2D>>;
+
+pub fn router(storage: SharedStorage) -> Router {
+    Router::new()
+        .route("/generate", post(generate))
+        .route("/terrains", post(save_terrain).get(list_terrains))
+        .route("/terrains/{name}", get(get_terrain).delete(delete_terrain))
+        .layer(Extension(storage))
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    NotFound(String),
+    NoDatabase,
+    Internal(String),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            ApiError::BadRequest(e) => (StatusCode::BAD_REQUEST, e),
+            ApiError::NotFound(name) => {
+                (StatusCode::NOT_FOUND, format!("no terrain named {}", name))
+            }
+            ApiError::NoDatabase => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "database not connected".to_string(),
+            ),
+            ApiError::Internal(e) => (StatusCode::INTERNAL_SERVER_ERROR, e),
+        };
+        (status, Json(ErrorBody { error })).into_response()
+    }
+}
+
+fn internal(e: impl std::fmt::Display) -> ApiError {
+    ApiError::Internal(e.to_string())
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GenerateResponse {
+    pub width: usize,
+    pub height: usize,
+    // Base64 PNG in the default palette
+    pub png: String,
+    // Normalized heights, row-major, width × height values
+    pub height_map: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveRequest {
+    pub name: String,
+    pub params: GenerationParams,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+// A stored terrain without its heights
+#[derive(Serialize, Deserialize)]
+pub struct TerrainSummary {
+    pub name: String,
+    pub seed: i64,
+    pub noise_type: String,
+    pub width: u32,
+    pub height: u32,
+    pub tags: Vec<String>,
+}
+
+impl From<TerrainMetadata> for TerrainSummary {
+    fn from(meta: TerrainMetadata) -> Self {
+        Self {
+            name: meta.name,
+            seed: meta.seed,
+            noise_type: meta.params.noise_type,
+            width: meta.width,
+            height: meta.height,
+            tags: meta.tags,
+        }
+    }
+}
+
+fn summary(doc: &TerrainDoc2D) -> TerrainSummary {
+    TerrainSummary {
+        name: doc.name.clone(),
+        seed: doc.seed,
+        noise_type: doc.params.noise_type.clone(),
+        width: doc.width,
+        height: doc.height,
+        tags: doc.tags.clone(),
+    }
+}
+
+fn encode_png(rgb: Vec<u8>, width: usize, height: usize) -> Result<String, ApiError> {
+    let img = image::RgbImage::from_raw(width as u32, height as u32, rgb)
+        .ok_or_else(|| internal("image size mismatch"))?;
+    let mut png = Cursor::new(Vec::new());
+    img.write_to(&mut png, image::ImageFormat::Png)
+        .map_err(internal)?;
+    Ok(BASE64.encode(png.into_inner()))
+}
+
+fn storage(storage: &SharedStorage) -> Result<&Storage2D, ApiError> {
+    storage.as_deref().ok_or(ApiError::NoDatabase)
+}
+
+// Work limits on top of GenerationParams::validate()
+fn check_limits(params: &GenerationParams) -> Result<(), String> {
+    let mut iterations = std::iter::once(params.erosion_iters)
+        .chain(params.extra_erosion.iter().map(|pass| pass.iterations));
+    if iterations.any(|n| n > MAX_ITERATIONS) {
+        return Err(format!(
+            "erosion iterations must be at most {}",
+            MAX_ITERATIONS
+        ));
+    }
+    let mut octaves =
+        std::iter::once(params.octaves).chain(params.layers.iter().map(|l| l.octaves));
+    if octaves.any(|n| n > MAX_OCTAVES) {
+        return Err(format!("octaves must be at most {}", MAX_OCTAVES));
+    }
+    if params.warp_iterations > MAX_WARP_ITERATIONS {
+        return Err(format!(
+            "warp_iterations must be at most {}",
+            MAX_WARP_ITERATIONS
+        ));
+    }
+    if params.extra_erosion.len() > MAX_PASSES || params.layers.len() > MAX_PASSES {
+        return Err(format!(
+            "at most {} extra erosion passes and {} layers",
+            MAX_PASSES, MAX_PASSES
+        ));
+    }
+    Ok(())
+}
+
+// Validate, then run the pipeline off the async workers
+async fn run_checked(
+    params: GenerationParams,
+) -> Result<(GenerationParams, HeightMap2D, Vec<u8>), ApiError> {
+    params.validate().map_err(ApiError::BadRequest)?;
+    let (width, height) = params.dims();
+    if width > MAX_SIDE || height > MAX_SIDE {
+        return Err(ApiError::BadRequest(format!(
+            "width and height must be at most {}",
+            MAX_SIDE
+        )));
+    }
+    check_limits(&params).map_err(ApiError::BadRequest)?;
+    tokio::task::spawn_blocking(move || {
+        let (grid, rgb) = run_pipeline(&params);
+        (params, grid, rgb)
+    })
+    .await
+    .map_err(internal)
+}
+
+async fn generate(
+    Json(params): Json<GenerationParams>,
+) -> Result<Json<GenerateResponse>, ApiError> {
+    let (params, grid, rgb) = run_checked(params).await?;
+    let (width, height) = params.dims();
+    Ok(Json(GenerateResponse {
+        width,
+        height,
+        png: encode_png(rgb, width, height)?,
+        height_map: flatten2(&grid),
+    }))
+}
+
+async fn save_terrain(
+    Extension(shared): Extension<SharedStorage>,
+    Json(request): Json<SaveRequest>,
+) -> Result<(StatusCode, Json<TerrainSummary>), ApiError> {
+    let storage = storage(&shared)?;
+    validate_terrain_name(&request.name).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let (params, grid, _) = run_checked(request.params).await?;
+    let doc = terrain_doc(request.name, &params, &grid, request.tags);
+    let saved = summary(&doc);
+    storage.create(doc).await.map_err(internal)?;
+    Ok((StatusCode::CREATED, Json(saved)))
+}
+
+async fn list_terrains(
+    Extension(shared): Extension<SharedStorage>,
+) -> Result<Json<Vec<TerrainSummary>>, ApiError> {
+    let all = storage(&shared)?
+        .list_all_metadata()
+        .await
+        .map_err(internal)?;
+    Ok(Json(all.into_iter().map(TerrainSummary::from).collect()))
+}
+
+// The stored heights with a freshly coloured PNG
+#[derive(Serialize, Deserialize)]
+pub struct TerrainResponse {
+    #[serde(flatten)]
+    pub summary: TerrainSummary,
+    pub png: String,
+    pub height_map: Vec<f32>,
+}
+
+async fn get_terrain(
+    Extension(shared): Extension<SharedStorage>,
+    Path(name): Path<String>,
+) -> Result<Json<TerrainResponse>, ApiError> {
+    let mut doc = storage(&shared)?
+        .read_by_name(&name)
+        .await
+        .map_err(internal)?
+        .ok_or(ApiError::NotFound(name))?;
+    doc.fill_missing_size();
+    let rgb = to_terrain_image_with_palette(&doc.height_map, &TerrainPalette::default());
+    Ok(Json(TerrainResponse {
+        summary: summary(&doc),
+        png: encode_png(rgb, doc.width as usize, doc.height as usize)?,
+        height_map: doc.height_map,
+    }))
+}
+
+async fn delete_terrain(
+    Extension(shared): Extension<SharedStorage>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if storage(&shared)?
+        .delete_by_name(&name)
+        .await
+        .map_err(internal)?
+    {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::NotFound(name))
+    }
+}
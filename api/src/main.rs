@@ -0,0 +1,37 @@
+// Serve the terrain API:
+//   cargo run --release --bin api
+// API_ADDR sets the listen address (default 127.0.0.1:3000). TERRAIN_MONGO_URI,
+// TERRAIN_DB_NAME and TERRAIN_COLLECTION pick the database like the app does
+use std::sync::Arc;
+
+use storage::Storage2D;
+
+fn env_or(key: &str, default: &str) -> String {
+    std::env::var(key)
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| default.to_string())
+}
+
+#[tokio::main]
+async fn main() {
+    let addr = env_or("API_ADDR", "127.0.0.1:3000");
+    let uri = env_or("TERRAIN_MONGO_URI", "mongodb://localhost:27017");
+    let db = env_or("TERRAIN_DB_NAME", "terrain_db");
+    let collection = env_or("TERRAIN_COLLECTION", "terrain2d");
+
+    // /generate works without a database, so a missing one is not fatal
+    let storage = match Storage2D::init(&uri, &db, &collection).await {
+        Ok(storage) => Some(Arc::new(storage)),
+        Err(e) => {
+            eprintln!("MongoDB unavailable at {} ({}), /terrains disabled", uri, e);
+            None
+        }
+    };
+
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .unwrap_or_else(|e| panic!("cannot listen on {}: {}", addr, e));
+    println!("Terrain API listening on http://{}", addr);
+    axum::serve(listener, api::router(storage)).await.unwrap();
+}
@@ -0,0 +1,100 @@
+// Requests against the router in memory, no socket or database needed
+use api::{ErrorBody, GenerateResponse, MAX_ITERATIONS, router};
+use app::core_pipeline::{GenerationParams, NoiseLayer, NoiseType, run_pipeline};
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use terrain::utils::flatten2;
+use tower::ServiceExt;
+
+async fn post_json(uri: &str, body: String) -> (StatusCode, Vec<u8>) {
+    let request = Request::post(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap();
+    let response = router(None).oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, bytes.to_vec())
+}
+
+#[tokio::test]
+async fn generate_returns_png_of_requested_size() {
+    let params = GenerationParams {
+        noise_type: NoiseType::Perlin2D,
+        width: 48,
+        height: Some(24),
+        seed: 1234,
+        ..GenerationParams::default()
+    };
+    let (status, body) = post_json("/generate", serde_json::to_string(&params).unwrap()).await;
+    assert_eq!(status, StatusCode::OK);
+    let response: GenerateResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!((response.width, response.height), (48, 24));
+
+    let png = BASE64.decode(&response.png).unwrap();
+    let img = image::load_from_memory_with_format(&png, image::ImageFormat::Png).unwrap();
+    assert_eq!((img.width(), img.height()), (48, 24));
+
+    // The same seed gives the same terrain as the pipeline run directly
+    let (grid, rgb) = run_pipeline(&params);
+    assert_eq!(response.height_map, flatten2(&grid));
+    assert_eq!(img.to_rgb8().into_raw(), rgb);
+}
+
+#[tokio::test]
+async fn generate_rejects_bad_params() {
+    let params = GenerationParams {
+        width: 100, // not 2^n + 1 for the default fractal noise
+        ..GenerationParams::default()
+    };
+    let (status, body) = post_json("/generate", serde_json::to_string(&params).unwrap()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let error: ErrorBody = serde_json::from_slice(&body).unwrap();
+    assert!(error.error.contains("2^n + 1"));
+}
+
+#[tokio::test]
+async fn generate_rejects_fractal_width_2_and_fractal_layers() {
+    let width_2 = GenerationParams {
+        width: 2,
+        ..GenerationParams::default()
+    };
+    let fractal_layer = GenerationParams {
+        noise_type: NoiseType::Perlin2D,
+        width: 100,
+        layers: vec![NoiseLayer {
+            noise_type: NoiseType::Fractal2D,
+            ..NoiseLayer::default()
+        }],
+        ..GenerationParams::default()
+    };
+    for params in [width_2, fractal_layer] {
+        let (status, body) = post_json("/generate", serde_json::to_string(&params).unwrap()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let error: ErrorBody = serde_json::from_slice(&body).unwrap();
+        assert!(error.error.contains("2^n + 1"));
+    }
+}
+
+#[tokio::test]
+async fn generate_caps_iterations() {
+    let params = GenerationParams {
+        noise_type: NoiseType::Perlin2D,
+        width: 16,
+        erosion_iters: MAX_ITERATIONS + 1,
+        ..GenerationParams::default()
+    };
+    let (status, body) = post_json("/generate", serde_json::to_string(&params).unwrap()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let error: ErrorBody = serde_json::from_slice(&body).unwrap();
+    assert!(error.error.contains("erosion iterations"));
+}
+
+#[tokio::test]
+async fn terrains_need_a_database() {
+    let request = Request::get("/terrains").body(Body::empty()).unwrap();
+    let response = router(None).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
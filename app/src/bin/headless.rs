@@ -0,0 +1,90 @@
+// Generate a terrain PNG without opening a window, e.g. on a server or in CI:
+//   headless [--settings profile.toml] [--seed N] [--noise fractal|perlin|simplex]
+//            [--size N] [--output terrain.png]
+// Without --settings the parameters are the app's defaults
+use std::process::exit;
+use std::time::Instant;
+
+use app::core_pipeline::{GenerationParams, NoiseType, Settings, run_pipeline};
+
+const USAGE: &str = "usage: headless [--settings profile.toml] [--seed N] \
+                     [--noise fractal|perlin|simplex] [--size N] [--output terrain.png]";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    exit(2);
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+    value
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| fail(&format!("{} needs a number", flag)))
+}
+
+fn main() {
+    let mut params = GenerationParams::default();
+    let mut output = String::from("terrain.png");
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--settings" => {
+                let path = args
+                    .next()
+                    .unwrap_or_else(|| fail("--settings needs a file"));
+                let text = std::fs::read_to_string(&path)
+                    .unwrap_or_else(|e| fail(&format!("{}: {}", path, e)));
+                let settings: Settings =
+                    toml::from_str(&text).unwrap_or_else(|e| fail(&format!("{}: {}", path, e)));
+                params = settings.params;
+            }
+            "--seed" => params.seed = parse("--seed", args.next()),
+            "--size" => {
+                params.width = parse("--size", args.next());
+                params.height = None;
+            }
+            "--noise" => {
+                params.noise_type = match args.next().as_deref() {
+                    Some("fractal") => NoiseType::Fractal2D,
+                    Some("perlin") => NoiseType::Perlin2D,
+                    Some("simplex") => NoiseType::Simplex2D,
+                    _ => fail("--noise is one of fractal, perlin, simplex"),
+                }
+            }
+            "--output" | "-o" => {
+                output = args.next().unwrap_or_else(|| fail("--output needs a file"));
+            }
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            other => fail(&format!("unknown argument {}", other)),
+        }
+    }
+
+    if let Err(message) = params.validate() {
+        fail(&message);
+    }
+    let (width, height) = params.dims();
+
+    let start = Instant::now();
+    let (_, image) = run_pipeline(&params);
+    if let Err(e) = image::save_buffer(
+        &output,
+        &image,
+        width as u32,
+        height as u32,
+        image::ColorType::Rgb8,
+    ) {
+        eprintln!("{}: {}", output, e);
+        exit(1);
+    }
+    println!(
+        "Saved {}×{} terrain (seed {}) to {} in {:.2} ms",
+        width,
+        height,
+        params.seed,
+        output,
+        start.elapsed().as_secs_f32() * 1000.0
+    );
+}
@@ -0,0 +1,112 @@
+// Where the app finds MongoDB, and the GUI preferences kept between runs
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::NoiseType;
+
+const DEFAULT_MONGO_URI: &str = "mongodb://localhost:27017";
+const DEFAULT_DB_NAME: &str = "terrain_db";
+const DEFAULT_COLLECTION: &str = "terrain2d";
+const PREFERENCES_FILE: &str = ".terrain_generator.toml";
+
+// MongoDB connection target
+#[derive(Clone, Debug, PartialEq)]
+pub struct DbConfig {
+    pub uri: String,
+    pub db_name: String,
+    pub collection: String,
+}
+
+impl DbConfig {
+    // TERRAIN_MONGO_URI, TERRAIN_DB_NAME and TERRAIN_COLLECTION win,
+    // then the URI saved in the preferences file, then the defaults
+    pub fn resolve(saved_uri: Option<&str>) -> Self {
+        Self::resolve_with(|key| std::env::var(key).ok(), saved_uri)
+    }
+
+    // resolve() with the environment lookup passed in, for tests
+    fn resolve_with(env: impl Fn(&str) -> Option<String>, saved_uri: Option<&str>) -> Self {
+        let var = |key: &str| env(key).filter(|v| !v.is_empty());
+        Self {
+            uri: var("TERRAIN_MONGO_URI")
+                .or_else(|| saved_uri.map(str::to_owned))
+                .unwrap_or_else(|| DEFAULT_MONGO_URI.to_owned()),
+            db_name: var("TERRAIN_DB_NAME").unwrap_or_else(|| DEFAULT_DB_NAME.to_owned()),
+            collection: var("TERRAIN_COLLECTION").unwrap_or_else(|| DEFAULT_COLLECTION.to_owned()),
+        }
+    }
+}
+
+// ~/.terrain_generator.toml, read at startup and written on exit.
+// Every field is optional so a partial or older file still loads
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub window_size: Option<[f32; 2]>,
+    pub seed: Option<u64>,
+    pub noise_type: Option<NoiseType>,
+    // Set from the Settings dialog, TERRAIN_MONGO_URI still overrides it
+    pub mongo_uri: Option<String>,
+}
+
+impl Preferences {
+    fn path() -> Option<PathBuf> {
+        directories::BaseDirs::new().map(|dirs| dirs.home_dir().join(PREFERENCES_FILE))
+    }
+
+    // Defaults if the file is missing or unreadable, preferences are never fatal
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = Self::path()
+            .ok_or_else(|| std::io::Error::other("no home directory for preferences"))?;
+        let text = toml::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DbConfig, Preferences};
+    use crate::NoiseType;
+
+    #[test]
+    fn env_overrides_saved_uri_and_defaults() {
+        let env = |key: &str| match key {
+            "TERRAIN_MONGO_URI" => Some("mongodb://db:27017".to_owned()),
+            "TERRAIN_COLLECTION" => Some("maps".to_owned()),
+            _ => None,
+        };
+        let config = DbConfig::resolve_with(env, Some("mongodb://saved:27017"));
+        assert_eq!(config.uri, "mongodb://db:27017");
+        assert_eq!(config.db_name, "terrain_db");
+        assert_eq!(config.collection, "maps");
+
+        let config = DbConfig::resolve_with(|_| None, Some("mongodb://saved:27017"));
+        assert_eq!(config.uri, "mongodb://saved:27017");
+        let config = DbConfig::resolve_with(|_| None, None);
+        assert_eq!(config.uri, "mongodb://localhost:27017");
+    }
+
+    #[test]
+    fn preferences_roundtrip_and_partial_file() {
+        let prefs = Preferences {
+            window_size: Some([1024.0, 768.0]),
+            seed: Some(7),
+            noise_type: Some(NoiseType::Simplex2D),
+            mongo_uri: None,
+        };
+        let text = toml::to_string_pretty(&prefs).unwrap();
+        assert_eq!(toml::from_str::<Preferences>(&text).unwrap(), prefs);
+
+        let partial: Preferences = toml::from_str("seed = 3").unwrap();
+        assert_eq!(partial.seed, Some(3));
+        assert_eq!(partial.window_size, None);
+    }
+}
@@ -0,0 +1,621 @@
+// Terrain generation without any GUI: parameters in, height-map and colour
+// image out. Shared by the egui app and the headless binary
+use core::{
+    ErosionPipeline, Fractal2DBuilder, NoiseGenerator, Perlin2DBuilder, Simplex2DBuilder, Terrace,
+    ThermalErosion2D,
+    domain_warp::{
+        BoundaryMode, DEFAULT_WARP_OFFSET_X, DEFAULT_WARP_OFFSET_Y, DEFAULT_WARP_SCALE,
+        DomainWarp2D, DomainWarp2DChained,
+    },
+    utils::{
+        BlendMode, HeightMap2D, TerrainPalette, blend_maps, flatten2, normalize2,
+        normalize2_clamped, normalize2_fixed_sea_level, to_terrain_image_with_palette,
+    },
+};
+use serde::{Deserialize, Serialize};
+use storage::models::{SCHEMA_VERSION, TerrainDoc2D, TerrainParams};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub enum NoiseType {
+    #[default]
+    Fractal2D,
+    Perlin2D,
+    Simplex2D,
+}
+
+// Settings saved before erosion_blend existed kept all of the erosion
+fn default_erosion_blend() -> f32 {
+    1.0
+}
+
+// Settings saved before warp_scale existed used the fixed 3.0
+fn default_warp_scale() -> f64 {
+    DEFAULT_WARP_SCALE
+}
+
+impl GenerationParams {
+    // (width, height) of the terrain to generate
+    pub fn dims(&self) -> (usize, usize) {
+        (self.width, self.height.unwrap_or(self.width))
+    }
+
+    // Parameters the generators would panic on, for input that doesn't come
+    // from the app's sliders
+    pub fn validate(&self) -> Result<(), String> {
+        let (width, height) = self.dims();
+        if width < 2 || height < 2 {
+            return Err("width and height must be at least 2".into());
+        }
+        // Diamond-square only works on 2^n + 1 cells, 3 at the least.
+        // Fractal layers are generated at the same side as the base
+        let side = width.max(height);
+        let fractal = std::iter::once(self.noise_type)
+            .chain(self.layers.iter().map(|l| l.noise_type))
+            .any(|t| t == NoiseType::Fractal2D);
+        if fractal && (side < 3 || !(side - 1).is_power_of_two()) {
+            return Err("fractal terrain needs a size of 2^n + 1, e.g. 129 or 257".into());
+        }
+        let mut octaves =
+            std::iter::once(self.octaves).chain(self.layers.iter().map(|l| l.octaves));
+        if octaves.any(|n| n == 0) {
+            return Err("octaves must be at least 1".into());
+        }
+        Ok(())
+    }
+}
+
+// Snapshot of every slider that affects generation,
+// so the worker thread never touches the live app state
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GenerationParams {
+    pub noise_type: NoiseType,
+    #[serde(alias = "size")]
+    pub width: usize,
+    // None in settings saved before non-square maps, meaning width × width
+    #[serde(default)]
+    pub height: Option<usize>,
+    pub seed: u64,
+    pub roughness: f64,
+    // Fractal2D only, wrap the edges so the terrain tiles
+    #[serde(default)]
+    pub tileable: bool,
+    pub frequency: f64,
+    pub persistence: f64,
+    pub octaves: u32,
+    pub enable_erosion: bool,
+    pub erosion_iters: u32,
+    pub talus_angle: f64,
+    // Share of the eroded terrain kept, the rest is the terrain before erosion
+    #[serde(default = "default_erosion_blend")]
+    pub erosion_blend: f32,
+    // Run after the main erosion, in order, e.g. a gentler talus to finish
+    #[serde(default)]
+    pub extra_erosion: Vec<ErosionPass>,
+    pub enable_warping: bool,
+    pub warp_strength: f64,
+    #[serde(default = "default_warp_scale")]
+    pub warp_scale: f64,
+    pub warp_iterations: usize,
+    // Warp again, sampling a second warp field where the first one lands
+    #[serde(default)]
+    pub second_order_warp: bool,
+    pub enable_terrace: bool,
+    pub terrace_steps: u32,
+    pub terrace_smoothing: f32,
+    #[serde(default)]
+    pub normalization: NormalizationMode,
+    // Blended on top of the base terrain, in order
+    #[serde(default)]
+    pub layers: Vec<NoiseLayer>,
+}
+
+// How the final height-map is mapped onto [0,1]
+// Clamped and FixedSeaLevel take raw heights, before normalization
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum NormalizationMode {
+    // Full range onto [0,1] with a gamma curve
+    #[default]
+    Stretch,
+    Clamped {
+        low: f32,
+        high: f32,
+    },
+    // `sea_level` lands on the water threshold
+    FixedSeaLevel {
+        sea_level: f32,
+    },
+}
+
+impl NormalizationMode {
+    // Every mode with its default settings, in dropdown order
+    pub const ALL: [NormalizationMode; 3] = [
+        NormalizationMode::Stretch,
+        NormalizationMode::Clamped {
+            low: -1.0,
+            high: 1.0,
+        },
+        NormalizationMode::FixedSeaLevel { sea_level: 0.0 },
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            NormalizationMode::Stretch => "Stretch (default)",
+            NormalizationMode::Clamped { .. } => "Clamped",
+            NormalizationMode::FixedSeaLevel { .. } => "Fixed Sea Level",
+        }
+    }
+
+    pub fn apply(&self, grid: &mut HeightMap2D) {
+        match *self {
+            NormalizationMode::Stretch => normalize2(grid),
+            NormalizationMode::Clamped { low, high } => normalize2_clamped(grid, low, high),
+            NormalizationMode::FixedSeaLevel { sea_level } => {
+                normalize2_fixed_sea_level(grid, sea_level)
+            }
+        }
+    }
+}
+
+// One extra noise layer in the layer stack
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NoiseLayer {
+    pub noise_type: NoiseType,
+    pub frequency: f64,
+    pub persistence: f64,
+    pub octaves: u32,
+    pub weight: f32,
+    pub blend_mode: BlendMode,
+}
+
+impl Default for NoiseLayer {
+    fn default() -> Self {
+        Self {
+            noise_type: NoiseType::Perlin2D,
+            frequency: 8.0,
+            persistence: 0.5,
+            octaves: 4,
+            weight: 0.25,
+            blend_mode: BlendMode::Add,
+        }
+    }
+}
+
+// One further thermal erosion step after the main one
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ErosionPass {
+    pub iterations: u32,
+    pub talus_angle: f64,
+}
+
+impl Default for ErosionPass {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            talus_angle: 0.5,
+        }
+    }
+}
+
+// The extra erosion steps as one pipeline
+pub fn erosion_pipeline(passes: &[ErosionPass]) -> ErosionPipeline {
+    passes
+        .iter()
+        .fold(ErosionPipeline::new(), |pipeline, pass| {
+            pipeline.with_step(ThermalErosion2D::new(
+                pass.iterations as usize,
+                pass.talus_angle as f32,
+            ))
+        })
+}
+
+// Contents of a settings profile file
+#[derive(Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(flatten)]
+    pub params: GenerationParams,
+    // Older profiles have no palette
+    #[serde(default)]
+    pub palette: TerrainPalette,
+}
+
+impl Default for GenerationParams {
+    // Same as a freshly started app
+    fn default() -> Self {
+        Self {
+            noise_type: NoiseType::Fractal2D,
+            width: 129,
+            height: None,
+            seed: 2025,
+            roughness: 1.0,
+            tileable: false,
+            frequency: 1.0,
+            persistence: 0.5,
+            octaves: 4,
+            enable_erosion: true,
+            erosion_iters: 5,
+            talus_angle: 1.0,
+            erosion_blend: 1.0,
+            extra_erosion: Vec::new(),
+            enable_warping: false,
+            warp_strength: 0.5,
+            warp_scale: DEFAULT_WARP_SCALE,
+            warp_iterations: 1,
+            second_order_warp: false,
+            enable_terrace: false,
+            terrace_steps: 8,
+            terrace_smoothing: 0.0,
+            normalization: NormalizationMode::Stretch,
+            layers: Vec::new(),
+        }
+    }
+}
+
+// Generate, erode, normalize: the grid and its colours with the default
+// palette as RGB8, row-major
+pub fn run_pipeline(params: &GenerationParams) -> (HeightMap2D, Vec<u8>) {
+    let (grid, _) = build_heightmap(params, |_, _| {});
+    let image = to_terrain_image_with_palette(&flatten2(&grid), &TerrainPalette::default());
+    (grid, image)
+}
+
+// Build the height-map described by `params`: noise, optional warp,
+// layer blending, erosion, normalization and terracing.
+// `on_erosion_pass(current, total)` is called after every erosion pass.
+// Also returns how many erosion passes ran before the terrain settled
+pub fn build_heightmap(
+    params: &GenerationParams,
+    on_erosion_pass: impl Fn(usize, usize),
+) -> (HeightMap2D, usize) {
+    let mut grid = base_grid(params);
+
+    // Extra layers are blended on [0,1] heights so every blend mode
+    // behaves the same whatever the range of the underlying noise
+    if !params.layers.is_empty() {
+        normalize2(&mut grid);
+        for (i, layer) in params.layers.iter().enumerate() {
+            let layer_params = GenerationParams {
+                noise_type: layer.noise_type,
+                seed: params.seed.wrapping_add(i as u64 + 1),
+                frequency: layer.frequency,
+                persistence: layer.persistence,
+                octaves: layer.octaves,
+                layers: Vec::new(),
+                ..params.clone()
+            };
+            let mut layer_grid = base_grid(&layer_params);
+            normalize2(&mut layer_grid);
+            blend_maps(&mut grid, &layer_grid, layer.blend_mode, layer.weight);
+        }
+    }
+
+    // Apply thermal erosion
+    let mut erosion_passes = 0;
+    if params.enable_erosion {
+        let erosion =
+            ThermalErosion2D::new(params.erosion_iters as usize, params.talus_angle as f32)
+                .with_blend_factor(params.erosion_blend)
+                .with_progress_callback(on_erosion_pass);
+        erosion.apply(&mut grid);
+        erosion_passes = erosion.iterations_taken();
+        erosion_pipeline(&params.extra_erosion).apply(&mut grid);
+    }
+
+    // Normalize only after erosion to avoid making erosion useless
+    params.normalization.apply(&mut grid); // normalize so heights are in [0,1]
+    // Terracing works on normalized heights
+    if params.enable_terrace {
+        Terrace::new(params.terrace_steps, params.terrace_smoothing).apply(&mut grid);
+    }
+    (grid, erosion_passes)
+}
+
+// Noise (plus optional domain warp) for a single layer, before erosion
+// Fractal2D and the domain warp only make squares, so non-square maps are
+// cut from a square of the longer side
+pub fn base_grid(params: &GenerationParams) -> HeightMap2D {
+    let (width, height) = params.dims();
+    let size = width.max(height);
+    let square = match params.noise_type {
+        NoiseType::Fractal2D => {
+            let mut fractal_base = Fractal2DBuilder::default()
+                .size(size)
+                .seed(params.seed)
+                .roughness(params.roughness)
+                .tileable(params.tileable)
+                .build();
+            let _ = fractal_base.generate(); // fill internal map
+
+            if params.enable_warping {
+                let mut fractal_warp = Fractal2DBuilder::default()
+                    .size(size)
+                    .seed(params.seed.wrapping_add(42))
+                    .roughness(params.roughness)
+                    .build();
+                let _ = fractal_warp.generate();
+                let warp = DomainWarp2D {
+                    base: &fractal_base,
+                    warp: &fractal_warp,
+                    warp2: None,
+                    size,
+                    warp_strength: params.warp_strength,
+                    warp_iterations: params.warp_iterations,
+                    warp_scale: params.warp_scale,
+                    warp_offset_x: DEFAULT_WARP_OFFSET_X,
+                    warp_offset_y: DEFAULT_WARP_OFFSET_Y,
+                    // A tileable base can wrap without a seam
+                    boundary: if params.tileable {
+                        BoundaryMode::Wrap
+                    } else {
+                        BoundaryMode::Mirror
+                    },
+                };
+                warp_grid(params, warp, || {
+                    let mut outer = Fractal2DBuilder::default()
+                        .size(size)
+                        .seed(params.seed.wrapping_add(84))
+                        .roughness(params.roughness)
+                        .build();
+                    let _ = outer.generate();
+                    Box::new(outer)
+                })
+            } else {
+                sample_rect(&fractal_base, size, size)
+            }
+        }
+
+        NoiseType::Perlin2D | NoiseType::Simplex2D => {
+            let base = noise_generator(params, params.seed);
+            if params.enable_warping {
+                let warp = noise_generator(params, params.seed.wrapping_add(42));
+                let warp = DomainWarp2D {
+                    base: base.as_ref(),
+                    warp: warp.as_ref(),
+                    warp2: None,
+                    size,
+                    warp_strength: params.warp_strength,
+                    warp_iterations: params.warp_iterations,
+                    warp_scale: params.warp_scale,
+                    warp_offset_x: DEFAULT_WARP_OFFSET_X,
+                    warp_offset_y: DEFAULT_WARP_OFFSET_Y,
+                    boundary: BoundaryMode::Mirror,
+                };
+                warp_grid(params, warp, || {
+                    noise_generator(params, params.seed.wrapping_add(84))
+                })
+            } else {
+                return sample_rect(base.as_ref(), width, height);
+            }
+        }
+    };
+    crop(square, width, height)
+}
+
+// Top-left width × height corner of `grid`
+fn crop(mut grid: HeightMap2D, width: usize, height: usize) -> HeightMap2D {
+    grid.truncate(height);
+    for row in grid.iter_mut() {
+        row.truncate(width);
+    }
+    grid
+}
+
+// Perlin / Simplex generator for the current parameters
+// Run `warp`, or with second_order_warp chain it with the same settings
+// around a second warp field from `outer`
+fn warp_grid(
+    params: &GenerationParams,
+    warp: DomainWarp2D,
+    outer: impl FnOnce() -> Box<dyn NoiseGenerator>,
+) -> HeightMap2D {
+    if !params.second_order_warp {
+        return warp.generate();
+    }
+    let outer = outer();
+    DomainWarp2DChained {
+        second: DomainWarp2D {
+            warp: outer.as_ref(),
+            ..warp
+        },
+        first: warp,
+    }
+    .generate()
+}
+
+fn noise_generator(params: &GenerationParams, seed: u64) -> Box<dyn NoiseGenerator> {
+    match params.noise_type {
+        NoiseType::Simplex2D => Box::new(
+            Simplex2DBuilder::default()
+                .seed(seed)
+                .frequency(params.frequency)
+                .persistence(params.persistence)
+                .octaves(params.octaves as usize)
+                .build(),
+        ),
+        _ => Box::new(
+            Perlin2DBuilder::default()
+                .seed(seed)
+                .frequency(params.frequency)
+                .persistence(params.persistence)
+                .octaves(params.octaves as usize)
+                .build(),
+        ),
+    }
+}
+
+// Sample a generator over a width×height grid, both axes scaled by the
+// longer side like Perlin2D::generate_rect()
+fn sample_rect(base: &dyn NoiseGenerator, width: usize, height: usize) -> HeightMap2D {
+    let scale = width.max(height) as f64;
+    let mut g = HeightMap2D::filled(width, height, 0.0);
+    for (y, row) in g.iter_mut().enumerate() {
+        for (x, val) in row.iter_mut().enumerate() {
+            let fx = x as f64 / scale;
+            let fy = y as f64 / scale;
+            *val = base.get2(fx, fy) as f32;
+        }
+    }
+    g
+}
+
+// Database document for a generated grid
+pub fn terrain_doc(
+    name: String,
+    params: &GenerationParams,
+    grid: &HeightMap2D,
+    tags: Vec<String>,
+) -> TerrainDoc2D {
+    TerrainDoc2D {
+        id: None,
+        name,
+        seed: params.seed as i64,
+        params: TerrainParams {
+            noise_type: format!("{:?}", params.noise_type).to_lowercase(),
+            frequency: params.frequency,
+            persistence: params.persistence,
+            octaves: params.octaves as usize,
+            amplitudes: None,
+            roughness: Some(params.roughness),
+            erosion_iters: Some(params.erosion_iters),
+            talus_angle: Some(params.talus_angle as f32),
+            warp_strength: Some(params.warp_strength),
+        },
+        height_map: flatten2(grid),
+        height_map_compressed: None,
+        dimensions: 2,
+        width: grid.first().map_or(0, Vec::len) as u32,
+        height: grid.len() as u32,
+        created_at: None,
+        tags,
+        schema_version: SCHEMA_VERSION,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ErosionPass, GenerationParams, NoiseLayer, NoiseType, Settings, base_grid, build_heightmap,
+        run_pipeline,
+    };
+
+    #[test]
+    fn second_order_warp_changes_the_terrain() {
+        let first = GenerationParams {
+            noise_type: NoiseType::Perlin2D,
+            width: 33,
+            enable_erosion: false,
+            enable_warping: true,
+            ..GenerationParams::default()
+        };
+        let second = GenerationParams {
+            second_order_warp: true,
+            ..first.clone()
+        };
+        assert_ne!(base_grid(&first), base_grid(&second));
+        // Ignored without warping at all
+        let plain = |p: &GenerationParams| GenerationParams {
+            enable_warping: false,
+            ..p.clone()
+        };
+        assert_eq!(base_grid(&plain(&first)), base_grid(&plain(&second)));
+    }
+
+    #[test]
+    fn extra_erosion_steps_change_the_terrain() {
+        let params = GenerationParams {
+            width: 33,
+            ..GenerationParams::default()
+        };
+        let more = GenerationParams {
+            extra_erosion: vec![ErosionPass::default(); 2],
+            ..params.clone()
+        };
+        let (plain, _) = build_heightmap(&params, |_, _| {});
+        let (eroded, _) = build_heightmap(&more, |_, _| {});
+        assert_ne!(plain, eroded);
+
+        // Steps survive a settings round trip
+        let text = toml::to_string(&Settings {
+            params: more.clone(),
+            palette: Default::default(),
+        })
+        .unwrap();
+        let back: Settings = toml::from_str(&text).unwrap();
+        assert_eq!(back.params.extra_erosion, more.extra_erosion);
+    }
+
+    #[test]
+    fn pipeline_returns_grid_and_matching_image() {
+        let params = GenerationParams {
+            width: 33,
+            height: Some(17),
+            noise_type: NoiseType::Perlin2D,
+            ..GenerationParams::default()
+        };
+        let (grid, image) = run_pipeline(&params);
+        assert_eq!(grid.len(), 17);
+        assert!(grid.iter().all(|row| row.len() == 33));
+        assert!(grid.iter().flatten().all(|h| (0.0..=1.0).contains(h)));
+        assert_eq!(image.len(), 33 * 17 * 3);
+        // Same parameters, same terrain
+        assert_eq!(run_pipeline(&params).0, grid);
+    }
+
+    #[test]
+    fn settings_profile_without_optional_fields_loads() {
+        // A profile saved before height, tileable, layers and palette existed
+        let text = r#"
+            noise_type = "Simplex2D"
+            size = 65
+            seed = 9
+            roughness = 1.0
+            frequency = 2.0
+            persistence = 0.5
+            octaves = 3
+            enable_erosion = false
+            erosion_iters = 5
+            talus_angle = 1.0
+            enable_warping = false
+            warp_strength = 0.5
+            warp_iterations = 1
+            enable_terrace = false
+            terrace_steps = 8
+            terrace_smoothing = 0.0
+        "#;
+        let settings: Settings = toml::from_str(text).unwrap();
+        assert_eq!(settings.params.dims(), (65, 65));
+        assert_eq!(settings.params.noise_type, NoiseType::Simplex2D);
+        assert!(settings.params.layers.is_empty());
+    }
+
+    #[test]
+    fn validate_rejects_what_would_panic() {
+        assert!(GenerationParams::default().validate().is_ok());
+        let fractal_100 = GenerationParams {
+            width: 100,
+            ..GenerationParams::default()
+        };
+        assert!(fractal_100.validate().is_err());
+        let no_octaves = GenerationParams {
+            noise_type: NoiseType::Perlin2D,
+            width: 100,
+            octaves: 0,
+            ..GenerationParams::default()
+        };
+        assert!(no_octaves.validate().is_err());
+        let fractal_2 = GenerationParams {
+            width: 2,
+            ..GenerationParams::default()
+        };
+        assert!(fractal_2.validate().is_err());
+        let fractal_layer = GenerationParams {
+            noise_type: NoiseType::Perlin2D,
+            width: 100,
+            layers: vec![NoiseLayer {
+                noise_type: NoiseType::Fractal2D,
+                ..NoiseLayer::default()
+            }],
+            ..GenerationParams::default()
+        };
+        assert!(fractal_layer.validate().is_err());
+    }
+}
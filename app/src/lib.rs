@@ -0,0 +1,2 @@
+// Generation pipeline of the app, usable without the GUI
+pub mod core_pipeline;
@@ -1,39 +1,75 @@
-use std::time::Instant;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use app::core_pipeline::{
+    ErosionPass, GenerationParams, NoiseLayer, NoiseType, NormalizationMode, Settings,
+    build_heightmap, terrain_doc,
+};
 use core::{
-    Fractal2D, NoiseGenerator, Perlin2D, Simplex2D, ThermalErosion2D,
-    domain_warp::DomainWarp2D,
-    utils::{flatten2, normalize2, to_terrain_image},
+    domain_warp::DEFAULT_WARP_SCALE,
+    export::{MTL_FILE_NAME, TEXTURE_FILE_NAME, to_exr, to_obj_with_mtl, to_stl},
+    marching_squares::{Polyline, extract_contours},
+    utils::{
+        BlendMode, HeightMap2D, TerrainPalette, WATER_THRESHOLD, flatten2, from_png_heightmap,
+        sample_bilinear, to_ambient_occlusion, to_terrain_image_with_palette,
+    },
 };
 use eframe::{App, Frame, NativeOptions, egui, run_native};
 use egui::{ColorImage, TextureHandle};
-use storage::Storage2D;
-use storage::models::{TerrainDoc2D, TerrainParams};
+use image::ImageDecoder;
+use storage::models::{TerrainSummary, validate_terrain_name};
+use storage::{ClientOptionsExt, Storage2D};
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+
+mod config;
+use config::{DbConfig, Preferences};
 
 const SPACE_LABEL: f32 = 5.0; // space between label and control
 const SPACE_WIDGET: f32 = 8.0; // space between controls
 const SPACE_RIGHT: f32 = 16.0; // space from the right edge
 const MIN_EXP: u32 = 6;
 const MAX_EXP: u32 = 9;
+const MAX_HISTORY: usize = 10; // generations kept for undo/redo
+const PREVIEW_CELLS: usize = 128; // max mesh resolution per side in the 3D preview
+const PREVIEW_HEIGHT_SCALE: f32 = 0.3; // vertical exaggeration relative to map width
+const PREVIEW_YAW: f32 = std::f32::consts::FRAC_PI_4; // initial 3D preview camera
+const PREVIEW_PITCH: f32 = 0.6;
+const MAX_LAYERS: usize = 4; // base layer plus up to three blended layers
+const MAX_EROSION_STEPS: usize = 3; // main erosion plus up to two further steps
+const SECTION_SAMPLES: usize = 256; // points along a cross-section profile
+const SECTION_PLOT_HEIGHT: f32 = 120.0;
+const MIN_ZOOM: f32 = 0.25;
+const AO_DISTANCE: usize = 16; // cells marched along each AO ray
+const CONTOUR_COLOR: [u8; 3] = [30, 30, 30]; // thin dark contour lines
+const BAND_NAMES: [&str; 5] = ["Water", "Sand", "Grass", "Rock", "Snow"]; // by palette band
+const LIST_POLL_INTERVAL: Duration = Duration::from_secs(30); // DB list refresh in the background
+const MAX_ZOOM: f32 = 8.0;
+const LOAD_TABLE_HEIGHT: f32 = 160.0; // rows beyond this scroll
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
-pub enum NoiseType {
-    Fractal2D,
-    Perlin2D,
-    Simplex2D,
-}
-impl Default for NoiseType {
-    fn default() -> Self {
-        NoiseType::Fractal2D
-    }
+// What the worker thread hands back to the UI
+struct GenerationResult {
+    params: GenerationParams,
+    grid: HeightMap2D,
+    seed: u64,
+    duration_ms: f32,
+    erosion_note: String,
 }
+
 struct TerrainApp {
     // parameters
     noise_type: NoiseType,
-    // slider is for n; size = 2^n + 1
+    // slider is for n; width = 2^n + 1
     exp: u32,
+    // height follows width unless unchecked, then it's 2^height_exp + 1
+    square: bool,
+    height_exp: u32,
     seed: u64,
     roughness: f64,
+    tileable: bool,
     erosion_iters: u32,
     frequency: f64,
     persistence: f64,
@@ -42,14 +78,83 @@ struct TerrainApp {
     // erosion parameters
     enable_erosion: bool,
     talus_angle: f64,
+    erosion_blend: f32,
+    extra_erosion: Vec<ErosionPass>, // further steps run after the main erosion
 
     // domain warping parameters
     enable_warping: bool,
     warp_strength: f64,
+    warp_scale: f64,
+    warp_iterations: usize,
+    second_order_warp: bool,
+
+    // terrace filter parameters
+    enable_terrace: bool,
+    terrace_steps: u32,
+    terrace_smoothing: f32,
+    normalization: NormalizationMode,
+
+    // layer stack, the sliders above are the base layer
+    layers: Vec<NoiseLayer>,
 
     // generated texture
     terrain_texture: Option<TextureHandle>,
 
+    // background generation
+    // One runtime for the whole app, created once instead of per click.
+    // block_on() from the UI is fine for the short DB calls: egui runs on the
+    // main thread, which is never inside a runtime, so nothing nests. Anything
+    // long (generation, batches) goes through spawn / spawn_blocking so the
+    // frame loop keeps running
+    runtime: Runtime,                // shared by generation and all DB calls
+    storage: Option<Arc<Storage2D>>, // connected on first use, see storage()
+    db_config: DbConfig,             // where storage() connects to
+    generation_job: Option<JoinHandle<()>>,
+    generation_result: Arc<Mutex<Option<GenerationResult>>>, // filled in by the worker
+    generation_progress: Arc<Mutex<f32>>,                    // erosion progress in [0,1]
+
+    // batch generation
+    batch_start_seed: u64,
+    batch_end_seed: u64, // inclusive
+    batch_name_prefix: String,
+    batch_job: Option<JoinHandle<Result<usize, String>>>, // number of terrains saved
+    // Re-fetches the name list so terrains saved by other processes show up.
+    // The task only writes into `polled_list`, update() swaps it into `load_list`
+    list_poller: Option<JoinHandle<()>>,
+    polled_list: Arc<Mutex<Option<Vec<TerrainSummary>>>>,
+    batch_saved: Arc<Mutex<usize>>,
+
+    // terrain colors, edited in the "Color Palette" section
+    palette: TerrainPalette,
+    // Display only: replaces the palette's water threshold, never saved with a terrain
+    water_level: f32,
+    // Ambient occlusion shading over the terrain image
+    ao_overlay: bool,
+    ao_rays: usize,
+    last_ao: Option<Vec<Vec<f32>>>, // AO of last_grid, kept until the grid or ray count changes
+    // Contour lines drawn over the terrain, display only unless save_with_contours
+    contours: bool,
+    contour_count: usize,
+    save_with_contours: bool,
+    last_contours: Option<Vec<Polyline>>, // polylines of last_grid, in cell coordinates
+
+    // 2D view: scroll to zoom, middle or Alt + drag to pan
+    zoom: f32,
+    pan_offset: egui::Vec2,
+
+    // cross-section mode: click two points on the image, drag to move them
+    cross_section: bool,
+    section_start: Option<egui::Pos2>, // first click, waiting for the second
+    section_line: Option<[egui::Pos2; 2]>, // endpoints in grid cells
+
+    // 3D preview camera, radians, changed by dragging the preview
+    preview_yaw: f32,
+    preview_pitch: f32,
+
+    // undo/redo, oldest first; history_pos is the entry currently shown
+    history: VecDeque<(GenerationParams, HeightMap2D)>,
+    history_pos: usize,
+
     // timing & status
     last_duration: Option<f32>,
     status_message: String,
@@ -57,25 +162,87 @@ struct TerrainApp {
     // Store the last RGB buffer
     last_flat: Option<Vec<u8>>,
     // Stores last size of the generated terrain
-    last_size: usize,
+    last_width: usize,
+    last_height: usize,
     // Last generated grid
     last_grid: Option<core::utils::HeightMap2D>,
 
     // Save name for terrain in DB
     save_name: String,
-    load_list: Vec<String>,
+    // Comma separated tags stored with the terrain
+    save_tags: String,
+    load_list: Vec<TerrainSummary>,
+    // Case-insensitive name filter for the load list
+    search_text: String,
+    // None shows every noise type
+    noise_filter: Option<NoiseType>,
+    // Names matching `noise_filter`, as returned by the DB
+    noise_filter_names: Option<Vec<String>>,
+    // Names shown in the load table
+    filtered_list: Vec<String>,
+    // Settings dialog
+    show_settings: bool,
+    settings_uri: String, // URI being edited, applied with the Apply button
+    // URI picked in the dialog, written to the preferences file on exit
+    saved_mongo_uri: Option<String>,
+    window_size: Option<[f32; 2]>, // last known inner size, saved on exit
+    // "(x, y, h) band" of the cell under the cursor, "—" off the map
+    hover_info: String,
+    // Waiting for "Are you sure?" before deleting `selected_name`
+    confirm_delete: bool,
+    // DB name of the terrain on screen, None if it was generated or imported
+    displayed_name: Option<String>,
     selected_name: Option<String>,
 }
 
 impl Default for TerrainApp {
     fn default() -> Self {
-        let mut app = Self {
+        Self {
             exp: 7, // 2^7 + 1 = 129
-            last_size: 129,
+            square: true,
+            height_exp: 7,
+            last_width: 129,
+            last_height: 129,
             seed: 2025,
             roughness: 1.0,
+            tileable: false,
             erosion_iters: 5,
             terrain_texture: None,
+            runtime: tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(2)
+                .enable_all()
+                .build()
+                .expect("failed to start tokio runtime"),
+            storage: None,
+            db_config: DbConfig::resolve(None),
+            generation_job: None,
+            generation_result: Arc::new(Mutex::new(None)),
+            generation_progress: Arc::new(Mutex::new(0.0)),
+            batch_start_seed: 0,
+            batch_end_seed: 99,
+            batch_name_prefix: "batch_".to_string(),
+            batch_job: None,
+            list_poller: None,
+            polled_list: Arc::new(Mutex::new(None)),
+            batch_saved: Arc::new(Mutex::new(0)),
+            palette: TerrainPalette::default(),
+            water_level: WATER_THRESHOLD,
+            ao_overlay: false,
+            ao_rays: 8,
+            last_ao: None,
+            contours: false,
+            contour_count: 10,
+            save_with_contours: false,
+            last_contours: None,
+            zoom: 1.0,
+            cross_section: false,
+            section_start: None,
+            section_line: None,
+            pan_offset: egui::Vec2::ZERO,
+            preview_yaw: PREVIEW_YAW,
+            preview_pitch: PREVIEW_PITCH,
+            history: VecDeque::new(),
+            history_pos: 0,
             last_duration: None,
             status_message: String::new(),
             last_flat: None,
@@ -85,35 +252,515 @@ impl Default for TerrainApp {
             octaves: 4,
             enable_erosion: true,
             talus_angle: 1.0,
+            erosion_blend: 1.0,
+            extra_erosion: Vec::new(),
             enable_warping: false,
             warp_strength: 0.5,
+            warp_scale: DEFAULT_WARP_SCALE,
+            warp_iterations: 1,
+            second_order_warp: false,
+            enable_terrace: false,
+            terrace_steps: 8,
+            terrace_smoothing: 0.0,
+            normalization: NormalizationMode::Stretch,
+            layers: Vec::new(),
             save_name: String::new(),
+            save_tags: String::new(),
             load_list: vec![],
+            search_text: String::new(),
+            noise_filter: None,
+            noise_filter_names: None,
+            filtered_list: vec![],
+            show_settings: false,
+            settings_uri: String::new(),
+            saved_mongo_uri: None,
+            window_size: None,
+            hover_info: "—".into(),
+            confirm_delete: false,
+            displayed_name: None,
             selected_name: None,
             last_grid: None,
-        };
-        // On startup, load the DB names
-        app.refresh_name_list();
-        app
+        }
     }
 }
 
 impl TerrainApp {
-    // Helper to block-on list_names() and update `self.load_list` + status.
+    // Default app with the saved preferences applied, then the DB names loaded
+    fn new(prefs: &Preferences) -> Self {
+        let mut app = Self::default();
+        app.db_config = DbConfig::resolve(prefs.mongo_uri.as_deref());
+        app.saved_mongo_uri = prefs.mongo_uri.clone();
+        app.seed = prefs.seed.unwrap_or(app.seed);
+        app.noise_type = prefs.noise_type.unwrap_or(app.noise_type);
+        app.refresh_name_list();
+        app
+    }
+
+    // What to write back to the preferences file
+    fn preferences(&self) -> Preferences {
+        Preferences {
+            window_size: self.window_size,
+            seed: Some(self.seed),
+            noise_type: Some(self.noise_type),
+            mongo_uri: self.saved_mongo_uri.clone(),
+        }
+    }
+
+    // Point the app at another MongoDB without restarting. The old connection
+    // and its list poller are dropped, the next DB action connects to `uri`
+    fn set_mongo_uri(&mut self, uri: String) {
+        if let Some(poller) = self.list_poller.take() {
+            poller.abort();
+        }
+        self.storage = None;
+        self.db_config.uri = uri.clone();
+        self.saved_mongo_uri = Some(uri);
+        self.load_list.clear();
+        self.noise_filter_names = None;
+        self.update_filtered_list();
+        self.selected_name = None;
+        self.refresh_name_list();
+    }
+
+    // Shared DB handle, connected once and reused so its connection pool is too.
+    // A failed connect isn't cached, the next DB action tries again
+    fn storage(&mut self) -> Result<Arc<Storage2D>, String> {
+        if let Some(storage) = &self.storage {
+            return Ok(storage.clone());
+        }
+        let storage = self
+            .runtime
+            .block_on(Storage2D::init_with_options(
+                &self.db_config.uri,
+                &self.db_config.db_name,
+                &self.db_config.collection,
+                Some(ClientOptionsExt::default()),
+            ))
+            .map_err(|e| e.to_string())?;
+        let storage = Arc::new(storage);
+        self.storage = Some(storage.clone());
+        Ok(storage)
+    }
+
+    // Terrain size picked with the resolution sliders
+    fn dims(&self) -> (usize, usize) {
+        let width = (1 << self.exp) + 1;
+        let height = if self.square {
+            width
+        } else {
+            (1 << self.height_exp) + 1
+        };
+        (width, height)
+    }
+
+    // Capture the current slider values for a generation run
+    fn generation_params(&self, width: usize, height: usize) -> GenerationParams {
+        GenerationParams {
+            noise_type: self.noise_type,
+            width,
+            height: Some(height),
+            seed: self.seed,
+            roughness: self.roughness,
+            tileable: self.tileable,
+            frequency: self.frequency,
+            persistence: self.persistence,
+            octaves: self.octaves,
+            enable_erosion: self.enable_erosion,
+            erosion_iters: self.erosion_iters,
+            talus_angle: self.talus_angle,
+            erosion_blend: self.erosion_blend,
+            extra_erosion: self.extra_erosion.clone(),
+            enable_warping: self.enable_warping,
+            warp_strength: self.warp_strength,
+            warp_scale: self.warp_scale,
+            warp_iterations: self.warp_iterations,
+            second_order_warp: self.second_order_warp,
+            enable_terrace: self.enable_terrace,
+            terrace_steps: self.terrace_steps,
+            terrace_smoothing: self.terrace_smoothing,
+            normalization: self.normalization,
+            layers: self.layers.clone(),
+        }
+    }
+
+    // Generate & measure on a blocking task so the UI stays responsive
+    fn start_generation(&mut self, ctx: &egui::Context, width: usize, height: usize) {
+        let params = self.generation_params(width, height);
+        let progress = Arc::new(Mutex::new(0.0f32));
+        self.generation_progress = progress.clone();
+        let slot = self.generation_result.clone();
+        let repaint = ctx.clone();
+        self.generation_job = Some(self.runtime.spawn_blocking(move || {
+            let result = generate_terrain(&params, &progress, &repaint);
+            *slot.lock().unwrap() = Some(result);
+            repaint.request_repaint();
+        }));
+        self.status_message = "Generating...".into();
+    }
+
+    // Pick up a finished background task and display its terrain
+    fn poll_generation(&mut self, ctx: &egui::Context) {
+        let finished = self.generation_result.lock().unwrap().take();
+        let Some(result) = finished else {
+            // The task only ends without posting a result if it panicked
+            if self
+                .generation_job
+                .as_ref()
+                .is_some_and(|job| job.is_finished())
+            {
+                self.generation_job = None;
+                self.status_message = "Generation failed".into();
+            }
+            return;
+        };
+        self.generation_job = None;
+
+        self.push_history(result.params, result.grid.clone());
+        self.show_grid(ctx, result.grid);
+        self.last_duration = Some(result.duration_ms);
+        self.status_message = format!(
+            "Generated in {:.2} ms (seed {}){}",
+            result.duration_ms, result.seed, result.erosion_note
+        );
+    }
+
+    // Report a finished batch run
+    fn poll_batch(&mut self) {
+        if !self.batch_job.as_ref().is_some_and(|job| job.is_finished()) {
+            return;
+        }
+        let Some(job) = self.batch_job.take() else {
+            return;
+        };
+        self.status_message = match self.runtime.block_on(job) {
+            Ok(Ok(saved)) => format!("Batch complete: {} terrains saved", saved),
+            Ok(Err(e)) => format!("Batch stopped: {}", e),
+            Err(_) => "Batch failed".into(),
+        };
+        self.refresh_name_list();
+    }
+
+    // Delete `selected_name` from the DB. A terrain on screen stays on screen
+    fn delete_selected(&mut self) {
+        let Some(name) = self.selected_name.clone() else {
+            return;
+        };
+        let storage = match self.storage() {
+            Ok(storage) => storage,
+            Err(e) => {
+                self.status_message = format!("DB init error: {}", e);
+                return;
+            }
+        };
+        match self.runtime.block_on(storage.delete_by_name(&name)) {
+            Ok(true) => {
+                self.refresh_name_list();
+                self.selected_name = None;
+                self.status_message = if self.displayed_name.as_deref() == Some(name.as_str()) {
+                    self.displayed_name = None;
+                    "Deleted — displaying last generated terrain".into()
+                } else {
+                    format!("Deleted “{}”", name)
+                };
+            }
+            Ok(false) => self.status_message = "Name not found".into(),
+            Err(e) => self.status_message = format!("Delete error: {}", e),
+        }
+    }
+
+    // Start the background list refresh once the DB is connected
+    fn start_list_poller(&mut self, ctx: &egui::Context) {
+        if self.list_poller.is_some() {
+            return;
+        }
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+        let polled = self.polled_list.clone();
+        let repaint = ctx.clone();
+        self.list_poller = Some(self.runtime.spawn(async move {
+            loop {
+                tokio::time::sleep(LIST_POLL_INTERVAL).await;
+                // A failed poll just keeps the current list until the next one
+                if let Ok(list) = storage.list_summaries().await {
+                    *polled.lock().unwrap() = Some(list);
+                    repaint.request_repaint();
+                }
+            }
+        }));
+    }
+
+    // Swap in the list from the last background poll if any name changed
+    fn poll_name_list(&mut self) {
+        let Some(list) = self.polled_list.lock().unwrap().take() else {
+            return;
+        };
+        let changed = list
+            .iter()
+            .map(|m| &m.name)
+            .ne(self.load_list.iter().map(|m| &m.name));
+        if changed {
+            self.replace_load_list(list);
+        }
+    }
+
+    // New names from the DB: the noise filter's matches may have changed too,
+    // so query them again before rebuilding the table
+    fn replace_load_list(&mut self, list: Vec<TerrainSummary>) {
+        self.load_list = list;
+        self.apply_noise_filter();
+    }
+
+    // Generate and store every seed in the batch range with the current sliders
+    fn start_batch(&mut self, ctx: &egui::Context, width: usize, height: usize) {
+        let storage = match self.storage() {
+            Ok(storage) => storage,
+            Err(e) => {
+                self.status_message = format!("DB init error: {}", e);
+                return;
+            }
+        };
+        let template = self.generation_params(width, height);
+        let seeds = self.batch_start_seed..=self.batch_end_seed;
+        let prefix = self.batch_name_prefix.clone();
+        let tags = parse_tags(&self.save_tags);
+        let saved = Arc::new(Mutex::new(0));
+        self.batch_saved = saved.clone();
+        let repaint = ctx.clone();
+
+        self.batch_job = Some(self.runtime.spawn(async move {
+            for seed in seeds {
+                let params = GenerationParams {
+                    seed,
+                    ..template.clone()
+                };
+                let ctx = repaint.clone();
+                let (params, grid) = tokio::task::spawn_blocking(move || {
+                    let grid = generate_terrain(&params, &Mutex::new(0.0), &ctx).grid;
+                    (params, grid)
+                })
+                .await
+                .map_err(|e| e.to_string())?;
+
+                let doc = terrain_doc(format!("{}{}", prefix, seed), &params, &grid, tags.clone());
+                storage.retry_create(doc).await.map_err(|e| e.to_string())?;
+                *saved.lock().unwrap() += 1;
+                repaint.request_repaint();
+            }
+            let total = *saved.lock().unwrap();
+            Ok(total)
+        }));
+        self.status_message = "Running batch...".into();
+    }
+
+    // Display a PNG heightmap picked in the file dialog or dropped on the window,
+    // read with `load`
+    fn import_heightmap(
+        &mut self,
+        ctx: &egui::Context,
+        path: &Path,
+        load: fn(&Path) -> Result<HeightMap2D, String>,
+    ) {
+        let name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy();
+        self.status_message = match load(path) {
+            Ok(grid) => {
+                let (width, height) = (grid.first().map_or(0, Vec::len), grid.len());
+                self.show_grid(ctx, grid);
+                format!("Loaded heightmap from {} ({}×{})", name, width, height)
+            }
+            Err(e) => format!("Can't load {}: {}", name, e),
+        };
+    }
+
+    // Make `grid` the displayed terrain
+    fn show_grid(&mut self, ctx: &egui::Context, grid: HeightMap2D) {
+        // Load sets this again right after, anything else shows an unsaved grid
+        self.displayed_name = None;
+        // Computed for the previous grid
+        self.last_ao = None;
+        self.last_contours = None;
+        // Keep size in sync with flat
+        self.last_height = grid.len();
+        self.last_width = grid.first().map_or(0, Vec::len);
+        // Save the last grid
+        self.last_grid = Some(grid);
+        self.recolor(ctx);
+    }
+
+    // Record a new generation, dropping any redo entries past the current one
+    fn push_history(&mut self, params: GenerationParams, grid: HeightMap2D) {
+        if !self.history.is_empty() {
+            self.history.truncate(self.history_pos + 1);
+        }
+        self.history.push_back((params, grid));
+        if self.history.len() > MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history_pos = self.history.len() - 1;
+    }
+
+    // Restore sliders and terrain from history entry `pos`
+    fn restore_history(&mut self, ctx: &egui::Context, pos: usize) {
+        let Some((params, grid)) = self.history.get(pos).cloned() else {
+            return;
+        };
+        self.history_pos = pos;
+        self.apply_params(&params);
+        self.show_grid(ctx, grid);
+        self.status_message = format!("History {}/{}", pos + 1, self.history.len());
+    }
+
+    // Copy a parameter snapshot back into the sliders
+    fn apply_params(&mut self, params: &GenerationParams) {
+        self.noise_type = params.noise_type;
+        let (width, height) = params.dims();
+        self.exp = (width - 1).trailing_zeros().clamp(MIN_EXP, MAX_EXP);
+        self.square = width == height;
+        self.height_exp = (height - 1).trailing_zeros().clamp(MIN_EXP, MAX_EXP);
+        self.seed = params.seed;
+        self.roughness = params.roughness;
+        self.tileable = params.tileable;
+        self.frequency = params.frequency;
+        self.persistence = params.persistence;
+        self.octaves = params.octaves;
+        self.enable_erosion = params.enable_erosion;
+        self.erosion_iters = params.erosion_iters;
+        self.talus_angle = params.talus_angle;
+        self.erosion_blend = params.erosion_blend;
+        self.extra_erosion = params.extra_erosion.clone();
+        self.enable_warping = params.enable_warping;
+        self.warp_strength = params.warp_strength;
+        self.warp_scale = params.warp_scale;
+        self.warp_iterations = params.warp_iterations;
+        self.second_order_warp = params.second_order_warp;
+        self.enable_terrace = params.enable_terrace;
+        self.terrace_steps = params.terrace_steps;
+        self.terrace_smoothing = params.terrace_smoothing;
+        self.normalization = params.normalization;
+        self.layers = params.layers.clone();
+    }
+
+    // Write the current slider values and palette to a TOML file
+    fn save_settings(&self, path: &Path) -> std::io::Result<()> {
+        let settings = Settings {
+            params: {
+                let (width, height) = self.dims();
+                self.generation_params(width, height)
+            },
+            palette: self.palette.clone(),
+        };
+        let text = toml::to_string_pretty(&settings).map_err(std::io::Error::other)?;
+        std::fs::write(path, text)
+    }
+
+    // Patch the sliders from a TOML file written by save_settings(),
+    // the displayed terrain is left alone until the next Generate
+    fn load_settings(&mut self, ctx: &egui::Context, path: &Path) -> std::io::Result<()> {
+        let text = std::fs::read_to_string(path)?;
+        let settings: Settings = toml::from_str(&text).map_err(std::io::Error::other)?;
+        self.apply_params(&settings.params);
+        self.palette = settings.palette;
+        self.recolor(ctx);
+        Ok(())
+    }
+
+    // Redraw the current terrain with the palette (and AO), without regenerating it
+    fn recolor(&mut self, ctx: &egui::Context) {
+        let Some(grid) = &self.last_grid else {
+            return;
+        };
+        let flat = flatten2(grid);
+        let mut img = to_terrain_image_with_palette(&flat, &self.display_palette());
+        if self.ao_overlay {
+            // Same vertical exaggeration as the 3D preview
+            let z_scale = self.last_width as f32 * PREVIEW_HEIGHT_SCALE;
+            let ao = self.last_ao.get_or_insert_with(|| {
+                to_ambient_occlusion(grid, self.ao_rays, AO_DISTANCE, z_scale)
+            });
+            // Darken occluded cells, but never below 20% so valleys stay readable
+            for (px, &a) in img.chunks_exact_mut(3).zip(ao.iter().flatten()) {
+                for c in px {
+                    *c = (*c as f32 * (a * 0.8 + 0.2)) as u8;
+                }
+            }
+        }
+        let color_image = ColorImage::from_rgb([self.last_width, self.last_height], &img);
+        self.last_flat = Some(img);
+        self.terrain_texture =
+            Some(ctx.load_texture("terrain", color_image, egui::TextureOptions::NEAREST));
+    }
+
+    // The palette with its water band ending at `water_level`. Higher bands
+    // are pushed up when the water rises past them, so thresholds stay ordered
+    fn display_palette(&self) -> TerrainPalette {
+        let mut palette = self.palette.clone();
+        palette.thresholds[0] = self.water_level;
+        for t in &mut palette.thresholds[1..] {
+            *t = t.max(self.water_level);
+        }
+        palette
+    }
+
+    // Share of the displayed terrain below `water_level`, in percent
+    fn water_coverage(&self) -> Option<f32> {
+        let grid = self.last_grid.as_ref()?;
+        let total = grid.iter().map(Vec::len).sum::<usize>();
+        if total == 0 {
+            return None;
+        }
+        let wet = grid
+            .iter()
+            .flatten()
+            .filter(|&&h| h < self.water_level)
+            .count();
+        Some(wet as f32 / total as f32 * 100.0)
+    }
+
+    // Query the DB for the names matching the noise type filter
+    fn apply_noise_filter(&mut self) {
+        self.noise_filter_names = None;
+        if let Some(noise_type) = self.noise_filter {
+            let noise_type = format!("{:?}", noise_type).to_lowercase();
+            match self.storage().and_then(|storage| {
+                self.runtime
+                    .block_on(storage.find_by_noise_type(&noise_type))
+                    .map_err(|e| e.to_string())
+            }) {
+                Ok(names) => self.noise_filter_names = Some(names),
+                Err(e) => self.status_message = format!("Filter error: {}", e),
+            }
+        }
+        self.update_filtered_list();
+    }
+
+    // Rebuild `filtered_list` from `load_list`, the search text and the noise filter
+    fn update_filtered_list(&mut self) {
+        let search = self.search_text.to_lowercase();
+        self.filtered_list = self
+            .load_list
+            .iter()
+            .map(|meta| &meta.name)
+            .filter(|name| name.to_lowercase().contains(&search))
+            .filter(|name| {
+                self.noise_filter_names
+                    .as_ref()
+                    .is_none_or(|names| names.contains(name))
+            })
+            .cloned()
+            .collect();
+    }
+
+    // Helper to block-on list_summaries() and update `self.load_list` + status.
     fn refresh_name_list(&mut self) {
-        let rt = tokio::runtime::Builder::new_current_thread()
-            .enable_all()
-            .build()
-            .unwrap();
-        match rt.block_on(Storage2D::init(
-            "mongodb://localhost:27017",
-            "terrain_db",
-            "terrain2d",
-        )) {
-            Ok(storage) => match rt.block_on(storage.list_names()) {
-                Ok(names) => {
-                    self.load_list = names;
+        match self.storage() {
+            Ok(storage) => match self.runtime.block_on(storage.list_summaries()) {
+                Ok(list) => {
                     self.status_message = "Loaded name list".to_owned();
+                    // After the message, so a filter error isn't overwritten
+                    self.replace_load_list(list);
                 }
                 Err(e) => {
                     self.status_message = format!("List error: {}", e);
@@ -126,10 +773,51 @@ impl TerrainApp {
     }
 }
 
+// Stop the list poller when the window closes, the runtime goes right after
+impl Drop for TerrainApp {
+    fn drop(&mut self) {
+        if let Some(poller) = self.list_poller.take() {
+            poller.abort();
+        }
+    }
+}
+
 impl App for TerrainApp {
+    // Keep the window size, seed and noise type for next time
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        let _ = self.preferences().save();
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
+        self.poll_generation(ctx);
+        self.poll_batch();
+        self.start_list_poller(ctx);
+        self.poll_name_list();
+        if let Some(rect) = ctx.input(|i| i.viewport().inner_rect) {
+            self.window_size = Some([rect.width(), rect.height()]);
+        }
+
+        // Heightmaps dropped onto the window
+        let dropped: Vec<_> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|f| f.path.clone())
+                .collect()
+        });
+        for path in dropped {
+            if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+            {
+                self.import_heightmap(ctx, &path, load_dropped_heightmap);
+            } else {
+                self.status_message = format!("Not a PNG: {}", path.display());
+            }
+        }
+
         // compute real size
-        let size = (1 << self.exp) + 1;
+        let (width, height) = self.dims();
         let total_width = ctx.available_rect().width();
         let panel_width = total_width * 0.4;
 
@@ -149,27 +837,50 @@ impl App for TerrainApp {
                             // Seed
                             ui.label("Seed");
                             ui.add_space(SPACE_LABEL);
-                            ui.add(egui::DragValue::new(&mut self.seed).speed(1.0));
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut self.seed).speed(1.0));
+                                let busy = self.generation_job.is_some();
+                                if ui
+                                    .add_enabled(!busy, egui::Button::new("🎲").small())
+                                    .on_hover_text("Random seed, and generate")
+                                    .clicked()
+                                {
+                                    self.seed = random_seed();
+                                    self.start_generation(ctx, width, height);
+                                }
+                                if ui.small_button("📋").on_hover_text("Copy seed").clicked() {
+                                    ctx.copy_text(self.seed.to_string());
+                                    self.status_message = format!("Copied seed {}", self.seed);
+                                }
+                            });
                             ui.add_space(SPACE_WIDGET);
 
                             // Resolution slider
-                            let prev_size = (1 << self.exp) + 1;
-                            // ui.horizontal(|ui| {
-                            ui.label("Resolution (2^n+1):");
+                            ui.horizontal(|ui| {
+                                ui.label("Resolution (2^n+1):");
+                                ui.checkbox(&mut self.square, "Square");
+                            });
                             ui.add_space(SPACE_LABEL);
                             // Stretch slider across entire panel width
                             ui.add_sized(
                                 [ui.available_width(), 0.0],
                                 egui::Slider::new(&mut self.exp, MIN_EXP..=MAX_EXP)
-                                    .text(format!("{}×{}", size, size))
+                                    .text(format!("{}×{}", width, height))
                                     .step_by(1.0),
                             );
-                            if prev_size != size {
+                            if !self.square {
+                                ui.add_sized(
+                                    [ui.available_width(), 0.0],
+                                    egui::Slider::new(&mut self.height_exp, MIN_EXP..=MAX_EXP)
+                                        .text("height")
+                                        .step_by(1.0),
+                                );
+                            }
+                            if self.dims() != (width, height) {
                                 self.terrain_texture = None; // reset texture on size change
                                 self.last_flat = None;
                                 self.status_message = "Texture reset due to size change".into();
                             }
-                            // });
                             ui.add_space(SPACE_WIDGET);
 
                             // Noise type selector
@@ -202,6 +913,7 @@ impl App for TerrainApp {
                                     ui.label("Roughness");
                                     ui.add_space(SPACE_LABEL);
                                     ui.add(egui::Slider::new(&mut self.roughness, 1.0..=5.0));
+                                    ui.checkbox(&mut self.tileable, "Tileable");
                                 }
                                 _ => {
                                     ui.label("Frequency");
@@ -241,6 +953,15 @@ impl App for TerrainApp {
                                         egui::Slider::new(&mut self.warp_strength, 0.0..=1.0)
                                             .text("Warp Strength"),
                                     );
+                                    ui.add(
+                                        egui::Slider::new(&mut self.warp_scale, 0.5..=10.0)
+                                            .text("Warp Scale"),
+                                    );
+                                    ui.add(
+                                        egui::Slider::new(&mut self.warp_iterations, 1..=3)
+                                            .text("Warp Passes"),
+                                    );
+                                    ui.checkbox(&mut self.second_order_warp, "2nd Order Warp");
                                 }
                             }
                         });
@@ -263,169 +984,519 @@ impl App for TerrainApp {
                                     ui.add(egui::Slider::new(&mut self.erosion_iters, 0..=50));
                                     ui.label("Talus Angle");
                                     ui.add(egui::Slider::new(&mut self.talus_angle, 0.1..=5.0));
+                                    ui.label("Blend with Original");
+                                    ui.add(
+                                        egui::Slider::new(&mut self.erosion_blend, 0.0..=1.0)
+                                            .custom_formatter(|v, _| {
+                                                format!("{:.0}% eroded", v * 100.0)
+                                            }),
+                                    );
+                                    // Further thermal steps, run in order after this one
+                                    for (i, pass) in self.extra_erosion.iter_mut().enumerate() {
+                                        ui.label(format!("Step {}", i + 2));
+                                        ui.add(
+                                            egui::Slider::new(&mut pass.iterations, 0..=50)
+                                                .text("Iterations"),
+                                        );
+                                        ui.add(
+                                            egui::Slider::new(&mut pass.talus_angle, 0.1..=5.0)
+                                                .text("Talus"),
+                                        );
+                                    }
+                                    ui.horizontal(|ui| {
+                                        if ui
+                                            .add_enabled(
+                                                self.extra_erosion.len() + 1 < MAX_EROSION_STEPS,
+                                                egui::Button::new("+Step"),
+                                            )
+                                            .clicked()
+                                        {
+                                            self.extra_erosion.push(ErosionPass::default());
+                                        }
+                                        if ui
+                                            .add_enabled(
+                                                !self.extra_erosion.is_empty(),
+                                                egui::Button::new("−Step"),
+                                            )
+                                            .clicked()
+                                        {
+                                            self.extra_erosion.pop();
+                                        }
+                                    });
                                 }
                             }
                         });
 
-                    ui.separator();
-
-                    // Generate & measure
-                    if ui.button("Generate Terrain").clicked() {
-                        let start = Instant::now();
+                    // Post-processing filters
+                    egui::CollapsingHeader::new("Advanced Filters")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            ui.label("Normalization");
+                            egui::ComboBox::from_id_salt("normalization_combo")
+                                .selected_text(self.normalization.label())
+                                .show_ui(ui, |ui| {
+                                    for mode in NormalizationMode::ALL {
+                                        let selected = std::mem::discriminant(&mode)
+                                            == std::mem::discriminant(&self.normalization);
+                                        if ui.selectable_label(selected, mode.label()).clicked()
+                                            && !selected
+                                        {
+                                            self.normalization = mode;
+                                        }
+                                    }
+                                });
+                            match &mut self.normalization {
+                                NormalizationMode::Stretch => {}
+                                NormalizationMode::Clamped { low, high } => {
+                                    ui.add(egui::Slider::new(low, -2.0..=2.0).text("Low"));
+                                    ui.add(egui::Slider::new(high, -2.0..=2.0).text("High"));
+                                }
+                                NormalizationMode::FixedSeaLevel { sea_level } => {
+                                    ui.add(
+                                        egui::Slider::new(sea_level, -1.0..=1.0)
+                                            .text("Sea Level Height"),
+                                    );
+                                }
+                            }
+                            ui.add_space(SPACE_WIDGET);
 
-                        // Base Generator
-                        let mut fractal_base = Fractal2D::new(size, self.seed, self.roughness);
-                        let mut grid = match self.noise_type {
-                            NoiseType::Fractal2D => {
-                                let base = {
-                                    let _ = fractal_base.generate(); // fill internal map
-                                    &fractal_base
-                                };
+                            ui.checkbox(&mut self.enable_terrace, "Apply Terracing");
+                            if self.enable_terrace {
+                                ui.label("Terrace Steps");
+                                ui.add(egui::Slider::new(&mut self.terrace_steps, 2..=32));
+                                ui.label("Smoothing");
+                                ui.add(egui::Slider::new(&mut self.terrace_smoothing, 0.0..=1.0));
+                            }
+                        });
 
-                                if self.enable_warping {
-                                    let mut fractal_warp = Fractal2D::new(
-                                        size,
-                                        self.seed.wrapping_add(42),
-                                        self.roughness,
+                    egui::CollapsingHeader::new("Layer Stack")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            ui.label("Layer 1: base terrain from the sliders above");
+                            let mut moved = None;
+                            for (i, layer) in self.layers.iter_mut().enumerate() {
+                                let frame = egui::Frame::default().inner_margin(4.0);
+                                let (_, dropped) = ui.dnd_drop_zone::<usize, ()>(frame, |ui| {
+                                    ui.horizontal(|ui| {
+                                        // Drag the handle onto another layer to reorder
+                                        ui.dnd_drag_source(
+                                            egui::Id::new(("noise_layer", i)),
+                                            i,
+                                            |ui| {
+                                                ui.label(format!("☰ Layer {}", i + 2));
+                                            },
+                                        );
+                                        egui::ComboBox::from_id_salt(("layer_noise", i))
+                                            .selected_text(format!("{:?}", layer.noise_type))
+                                            .show_ui(ui, |ui| {
+                                                for t in [
+                                                    NoiseType::Fractal2D,
+                                                    NoiseType::Perlin2D,
+                                                    NoiseType::Simplex2D,
+                                                ] {
+                                                    ui.selectable_value(
+                                                        &mut layer.noise_type,
+                                                        t,
+                                                        format!("{:?}", t),
+                                                    );
+                                                }
+                                            });
+                                        egui::ComboBox::from_id_salt(("layer_blend", i))
+                                            .selected_text(format!("{:?}", layer.blend_mode))
+                                            .show_ui(ui, |ui| {
+                                                for mode in [
+                                                    BlendMode::Add,
+                                                    BlendMode::Multiply,
+                                                    BlendMode::Screen,
+                                                    BlendMode::Max,
+                                                ] {
+                                                    ui.selectable_value(
+                                                        &mut layer.blend_mode,
+                                                        mode,
+                                                        format!("{:?}", mode),
+                                                    );
+                                                }
+                                            });
+                                    });
+                                    ui.add(
+                                        egui::Slider::new(&mut layer.weight, 0.0..=1.0)
+                                            .text("Weight"),
                                     );
-                                    let _ = fractal_warp.generate();
-                                    DomainWarp2D {
-                                        base,
-                                        warp: &fractal_warp,
-                                        size,
-                                        warp_strength: self.warp_strength,
+                                    if layer.noise_type != NoiseType::Fractal2D {
+                                        ui.add(
+                                            egui::Slider::new(&mut layer.frequency, 0.1..=32.0)
+                                                .text("Frequency"),
+                                        );
+                                        ui.add(
+                                            egui::Slider::new(&mut layer.persistence, 0.1..=1.0)
+                                                .text("Persistence"),
+                                        );
+                                        ui.add(
+                                            egui::Slider::new(&mut layer.octaves, 1..=8)
+                                                .text("Octaves"),
+                                        );
                                     }
-                                    .generate()
-                                } else {
-                                    let mut g = vec![vec![0.0; size]; size];
-                                    for y in 0..size {
-                                        for x in 0..size {
-                                            let fx = x as f64 / size as f64;
-                                            let fy = y as f64 / size as f64;
-                                            g[y][x] = base.get2(fx, fy) as f32;
-                                        }
+                                });
+                                if let Some(from) = dropped {
+                                    moved = Some((*from, i));
+                                }
+                            }
+                            if let Some((from, to)) = moved
+                                && from < self.layers.len()
+                            {
+                                let layer = self.layers.remove(from);
+                                self.layers.insert(to, layer);
+                            }
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .add_enabled(
+                                        self.layers.len() + 1 < MAX_LAYERS,
+                                        egui::Button::new("+Layer"),
+                                    )
+                                    .clicked()
+                                {
+                                    self.layers.push(NoiseLayer::default());
+                                }
+                                if ui
+                                    .add_enabled(
+                                        !self.layers.is_empty(),
+                                        egui::Button::new("−Layer"),
+                                    )
+                                    .clicked()
+                                {
+                                    self.layers.pop();
+                                }
+                            });
+                        });
+
+                    // Recolors the current terrain, nothing is regenerated
+                    ui.label("Water Level");
+                    ui.add_space(SPACE_LABEL);
+                    if ui
+                        .add(egui::Slider::new(&mut self.water_level, 0.0..=0.6))
+                        .changed()
+                    {
+                        self.recolor(ctx);
+                        if let Some(pct) = self.water_coverage() {
+                            self.status_message = format!("{:.1}% water coverage", pct);
+                        }
+                    }
+                    ui.add_space(SPACE_WIDGET);
+
+                    egui::CollapsingHeader::new("Ambient Occlusion")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            let mut changed =
+                                ui.checkbox(&mut self.ao_overlay, "AO Overlay").changed();
+                            ui.horizontal(|ui| {
+                                ui.label("AO Rays");
+                                ui.add_space(SPACE_LABEL);
+                                for rays in [4, 8, 16] {
+                                    if ui
+                                        .selectable_value(&mut self.ao_rays, rays, rays.to_string())
+                                        .changed()
+                                    {
+                                        self.last_ao = None;
+                                        changed = true;
                                     }
-                                    g
                                 }
+                            });
+                            if changed {
+                                self.recolor(ctx);
                             }
+                        });
 
-                            NoiseType::Perlin2D | NoiseType::Simplex2D => {
-                                let base: Box<dyn NoiseGenerator> = match self.noise_type {
-                                    NoiseType::Perlin2D => Box::new(Perlin2D::new(
-                                        self.seed,
-                                        self.frequency,
-                                        self.persistence,
-                                        self.octaves as usize,
-                                    )),
-                                    NoiseType::Simplex2D => Box::new(Simplex2D::new(
-                                        self.seed,
-                                        self.frequency,
-                                        self.persistence,
-                                        self.octaves as usize,
-                                    )),
-                                    _ => unreachable!(),
-                                };
+                    egui::CollapsingHeader::new("Contours")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            ui.checkbox(&mut self.contours, "Contour Lines");
+                            ui.label("Contour Interval");
+                            ui.add_space(SPACE_LABEL);
+                            if ui
+                                .add(
+                                    egui::Slider::new(&mut self.contour_count, 5..=20)
+                                        .text("lines"),
+                                )
+                                .changed()
+                            {
+                                self.last_contours = None;
+                            }
+                            ui.checkbox(&mut self.save_with_contours, "Save with Contours");
+                        });
 
-                                if self.enable_warping {
-                                    let warp: Box<dyn NoiseGenerator> = match self.noise_type {
-                                        NoiseType::Perlin2D => Box::new(Perlin2D::new(
-                                            self.seed.wrapping_add(42),
-                                            self.frequency,
-                                            self.persistence,
-                                            self.octaves as usize,
-                                        )),
-                                        NoiseType::Simplex2D => Box::new(Simplex2D::new(
-                                            self.seed.wrapping_add(42),
-                                            self.frequency,
-                                            self.persistence,
-                                            self.octaves as usize,
-                                        )),
-                                        _ => unreachable!(),
-                                    };
-
-                                    DomainWarp2D {
-                                        base: base.as_ref(),
-                                        warp: warp.as_ref(),
-                                        size,
-                                        warp_strength: self.warp_strength,
+                    egui::CollapsingHeader::new("Color Palette")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            let mut changed = false;
+                            for (band, name) in BAND_NAMES.iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.label(*name);
+                                    for color in &mut self.palette.colors[band] {
+                                        changed |=
+                                            egui::color_picker::color_edit_button_srgb(ui, color)
+                                                .changed();
                                     }
-                                    .generate()
-                                } else {
-                                    let mut g = vec![vec![0.0; size]; size];
-                                    for y in 0..size {
-                                        for x in 0..size {
-                                            let fx = x as f64 / size as f64;
-                                            let fy = y as f64 / size as f64;
-                                            g[y][x] = base.get2(fx, fy) as f32;
-                                        }
+                                    // Keep thresholds ordered by bounding each by its neighbours.
+                                    // Water ends at the Water Level slider instead
+                                    if band > 0 && band < self.palette.thresholds.len() {
+                                        let lo = if band == 1 {
+                                            self.water_level
+                                        } else {
+                                            self.palette.thresholds[band - 1]
+                                        };
+                                        let hi = self
+                                            .palette
+                                            .thresholds
+                                            .get(band + 1)
+                                            .copied()
+                                            .unwrap_or(1.0);
+                                        changed |= ui
+                                            .add(egui::Slider::new(
+                                                &mut self.palette.thresholds[band],
+                                                lo..=hi,
+                                            ))
+                                            .changed();
                                     }
-                                    g
-                                }
+                                });
                             }
-                        };
+                            if ui.button("Reset to Default").clicked() {
+                                self.palette = TerrainPalette::default();
+                                changed = true;
+                            }
+                            if changed {
+                                self.recolor(ctx);
+                            }
+                        });
 
-                        // Apply thermal erosion
-                        if self.enable_erosion {
-                            ThermalErosion2D::new(
-                                self.erosion_iters as usize,
-                                self.talus_angle as f32,
-                            )
-                            .apply(&mut grid);
+                    egui::CollapsingHeader::new("Batch Generation")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("Seeds");
+                                ui.add(egui::DragValue::new(&mut self.batch_start_seed));
+                                ui.label("to");
+                                ui.add(egui::DragValue::new(&mut self.batch_end_seed));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Name prefix");
+                                ui.text_edit_singleline(&mut self.batch_name_prefix);
+                            });
+                            let running = self.batch_job.is_some();
+                            let total = self
+                                .batch_end_seed
+                                .saturating_sub(self.batch_start_seed)
+                                .saturating_add(1);
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .add_enabled(!running, egui::Button::new("Run Batch"))
+                                    .clicked()
+                                {
+                                    self.start_batch(ctx, width, height);
+                                }
+                                if running {
+                                    ui.add(egui::Spinner::new());
+                                    let saved = *self.batch_saved.lock().unwrap();
+                                    ui.label(format!("{} / {}", saved, total));
+                                }
+                            });
+                        });
+
+                    ui.separator();
+
+                    // Generate button, with progress while the task runs
+                    ui.horizontal(|ui| {
+                        let busy = self.generation_job.is_some();
+                        if ui
+                            .add_enabled(!busy, egui::Button::new("Generate Terrain"))
+                            .clicked()
+                        {
+                            self.start_generation(ctx, width, height);
                         }
+                        if busy {
+                            ui.add(egui::Spinner::new());
+                            let progress = *self.generation_progress.lock().unwrap();
+                            ui.add(egui::ProgressBar::new(progress).show_percentage());
+                        }
+                    });
+                    ui.add_space(SPACE_WIDGET);
 
-                        // Normalize only after erosion to avoid making erosion useless
-                        normalize2(&mut grid); // normalize so heights are in [0,1]
-                        // Save the last grid
-                        self.last_grid = Some(grid.clone());
-                        let flat = flatten2(&grid);
-                        let img = to_terrain_image(&flat, size);
-                        self.last_flat = Some(img.clone());
-                        // Keep size in sync with flat
-                        self.last_size = size;
-                        let color_image = ColorImage::from_rgb([size, size], &img);
-                        self.terrain_texture = Some(ctx.load_texture(
-                            "terrain",
-                            color_image,
-                            egui::TextureOptions::NEAREST,
-                        ));
-                        self.last_duration = Some(start.elapsed().as_secs_f32() * 1000.0);
-                        self.status_message = format!(
-                            "Generated in {:.2} ms (seed {})",
-                            self.last_duration.unwrap(),
-                            self.seed
-                        );
-                        ctx.request_repaint();
+                    ui.horizontal(|ui| {
+                        let busy = self.generation_job.is_some();
+                        let can_undo = !busy && self.history_pos > 0;
+                        let can_redo = !busy && self.history_pos + 1 < self.history.len();
+                        if ui
+                            .add_enabled(can_undo, egui::Button::new("Undo"))
+                            .clicked()
+                        {
+                            self.restore_history(ctx, self.history_pos - 1);
+                        }
+                        if ui
+                            .add_enabled(can_redo, egui::Button::new("Redo"))
+                            .clicked()
+                        {
+                            self.restore_history(ctx, self.history_pos + 1);
+                        }
+                    });
+                    ui.add_space(SPACE_WIDGET);
+
+                    // Parameter profiles
+                    ui.horizontal(|ui| {
+                        if ui.button("Save Settings").clicked()
+                            && let Some(path) = rfd::FileDialog::new()
+                                .set_title("Save Settings")
+                                .set_directory(".")
+                                .set_file_name("terrain_settings.toml")
+                                .add_filter("TOML", &["toml"])
+                                .save_file()
+                        {
+                            self.status_message = match self.save_settings(&path) {
+                                Ok(()) => format!("Saved settings to {}", path.display()),
+                                Err(e) => format!("Settings save error: {}", e),
+                            };
+                        }
+                        if ui.button("Preferences").clicked() {
+                            self.settings_uri = self.db_config.uri.clone();
+                            self.show_settings = true;
+                        }
+                        if ui.button("Load Settings").clicked()
+                            && let Some(path) = rfd::FileDialog::new()
+                                .set_title("Load Settings")
+                                .set_directory(".")
+                                .add_filter("TOML", &["toml"])
+                                .pick_file()
+                        {
+                            self.status_message = match self.load_settings(ctx, &path) {
+                                Ok(()) => format!("Loaded settings from {}", path.display()),
+                                Err(e) => format!("Settings load error: {}", e),
+                            };
+                        }
+                    });
+                    ui.add_space(SPACE_WIDGET);
+
+                    // Import an external heightmap instead of generating one
+                    if ui.button("Load PNG").clicked()
+                        && let Some(path) = rfd::FileDialog::new()
+                            .set_title("Load Heightmap PNG")
+                            .set_directory(".")
+                            .add_filter("PNG", &["png"])
+                            .pick_file()
+                    {
+                        // Color images are converted to luminance
+                        self.import_heightmap(ctx, &path, |path| {
+                            from_png_heightmap(path).map_err(|e| e.to_string())
+                        });
                     }
                     ui.add_space(SPACE_WIDGET);
 
                     // Terrain name and save options
                     ui.label("Terrain Name:");
                     ui.add_space(SPACE_LABEL);
-                    ui.text_edit_singleline(&mut self.save_name);
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut self.save_name);
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.save_tags)
+                                .hint_text("tags, comma separated"),
+                        );
+                    });
                     ui.add_space(SPACE_WIDGET);
 
                     ui.horizontal(|ui| {
                         // Save to PNG
-                        if ui.button("Save as PNG").clicked() {
-                            if let Some(img) = &self.last_flat {
-                                if let Some(path) = rfd::FileDialog::new()
-                                    .set_title("Save Terrain as PNG")
-                                    .set_directory(".")
-                                    .set_file_name(&format!("terrain_{}.png", self.save_name))
-                                    .save_file()
-                                {
-                                    image::save_buffer(
-                                        &path,
-                                        img,
-                                        self.last_size as u32,
-                                        self.last_size as u32,
-                                        image::ColorType::Rgb8,
-                                    )
-                                    .unwrap();
-                                    self.status_message =
-                                        format!("Saved PNG to {}", path.display());
-                                }
+                        if ui.button("Save as PNG").clicked()
+                            && let Some(flat) = &self.last_flat
+                            && let Some(path) = rfd::FileDialog::new()
+                                .set_title("Save Terrain as PNG")
+                                .set_directory(".")
+                                .set_file_name(format!("terrain_{}.png", self.save_name))
+                                .save_file()
+                        {
+                            let mut img = flat.clone();
+                            if self.save_with_contours
+                                && let Some(grid) = &self.last_grid
+                            {
+                                let lines = self.last_contours.get_or_insert_with(|| {
+                                    contour_polylines(grid, self.contour_count)
+                                });
+                                draw_contours_rgb(&mut img, self.last_width, lines);
                             }
+                            image::save_buffer(
+                                &path,
+                                &img,
+                                self.last_width as u32,
+                                self.last_height as u32,
+                                image::ColorType::Rgb8,
+                            )
+                            .unwrap();
+                            self.status_message = format!("Saved PNG to {}", path.display());
+                        }
+                        if ui.button("Export STL").clicked()
+                            && let Some(grid) = &self.last_grid
+                            && let Some(path) = rfd::FileDialog::new()
+                                .set_title("Export Terrain as STL")
+                                .set_directory(".")
+                                .set_file_name(format!("terrain_{}.stl", self.save_name))
+                                .add_filter("STL", &["stl"])
+                                .save_file()
+                        {
+                            // Same vertical exaggeration as the 3D preview
+                            let z_scale = self.last_width as f32 * PREVIEW_HEIGHT_SCALE;
+                            let result = std::fs::File::create(&path).and_then(|file| {
+                                let mut writer = std::io::BufWriter::new(file);
+                                to_stl(grid, z_scale, &mut writer)?;
+                                writer.flush()
+                            });
+                            self.status_message = match result {
+                                Ok(()) => format!("Exported STL to {}", path.display()),
+                                Err(e) => format!("STL export error: {}", e),
+                            };
+                        }
+                        if ui.button("Export OBJ").clicked()
+                            && let (Some(grid), Some(flat)) = (&self.last_grid, &self.last_flat)
+                            && let Some(path) = rfd::FileDialog::new()
+                                .set_title("Export Terrain as OBJ")
+                                .set_directory(".")
+                                .set_file_name(format!("terrain_{}.obj", self.save_name))
+                                .add_filter("OBJ", &["obj"])
+                                .save_file()
+                        {
+                            // The material and its texture sit next to the OBJ
+                            let z_scale = self.last_width as f32 * PREVIEW_HEIGHT_SCALE;
+                            let result = (|| -> std::io::Result<()> {
+                                let mut obj =
+                                    std::io::BufWriter::new(std::fs::File::create(&path)?);
+                                let mut mtl = std::io::BufWriter::new(std::fs::File::create(
+                                    path.with_file_name(MTL_FILE_NAME),
+                                )?);
+                                to_obj_with_mtl(grid, z_scale, &mut obj, &mut mtl)?;
+                                obj.flush()?;
+                                mtl.flush()?;
+                                image::save_buffer(
+                                    path.with_file_name(TEXTURE_FILE_NAME),
+                                    flat,
+                                    self.last_width as u32,
+                                    self.last_height as u32,
+                                    image::ColorType::Rgb8,
+                                )
+                                .map_err(std::io::Error::other)
+                            })();
+                            self.status_message = match result {
+                                Ok(()) => format!("Exported OBJ to {}", path.display()),
+                                Err(e) => format!("OBJ export error: {}", e),
+                            };
+                        }
+                        if ui.button("Export EXR").clicked()
+                            && let Some(grid) = &self.last_grid
+                            && let Some(path) = rfd::FileDialog::new()
+                                .set_title("Export Heightmap as EXR")
+                                .set_directory(".")
+                                .set_file_name(format!("terrain_{}.exr", self.save_name))
+                                .add_filter("OpenEXR", &["exr"])
+                                .save_file()
+                        {
+                            self.status_message = match to_exr(grid, &path) {
+                                Ok(()) => format!("Exported EXR to {}", path.display()),
+                                Err(e) => format!("EXR export error: {}", e),
+                            };
                         }
                         ui.add_space(SPACE_WIDGET);
 
@@ -434,46 +1505,21 @@ impl App for TerrainApp {
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                             ui.add_space(SPACE_RIGHT);
                             if ui.button("Save to Database").clicked() {
-                                if self.save_name.trim().is_empty() {
-                                    self.status_message =
-                                        "Terrain is not stored \nTerrain name is required".into();
+                                if let Err(e) = validate_terrain_name(&self.save_name) {
+                                    self.status_message = format!("Terrain is not stored \n{}", e);
                                     return;
                                 }
                                 if let Some(grid) = &self.last_grid {
-                                    // flatten the stored grid
-                                    let flat = flatten2(grid);
-                                    let params = TerrainParams {
-                                        noise_type: format!("{:?}", self.noise_type).to_lowercase(),
-                                        frequency: self.frequency,
-                                        persistence: self.persistence,
-                                        octaves: self.octaves as usize,
-                                        roughness: Some(self.roughness),
-                                        erosion_iters: Some(self.erosion_iters),
-                                        talus_angle: Some(self.talus_angle as f32),
-                                        warp_strength: Some(self.warp_strength),
-                                    };
-                                    let doc = TerrainDoc2D {
-                                        id: None,
-                                        name: self.save_name.clone(),
-                                        seed: self.seed as i64,
-                                        params,
-                                        height_map: flat,
-                                        dimensions: 2,
-                                    };
-
-                                    let success = {
-                                        let rt = tokio::runtime::Builder::new_current_thread()
-                                            .enable_all()
-                                            .build()
-                                            .unwrap();
-                                        rt.block_on(Storage2D::init(
-                                            "mongodb://localhost:27017",
-                                            "terrain_db",
-                                            "terrain2d",
-                                        ))
-                                        .and_then(|storage| rt.block_on(storage.create(doc)))
-                                        .is_ok()
-                                    };
+                                    let doc = terrain_doc(
+                                        self.save_name.clone(),
+                                        &self.generation_params(self.last_width, self.last_height),
+                                        grid,
+                                        parse_tags(&self.save_tags),
+                                    );
+
+                                    let success = self.storage().is_ok_and(|storage| {
+                                        self.runtime.block_on(storage.retry_create(doc)).is_ok()
+                                    });
 
                                     if success {
                                         self.status_message = "Saved to MongoDB".into();
@@ -496,83 +1542,151 @@ impl App for TerrainApp {
                         self.refresh_name_list();
                     }
                     ui.add_space(SPACE_WIDGET);
-                    // Draw ComboBox
                     ui.horizontal(|ui| {
-                        ui.label("Load terrain:");
+                        ui.label("Search:");
                         ui.add_space(SPACE_LABEL);
-                        egui::ComboBox::from_label("")
-                            .selected_text(
-                                self.selected_name
-                                    .as_ref()
-                                    .map(|s| s.as_str())
-                                    .unwrap_or("<none>"),
-                            )
-                            .show_ui(ui, |ui| {
-                                for name in &self.load_list {
-                                    ui.selectable_value(
-                                        &mut self.selected_name,
-                                        Some(name.clone()),
-                                        name,
-                                    );
-                                }
-                            });
+                        if ui.text_edit_singleline(&mut self.search_text).changed() {
+                            self.update_filtered_list();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let mut changed = ui
+                            .radio_value(&mut self.noise_filter, None, "All")
+                            .changed();
+                        for noise_type in [
+                            NoiseType::Fractal2D,
+                            NoiseType::Perlin2D,
+                            NoiseType::Simplex2D,
+                        ] {
+                            changed |= ui
+                                .radio_value(
+                                    &mut self.noise_filter,
+                                    Some(noise_type),
+                                    format!("{:?}", noise_type),
+                                )
+                                .changed();
+                        }
+                        if changed {
+                            self.apply_noise_filter();
+                        }
                     });
                     ui.add_space(SPACE_WIDGET);
-                    // Add a “Load Selected” button
-                    if ui.button("Load Selected").clicked() {
-                        if let Some(name) = &self.selected_name {
-                            let rt = tokio::runtime::Builder::new_current_thread()
-                                .enable_all()
-                                .build()
-                                .unwrap();
-                            match rt.block_on(Storage2D::init(
-                                "mongodb://localhost:27017",
-                                "terrain_db",
-                                "terrain2d",
-                            )) {
-                                Ok(storage) => match rt.block_on(storage.read_by_name(name)) {
-                                    Ok(Some(doc)) => {
-                                        // compute size from flattened length
-                                        let len = doc.height_map.len();
-                                        let size = (len as f64).sqrt() as usize;
-                                        assert!(
-                                            size * size == len,
-                                            "stored height_map length must be square"
-                                        );
-                                        // update last_size and last_flat
-                                        self.last_size = size;
-                                        self.last_flat = Some(
-                                            doc.height_map
-                                                .clone()
-                                                .iter()
-                                                .map(|&v| (v * 255.0) as u8)
-                                                .collect(),
+                    // Stored terrains, newest first. Click a row to select it
+                    ui.label("Load terrain:");
+                    egui::ScrollArea::vertical()
+                        .max_height(LOAD_TABLE_HEIGHT)
+                        .show(ui, |ui| {
+                            egui::Grid::new("load_table")
+                                .striped(true)
+                                .num_columns(4)
+                                .show(ui, |ui| {
+                                    for header in ["Name", "Type", "Size", "Date"] {
+                                        ui.strong(header);
+                                    }
+                                    ui.end_row();
+                                    let shown = self
+                                        .load_list
+                                        .iter()
+                                        .filter(|row| self.filtered_list.contains(&row.name));
+                                    for row in shown {
+                                        let selected =
+                                            self.selected_name.as_deref() == Some(&row.name);
+                                        if ui
+                                            .selectable_label(selected, &row.name)
+                                            .on_hover_text(format!(
+                                                "seed {}\n{}",
+                                                row.seed,
+                                                row.tags.join(", ")
+                                            ))
+                                            .clicked()
+                                        {
+                                            self.selected_name = Some(row.name.clone());
+                                        }
+                                        ui.label(&row.noise_type);
+                                        ui.label(summary_size(row));
+                                        ui.label(summary_date(row));
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                    ui.add_space(SPACE_WIDGET);
+                    // “Load Selected” and “Delete Selected” side by side
+                    let mut load_clicked = false;
+                    ui.horizontal(|ui| {
+                        load_clicked = ui.button("Load Selected").clicked();
+                        ui.add_space(SPACE_LABEL);
+                        if ui.button("Delete Selected").clicked() {
+                            if self.selected_name.is_some() {
+                                self.confirm_delete = true;
+                            } else {
+                                self.status_message = "No terrain selected".into();
+                            }
+                        }
+                    });
+                    // Ask before deleting, there is no undo
+                    if self.confirm_delete {
+                        ui.horizontal(|ui| {
+                            ui.label(format!(
+                                "Delete “{}”? Are you sure?",
+                                self.selected_name.as_deref().unwrap_or_default()
+                            ));
+                            if ui.button("Yes").clicked() {
+                                self.confirm_delete = false;
+                                self.delete_selected();
+                            }
+                            if ui.button("No").clicked() {
+                                self.confirm_delete = false;
+                            }
+                        });
+                    }
+                    if load_clicked {
+                        if let Some(name) = self.selected_name.clone() {
+                            match self.storage() {
+                                Ok(storage) => match self
+                                    .runtime
+                                    .block_on(storage.retry_read(&name))
+                                {
+                                    // e.g. an old document whose length isn't a square,
+                                    // from_flat() would panic on it
+                                    Ok(Some(doc))
+                                        if doc.height_map.len()
+                                            != doc.width as usize * doc.height as usize =>
+                                    {
+                                        self.status_message = format!(
+                                            "“{}” is damaged: {} heights for {}×{}",
+                                            name,
+                                            doc.height_map.len(),
+                                            doc.width,
+                                            doc.height
                                         );
-
-                                        // rebuild texture:
-                                        let img = to_terrain_image(&doc.height_map, self.last_size);
-                                        let color_image = ColorImage::from_rgb(
-                                            [self.last_size, self.last_size],
-                                            &img,
+                                    }
+                                    Ok(Some(doc)) => {
+                                        // Stored size, filled in by storage for old documents
+                                        let width = doc.width as usize;
+                                        let height = doc.height as usize;
+                                        // update last_width/height, last_grid, last_flat and the texture
+                                        self.show_grid(
+                                            ctx,
+                                            HeightMap2D::from_flat(doc.height_map, width, height),
                                         );
-                                        self.terrain_texture = Some(ctx.load_texture(
-                                            "terrain",
-                                            color_image,
-                                            egui::TextureOptions::NEAREST,
-                                        ));
                                         self.status_message = format!("Loaded “{}”", name);
+                                        self.displayed_name = Some(name.clone());
 
                                         // Sync configuration with loaded terrain parameters
                                         let params = &doc.params;
                                         self.seed = doc.seed as u64;
                                         self.save_name = doc.name.clone();
-                                        // Update resolution exponent based on map size
-                                        self.exp = match size {
+                                        self.save_tags = doc.tags.join(", ");
+                                        // Update resolution exponents based on map size
+                                        let exp_for = |side: usize, current: u32| match side {
                                             129 => 7,
                                             257 => 8,
                                             513 => 9,
-                                            _ => self.exp, // fallback to current if unknown
+                                            _ => current, // fallback to current if unknown
                                         };
+                                        self.exp = exp_for(width, self.exp);
+                                        self.height_exp = exp_for(height, self.height_exp);
+                                        self.square = width == height;
                                         // Update noise type
                                         self.noise_type = match params.noise_type.as_str() {
                                             "fractal2d" => NoiseType::Fractal2D,
@@ -612,71 +1726,212 @@ impl App for TerrainApp {
 
                     ui.separator();
                     ui.label(&self.status_message);
+                    // Cell under the cursor, filled in by the central panel
+                    ui.label(&self.hover_info);
                 });
             });
 
+        // Active DB connection, the URI can be changed in place
+        let mut show_settings = self.show_settings;
+        egui::Window::new("Settings")
+            .open(&mut show_settings)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(format!("Active MongoDB URI: {}", self.db_config.uri));
+                ui.label(format!(
+                    "Database: {}, collection: {}",
+                    self.db_config.db_name, self.db_config.collection
+                ));
+                ui.add_space(SPACE_WIDGET);
+                ui.label("MongoDB URI:");
+                ui.add_space(SPACE_LABEL);
+                ui.text_edit_singleline(&mut self.settings_uri);
+                ui.add_space(SPACE_WIDGET);
+                if ui.button("Apply").clicked() {
+                    let uri = self.settings_uri.trim().to_owned();
+                    if uri.is_empty() {
+                        self.status_message = "MongoDB URI can't be empty".into();
+                    } else {
+                        self.set_mongo_uri(uri);
+                    }
+                }
+            });
+        self.show_settings &= show_settings;
+
         // central display
         egui::CentralPanel::default().show(ctx, |ui| {
             if let Some(tex) = &self.terrain_texture {
+                // Top half: flat texture, bottom half: 3D preview
+                ui.horizontal(|ui| {
+                    if ui.button("Reset View").clicked() {
+                        self.zoom = 1.0;
+                        self.pan_offset = egui::Vec2::ZERO;
+                        self.preview_yaw = PREVIEW_YAW;
+                        self.preview_pitch = PREVIEW_PITCH;
+                    }
+                    if ui
+                        .checkbox(&mut self.cross_section, "Cross Section")
+                        .changed()
+                    {
+                        self.section_start = None;
+                        self.section_line = None;
+                    }
+                });
                 let available = ui.available_size();
-                ui.image((tex.id(), available));
-                ui.separator();
-                ui.label("3D Preview:");
-                // pull back your last‐computed f32 heights:
-                let flat = match &self.last_flat {
-                    Some(v) => v,
-                    None => {
-                        ui.label("no data");
-                        return;
+                let side = available.x.min(available.y * 0.5);
+                let (response, painter) =
+                    ui.allocate_painter(egui::vec2(side, side), egui::Sense::click_and_drag());
+                let view = response.rect;
+
+                if let Some(cursor) = response.hover_pos() {
+                    let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+                    if scroll != 0.0 {
+                        let old_zoom = self.zoom;
+                        self.zoom = (self.zoom * (scroll * 0.002).exp()).clamp(MIN_ZOOM, MAX_ZOOM);
+                        // Keep the pixel under the cursor in place
+                        let anchor = cursor - view.center();
+                        self.pan_offset =
+                            anchor + (self.pan_offset - anchor) * (self.zoom / old_zoom);
                     }
-                };
-                let hscale = 100.0;
-                let angle = std::f32::consts::FRAC_PI_4; // 45°
-                let (_ca, _sa) = (angle.cos(), angle.sin());
-
-                // Build mesh:
-                let mut verts = Vec::new();
-                let mut inds = Vec::new();
-                let mesh_size = self.last_size;
-                for y in 0..mesh_size - 1 {
-                    for x in 0..mesh_size - 1 {
-                        let corners = [
-                            (x as f32, flat[y * mesh_size + x] as f32 * hscale),
-                            (x as f32 + 1.0, flat[y * mesh_size + x + 1] as f32 * hscale),
-                            (x as f32, flat[(y + 1) * mesh_size + x] as f32 * hscale),
-                            (
-                                x as f32 + 1.0,
-                                flat[(y + 1) * mesh_size + x + 1] as f32 * hscale,
-                            ),
-                        ];
-                        for &(dx, h) in &corners {
-                            // simple side‐view projection:
-                            let px = dx;
-                            let py = -h;
-                            verts.push(egui::epaint::Vertex {
-                                pos: egui::pos2(px, py),
-                                uv: egui::pos2(0.0, 0.0),
-                                color: egui::Color32::WHITE,
-                            });
+                }
+                let alt = ui.input(|i| i.modifiers.alt);
+                if response.dragged_by(egui::PointerButton::Middle)
+                    || (alt && response.dragged_by(egui::PointerButton::Primary))
+                {
+                    self.pan_offset += response.drag_delta();
+                }
+
+                // Fit the terrain's aspect ratio inside the square view
+                let longest = self.last_width.max(self.last_height).max(1) as f32;
+                let fit = egui::vec2(
+                    self.last_width as f32 / longest,
+                    self.last_height as f32 / longest,
+                );
+                let image_rect = egui::Rect::from_center_size(
+                    view.center() + self.pan_offset,
+                    view.size() * fit * self.zoom,
+                );
+                painter.image(
+                    tex.id(),
+                    image_rect,
+                    egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                    egui::Color32::WHITE,
+                );
+
+                if self.contours
+                    && let Some(grid) = &self.last_grid
+                {
+                    let lines = self
+                        .last_contours
+                        .get_or_insert_with(|| contour_polylines(grid, self.contour_count));
+                    // Cell coordinates to the centre of that pixel on screen
+                    let cell = egui::vec2(
+                        image_rect.width() / self.last_width.max(1) as f32,
+                        image_rect.height() / self.last_height.max(1) as f32,
+                    );
+                    let stroke = egui::Stroke::new(1.0, egui::Color32::from_rgb(30, 30, 30));
+                    for line in lines.iter() {
+                        let points = line
+                            .iter()
+                            .map(|&(x, y)| image_rect.min + egui::vec2(x + 0.5, y + 0.5) * cell)
+                            .collect();
+                        painter.add(egui::Shape::line(points, stroke));
+                    }
+                }
+
+                // Readout for the pixel under the cursor
+                self.hover_info = response
+                    .hover_pos()
+                    .filter(|&p| image_rect.contains(p))
+                    .and_then(|p| {
+                        let t = (p - image_rect.min) / image_rect.size();
+                        let x = ((t.x * self.last_width as f32) as usize)
+                            .min(self.last_width.saturating_sub(1));
+                        let y = ((t.y * self.last_height as f32) as usize)
+                            .min(self.last_height.saturating_sub(1));
+                        let h = *self.last_grid.as_ref()?.get(y)?.get(x)?;
+                        let band = BAND_NAMES[self.display_palette().band(h)];
+                        Some(format!("(x={}, y={}, h={:.3}) {}", x, y, h, band))
+                    })
+                    .unwrap_or_else(|| "—".into());
+
+                if self.cross_section {
+                    // Screen <-> grid cell coordinates for the zoomed image
+                    let cells = egui::vec2(
+                        (self.last_width.max(2) - 1) as f32,
+                        (self.last_height.max(2) - 1) as f32,
+                    );
+                    let to_grid = |p: egui::Pos2| {
+                        let t = (p - image_rect.min) / image_rect.size();
+                        egui::pos2(t.x * cells.x, t.y * cells.y)
+                    };
+                    let to_screen = |g: egui::Pos2| {
+                        image_rect.min + egui::vec2(g.x, g.y) / cells * image_rect.size()
+                    };
+
+                    if response.clicked()
+                        && let Some(pos) = response.interact_pointer_pos()
+                    {
+                        let p = to_grid(pos);
+                        match self.section_start.take() {
+                            Some(start) => self.section_line = Some([start, p]),
+                            None => {
+                                self.section_start = Some(p);
+                                self.section_line = None;
+                            }
                         }
-                        let base = verts.len() as u32 - 4;
-                        inds.extend_from_slice(&[
-                            base,
-                            base + 1,
-                            base + 2,
-                            base + 1,
-                            base + 3,
-                            base + 2,
-                        ]);
+                    }
+                    // Drag whichever endpoint is closer to the pointer
+                    if !alt
+                        && response.dragged_by(egui::PointerButton::Primary)
+                        && let (Some(pos), Some(line)) =
+                            (response.interact_pointer_pos(), self.section_line.as_mut())
+                    {
+                        let near = if to_screen(line[0]).distance(pos)
+                            <= to_screen(line[1]).distance(pos)
+                        {
+                            0
+                        } else {
+                            1
+                        };
+                        line[near] = to_grid(pos);
+                    }
+
+                    let stroke = egui::Stroke::new(2.0, egui::Color32::RED);
+                    if let Some(start) = self.section_start {
+                        painter.circle_filled(to_screen(start), 4.0, egui::Color32::RED);
+                    }
+                    if let Some([a, b]) = self.section_line {
+                        painter.line_segment([to_screen(a), to_screen(b)], stroke);
+                        painter.circle_filled(to_screen(a), 4.0, egui::Color32::RED);
+                        painter.circle_filled(to_screen(b), 4.0, egui::Color32::RED);
+                    }
+
+                    if let (Some(grid), Some(line)) = (&self.last_grid, self.section_line) {
+                        ui.separator();
+                        draw_cross_section(ui, grid, line);
                     }
                 }
-                let mesh = egui::epaint::Mesh {
-                    vertices: verts,
-                    indices: inds,
-                    texture_id: egui::TextureId::default(),
-                };
-                // add as a mesh Shape
-                ui.painter().add(egui::epaint::Shape::mesh(mesh));
+                ui.separator();
+                ui.label("3D Preview (drag to orbit):");
+
+                let (response, painter) =
+                    ui.allocate_painter(ui.available_size(), egui::Sense::drag());
+                if response.dragged() {
+                    let delta = response.drag_delta();
+                    self.preview_yaw += delta.x * 0.01;
+                    self.preview_pitch = (self.preview_pitch + delta.y * 0.01).clamp(0.1, 1.5);
+                }
+                if let Some(grid) = &self.last_grid {
+                    painter.add(egui::Shape::mesh(build_preview_mesh(
+                        grid,
+                        &self.display_palette(),
+                        self.preview_yaw,
+                        self.preview_pitch,
+                        response.rect,
+                    )));
+                }
             } else {
                 ui.centered_and_justified(|ui| {
                     ui.label("Click “Generate” to start");
@@ -686,17 +1941,355 @@ impl App for TerrainApp {
     }
 }
 
+// `count` contour polylines at evenly spaced heights from 0.1 to 0.9
+fn contour_polylines(grid: &HeightMap2D, count: usize) -> Vec<Polyline> {
+    let step = 0.8 / (count.max(2) - 1) as f32;
+    let levels: Vec<f32> = (0..count).map(|i| 0.1 + step * i as f32).collect();
+    extract_contours(grid, &levels)
+        .into_iter()
+        .flat_map(|(_, lines)| lines)
+        .collect()
+}
+
+// Burn contour polylines into an RGB buffer `width` pixels wide,
+// one pixel per cell so the points are already pixel coordinates
+fn draw_contours_rgb(img: &mut [u8], width: usize, lines: &[Polyline]) {
+    let height = img.len() / 3 / width.max(1);
+    let mut plot = |x: f32, y: f32| {
+        let (x, y) = (x.round() as usize, y.round() as usize);
+        if x < width && y < height {
+            let i = (y * width + x) * 3;
+            img[i..i + 3].copy_from_slice(&CONTOUR_COLOR);
+        }
+    };
+    for line in lines {
+        for pair in line.windows(2) {
+            let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+            // One sample per pixel along the longer axis
+            let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+            for s in 0..=steps {
+                let t = s as f32 / steps as f32;
+                plot(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+            }
+        }
+    }
+}
+
+// Elevation profile of `grid` along `line` (grid cells) with min / max / mean
+fn draw_cross_section(ui: &mut egui::Ui, grid: &HeightMap2D, line: [egui::Pos2; 2]) {
+    let [a, b] = line;
+    let profile: Vec<f32> = (0..SECTION_SAMPLES)
+        .map(|i| {
+            let t = i as f32 / (SECTION_SAMPLES - 1) as f32;
+            let p = a.lerp(b, t);
+            sample_bilinear(grid, p.x, p.y)
+        })
+        .collect();
+    let min = profile.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = profile.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let mean = profile.iter().sum::<f32>() / profile.len() as f32;
+    ui.label(format!(
+        "Cross section: min {:.3}  max {:.3}  mean {:.3}",
+        min, max, mean
+    ));
+
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), SECTION_PLOT_HEIGHT),
+        egui::Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+    // Flat profiles would divide by zero, give them a nominal range
+    let range = (max - min).max(1e-6);
+    let points: Vec<egui::Pos2> = profile
+        .iter()
+        .enumerate()
+        .map(|(i, &h)| {
+            let x = rect.left() + rect.width() * i as f32 / (SECTION_SAMPLES - 1) as f32;
+            let y = rect.bottom() - rect.height() * (h - min) / range;
+            egui::pos2(x, y)
+        })
+        .collect();
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1.5, ui.visuals().text_color()),
+    ));
+}
+
+// Perspective view of `grid` fitted into `rect`.
+// egui has no depth buffer, so triangles are sorted back to front
+// (painter's algorithm). Each triangle is lit by its face normal while
+// the height colours are interpolated across it by the rasteriser.
+fn build_preview_mesh(
+    grid: &HeightMap2D,
+    palette: &TerrainPalette,
+    yaw: f32,
+    pitch: f32,
+    rect: egui::Rect,
+) -> egui::Mesh {
+    let height = grid.len();
+    let width = grid.first().map_or(0, Vec::len);
+    let mut mesh = egui::Mesh::default();
+    if width < 2 || height < 2 {
+        return mesh;
+    }
+    // Both axes share the longer side's scale so the mesh keeps its aspect
+    let span = (width.max(height) - 1) as f32;
+    let step = (width.max(height) - 1).div_ceil(PREVIEW_CELLS);
+    let (sin_yaw, cos_yaw) = yaw.sin_cos();
+    let (sin_pitch, cos_pitch) = pitch.sin_cos();
+    let camera_dist = 2.0;
+    let focal = rect.width().min(rect.height()) * 1.2;
+    let light = {
+        let (x, y, z) = (0.4f32, 0.8f32, 0.3f32);
+        let len = (x * x + y * y + z * z).sqrt();
+        [x / len, y / len, z / len]
+    };
+
+    // World position of grid cell (x, y), centred on the origin
+    let world = |x: usize, y: usize| -> [f32; 3] {
+        [
+            (x as f32 - (width - 1) as f32 * 0.5) / span,
+            grid[y][x] * PREVIEW_HEIGHT_SCALE,
+            (y as f32 - (height - 1) as f32 * 0.5) / span,
+        ]
+    };
+    // Orbit camera: rotate by yaw around Y, then pitch around X,
+    // returns screen position and view depth
+    let project = |p: [f32; 3]| -> (egui::Pos2, f32) {
+        let x = p[0] * cos_yaw - p[2] * sin_yaw;
+        let z = p[0] * sin_yaw + p[2] * cos_yaw;
+        let y = p[1] * cos_pitch - z * sin_pitch;
+        let depth = p[1] * sin_pitch + z * cos_pitch + camera_dist;
+        let screen = egui::pos2(
+            rect.center().x + focal * x / depth,
+            rect.center().y - focal * y / depth,
+        );
+        (screen, depth)
+    };
+    let shade = |a: [f32; 3], b: [f32; 3], c: [f32; 3]| -> f32 {
+        let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let n = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2])
+            .sqrt()
+            .max(f32::EPSILON);
+        // Both triangles of a quad are wound so that n points up (+Y)
+        let lambert = (n[0] * light[0] + n[1] * light[1] + n[2] * light[2]) / len;
+        0.35 + 0.65 * lambert.max(0.0)
+    };
+
+    // (depth, corners) for every triangle
+    let mut triangles = Vec::new();
+    for y in (0..height - 1).step_by(step) {
+        let y1 = (y + step).min(height - 1);
+        for x in (0..width - 1).step_by(step) {
+            let x1 = (x + step).min(width - 1);
+            let quad = [(x, y), (x1, y), (x, y1), (x1, y1)];
+            for tri in [[quad[0], quad[2], quad[1]], [quad[1], quad[2], quad[3]]] {
+                let corners = tri.map(|(cx, cy)| world(cx, cy));
+                let depth = corners.iter().map(|&c| project(c).1).sum::<f32>() / 3.0;
+                triangles.push((depth, corners));
+            }
+        }
+    }
+    triangles.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    for (_, corners) in triangles {
+        let light = shade(corners[0], corners[1], corners[2]);
+        for c in corners {
+            let [r, g, b] = palette.color(c[1] / PREVIEW_HEIGHT_SCALE);
+            let lit = |v: u8| (v as f32 * light) as u8;
+            mesh.colored_vertex(
+                project(c).0,
+                egui::Color32::from_rgb(lit(r), lit(g), lit(b)),
+            );
+        }
+        let base = mesh.vertices.len() as u32 - 3;
+        mesh.add_triangle(base, base + 1, base + 2);
+    }
+    mesh
+}
+
+// Fresh seed for the 🎲 button
+fn random_seed() -> u64 {
+    rand::random()
+}
+
+// Run the pipeline on a worker thread, reporting erosion to the progress bar
+fn generate_terrain(
+    params: &GenerationParams,
+    progress: &Mutex<f32>,
+    ctx: &egui::Context,
+) -> GenerationResult {
+    let start = Instant::now();
+    let (grid, erosion_passes) = build_heightmap(params, |current, total| {
+        *progress.lock().unwrap() = current as f32 / total.max(1) as f32;
+        ctx.request_repaint();
+    });
+    *progress.lock().unwrap() = 1.0;
+
+    let mut erosion_note = String::new();
+    if params.enable_erosion && erosion_passes < params.erosion_iters as usize {
+        erosion_note = format!("\nConverged in {} iterations", erosion_passes);
+    }
+
+    GenerationResult {
+        params: params.clone(),
+        grid,
+        seed: params.seed,
+        duration_ms: start.elapsed().as_secs_f32() * 1000.0,
+        erosion_note,
+    }
+}
+
+// Read a PNG dropped onto the window, which must be grayscale.
+// The "Load PNG" button uses from_png_heightmap() directly and converts color
+fn load_dropped_heightmap(path: &Path) -> Result<HeightMap2D, String> {
+    let color = image::ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| e.to_string())?
+        .color_type();
+    if color.has_color() {
+        return Err("heightmap must be grayscale".into());
+    }
+
+    from_png_heightmap(path).map_err(|e| e.to_string())
+}
+
+// Split "a, b,,c" into ["a", "b", "c"]
+fn parse_tags(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// "129×129", or "—" for documents saved before sizes were stored
+fn summary_size(row: &TerrainSummary) -> String {
+    if row.width > 0 {
+        format!("{}×{}", row.width, row.height)
+    } else {
+        "—".into()
+    }
+}
+
+// Creation day as YYYY-MM-DD, "—" if unknown
+fn summary_date(row: &TerrainSummary) -> String {
+    row.created_at
+        .and_then(|t| t.try_to_rfc3339_string().ok())
+        .and_then(|t| t.get(..10).map(str::to_string))
+        .unwrap_or_else(|| "—".into())
+}
+
 fn main() {
+    let prefs = Preferences::load();
     let opts = NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([800.0, 600.0])
+            .with_inner_size(prefs.window_size.unwrap_or([800.0, 600.0]))
             .with_min_inner_size([400.0, 300.0]),
         ..Default::default()
     };
     run_native(
         "FYP Terrain Generator",
         opts,
-        Box::new(|_cc| Ok(Box::new(TerrainApp::default()))),
+        Box::new(move |_cc| Ok(Box::new(TerrainApp::new(&prefs)))),
     )
     .unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CONTOUR_COLOR, draw_contours_rgb, load_dropped_heightmap, random_seed, summary_date,
+        summary_size,
+    };
+    use storage::models::TerrainSummary;
+
+    #[test]
+    fn load_table_cells() {
+        let mut row = TerrainSummary {
+            name: "alps".into(),
+            seed: 1,
+            noise_type: "fractal2d".into(),
+            width: 129,
+            height: 65,
+            created_at: Some(bson::DateTime::from_millis(1_700_000_000_000)),
+            tags: vec![],
+        };
+        assert_eq!(summary_size(&row), "129×65");
+        assert_eq!(summary_date(&row), "2023-11-14");
+        row.width = 0;
+        row.created_at = None;
+        assert_eq!(summary_size(&row), "—");
+        assert_eq!(summary_date(&row), "—");
+    }
+
+    fn save_png(name: &str, img: image::DynamicImage) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(name);
+        img.save(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn load_grayscale_heightmap() {
+        let img = image::GrayImage::from_fn(129, 129, |x, y| image::Luma([(x ^ y) as u8]));
+        let path = save_png("app_heightmap_129.png", img.into());
+        let grid = load_dropped_heightmap(&path);
+        let _ = std::fs::remove_file(&path);
+        let grid = grid.unwrap();
+        assert_eq!(grid.len(), 129);
+        assert_eq!(grid[0].len(), 129);
+    }
+
+    #[test]
+    fn reject_color_accept_non_square() {
+        let rgb = image::RgbImage::from_pixel(16, 16, image::Rgb([10, 200, 30]));
+        let rgb_path = save_png("app_heightmap_rgb.png", rgb.into());
+        let wide = image::GrayImage::new(32, 16);
+        let wide_path = save_png("app_heightmap_wide.png", wide.into());
+
+        let rgb_result = load_dropped_heightmap(&rgb_path);
+        let wide_result = load_dropped_heightmap(&wide_path);
+        let _ = std::fs::remove_file(&rgb_path);
+        let _ = std::fs::remove_file(&wide_path);
+
+        assert!(rgb_result.is_err());
+        let wide = wide_result.unwrap();
+        assert_eq!((wide[0].len(), wide.len()), (32, 16));
+    }
+
+    #[test]
+    fn contours_burned_into_image() {
+        let (width, height) = (8, 4);
+        let mut img = vec![255u8; width * height * 3];
+        // Horizontal line along row 2, from column 1 to 6
+        draw_contours_rgb(&mut img, width, &[vec![(1.0, 2.0), (6.0, 2.0)]]);
+        for x in 0..width {
+            let i = (2 * width + x) * 3;
+            let expected = if (1..=6).contains(&x) {
+                CONTOUR_COLOR
+            } else {
+                [255; 3]
+            };
+            assert_eq!(img[i..i + 3], expected, "column {}", x);
+        }
+        // Other rows untouched
+        assert!(img[..2 * width * 3].iter().all(|&c| c == 255));
+    }
+
+    #[test]
+    fn random_seeds_are_distinct() {
+        // Ten clicks on the dice, a repeat among 2^64 values is near impossible
+        let seeds: std::collections::HashSet<u64> = (0..10).map(|_| random_seed()).collect();
+        assert_eq!(seeds.len(), 10);
+    }
+}
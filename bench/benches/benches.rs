@@ -1,8 +1,12 @@
 use core::{
-    DomainWarp2D, Fractal2D, NoiseGenerator, Perlin2D, Simplex2D, ThermalErosion2D,
-    utils::{HeightMap2D, flatten2, normalize2, to_terrain_image},
+    BoundaryMode, DistanceMode, DomainWarp2D, DomainWarp2DChained, Fractal2D, NoiseGenerator,
+    OpenSimplex2D, Perlin2D, Simplex2D, ThermalErosion2D, VoronoiDiagram,
+    domain_warp::{DEFAULT_WARP_OFFSET_X, DEFAULT_WARP_OFFSET_Y, DEFAULT_WARP_SCALE},
+    utils::{HeightMap2D, flatten2, iter2, normalize2, to_ambient_occlusion, to_terrain_image},
+    voronoi::voronoi_heights,
 };
 use criterion::{Criterion, criterion_group, criterion_main};
+use storage::compression::{compress_heights, decompress_heights};
 
 const SIZE: usize = 257;
 const SEED: u64 = 2025;
@@ -10,11 +14,11 @@ const SEED: u64 = 2025;
 fn bench_fractal_pipeline(c: &mut Criterion) {
     c.bench_function("Fractal2D + normalize + flatten + image", |b| {
         b.iter(|| {
-            let mut f = Fractal2D::new(SIZE, SEED, 1.0);
+            let mut f = Fractal2D::new(513, SEED, 1.0);
             let mut map = f.generate();
             normalize2(&mut map);
             let flat = flatten2(&map);
-            let _img = to_terrain_image(&flat, SIZE);
+            let _img = to_terrain_image(&flat, SIZE, SIZE, None);
         })
     });
 }
@@ -24,12 +28,12 @@ fn bench_fractal_with_erosion(c: &mut Criterion) {
         "Fractal2D + erosion (5 iters) + normalize + flatten + image",
         |b| {
             b.iter(|| {
-                let mut f = Fractal2D::new(SIZE, SEED, 1.0);
+                let mut f = Fractal2D::new(513, SEED, 1.0);
                 let mut map = f.generate();
                 ThermalErosion2D::new(5, 1.0).apply(&mut map);
                 normalize2(&mut map);
                 let flat = flatten2(&map);
-                let _img = to_terrain_image(&flat, SIZE);
+                let _img = to_terrain_image(&flat, SIZE, SIZE, None);
             })
         },
     );
@@ -48,7 +52,7 @@ fn bench_perlin2_plain(c: &mut Criterion) {
                 .collect();
             normalize2(&mut map);
             let flat = flatten2(&map);
-            let _img = to_terrain_image(&flat, SIZE);
+            let _img = to_terrain_image(&flat, SIZE, SIZE, None);
         })
     });
 }
@@ -63,13 +67,19 @@ fn bench_perlin_with_warp(c: &mut Criterion) {
                 let mut map = DomainWarp2D {
                     base: &base,
                     warp: &warp,
+                    warp2: None,
                     size: SIZE,
                     warp_strength: 0.5,
+                    warp_iterations: 1,
+                    warp_scale: DEFAULT_WARP_SCALE,
+                    warp_offset_x: DEFAULT_WARP_OFFSET_X,
+                    warp_offset_y: DEFAULT_WARP_OFFSET_Y,
+                    boundary: BoundaryMode::default(),
                 }
                 .generate();
                 normalize2(&mut map);
                 let flat = flatten2(&map);
-                let _img = to_terrain_image(&flat, SIZE);
+                let _img = to_terrain_image(&flat, SIZE, SIZE, None);
             })
         },
     );
@@ -90,7 +100,7 @@ fn bench_simplex_plain(c: &mut Criterion) {
                 .collect();
             normalize2(&mut map);
             let flat = flatten2(&map);
-            let _img = to_terrain_image(&flat, SIZE);
+            let _img = to_terrain_image(&flat, SIZE, SIZE, None);
         })
     });
 }
@@ -105,25 +115,252 @@ fn bench_simplex_with_warp(c: &mut Criterion) {
                 let mut map = DomainWarp2D {
                     base: &base,
                     warp: &warp,
+                    warp2: None,
                     size: SIZE,
                     warp_strength: 0.5,
+                    warp_iterations: 1,
+                    warp_scale: DEFAULT_WARP_SCALE,
+                    warp_offset_x: DEFAULT_WARP_OFFSET_X,
+                    warp_offset_y: DEFAULT_WARP_OFFSET_Y,
+                    boundary: BoundaryMode::default(),
                 }
                 .generate();
                 normalize2(&mut map);
                 let flat = flatten2(&map);
-                let _img = to_terrain_image(&flat, SIZE);
+                let _img = to_terrain_image(&flat, SIZE, SIZE, None);
             })
         },
     );
 }
 
+// There is no Cellular2D generator in core yet, so only Voronoi is measured here
+fn bench_voronoi_mesa(c: &mut Criterion) {
+    c.bench_function("Voronoi (64 cells) + heights + flatten + image", |b| {
+        b.iter(|| {
+            let grid =
+                VoronoiDiagram::new(SEED, 64, SIZE, SIZE, DistanceMode::Euclidean).generate();
+            let map = voronoi_heights(&grid, SEED);
+            let flat = flatten2(&map);
+            let _img = to_terrain_image(&flat, SIZE, SIZE, None);
+        })
+    });
+}
+
+fn bench_ambient_occlusion(c: &mut Criterion) {
+    let mut f = Fractal2D::new(513, SEED, 1.0);
+    let mut map = f.generate();
+    normalize2(&mut map);
+    c.bench_function("Ambient occlusion (8 rays) on Fractal2D", |b| {
+        b.iter(|| {
+            let _ao = to_ambient_occlusion(&map, 8, 16, 32.0);
+        })
+    });
+}
+
+// The ray counts offered by the app's AO overlay, at the app's default size
+fn bench_ambient_occlusion_rays(c: &mut Criterion) {
+    let mut map = Perlin2D::new(SEED, 4.0, 0.5, 4).generate(SIZE);
+    normalize2(&mut map);
+    let mut group = c.benchmark_group("Ambient occlusion 257x257");
+    for rays in [4, 8, 16] {
+        group.bench_function(format!("{} rays", rays), |b| {
+            b.iter(|| to_ambient_occlusion(&map, rays, 16, SIZE as f32 * 0.3))
+        });
+    }
+    group.finish();
+}
+
+fn bench_warp_iterations(c: &mut Criterion) {
+    let base = Perlin2D::new(SEED, 4.0, 0.5, 4);
+    let warp = Perlin2D::new(SEED.wrapping_add(42), 4.0, 0.5, 4);
+    let warp2 = Perlin2D::new(SEED.wrapping_add(84), 4.0, 0.5, 4);
+    let mut group = c.benchmark_group("Perlin2D Domain Warp passes");
+    for iterations in 1..=3 {
+        group.bench_function(format!("{} pass(es)", iterations), |b| {
+            b.iter(|| {
+                DomainWarp2D {
+                    base: &base,
+                    warp: &warp,
+                    warp2: Some(&warp2),
+                    size: SIZE,
+                    warp_strength: 0.5,
+                    warp_iterations: iterations,
+                    warp_scale: DEFAULT_WARP_SCALE,
+                    warp_offset_x: DEFAULT_WARP_OFFSET_X,
+                    warp_offset_y: DEFAULT_WARP_OFFSET_Y,
+                    boundary: BoundaryMode::default(),
+                }
+                .generate()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_warp_order(c: &mut Criterion) {
+    let base = Perlin2D::new(SEED, 4.0, 0.5, 4);
+    let warp = Perlin2D::new(SEED.wrapping_add(42), 4.0, 0.5, 4);
+    let outer = Perlin2D::new(SEED.wrapping_add(84), 4.0, 0.5, 4);
+    let first = DomainWarp2D {
+        base: &base,
+        warp: &warp,
+        warp2: None,
+        size: SIZE,
+        warp_strength: 0.5,
+        warp_iterations: 1,
+        warp_scale: DEFAULT_WARP_SCALE,
+        warp_offset_x: DEFAULT_WARP_OFFSET_X,
+        warp_offset_y: DEFAULT_WARP_OFFSET_Y,
+        boundary: BoundaryMode::default(),
+    };
+    let mut group = c.benchmark_group("Perlin2D Domain Warp order");
+    group.bench_function("1st order", |b| b.iter(|| first.generate()));
+    group.bench_function("2nd order", |b| {
+        b.iter(|| {
+            DomainWarp2DChained {
+                first,
+                second: DomainWarp2D {
+                    warp: &outer,
+                    ..first
+                },
+            }
+            .generate()
+        })
+    });
+    group.finish();
+}
+
+fn bench_perlin_get2_slice(c: &mut Criterion) {
+    const N: usize = 512;
+    let perlin = Perlin2D::new(SEED, 4.0, 0.5, 4);
+    let points: Vec<(f64, f64)> = (0..N * N)
+        .map(|i| ((i % N) as f64 / N as f64, (i / N) as f64 / N as f64))
+        .collect();
+    let mut group = c.benchmark_group("Perlin2D 512x512 batch sampling");
+    group.bench_function("manual get2 loop", |b| {
+        let generator: &dyn NoiseGenerator = &perlin;
+        b.iter(|| {
+            let out: Vec<f64> = points.iter().map(|&(x, y)| generator.get2(x, y)).collect();
+            out
+        })
+    });
+    group.bench_function("get2_slice", |b| {
+        let mut out = Vec::with_capacity(points.len());
+        b.iter(|| perlin.get2_slice(&points, &mut out))
+    });
+    group.finish();
+}
+
+// OpenSimplex2D against Simplex2D over the same grid and octaves
+fn bench_opensimplex_vs_simplex(c: &mut Criterion) {
+    let mut group = c.benchmark_group("257x257 generate, 4 octaves");
+    group.bench_function("Simplex2D", |b| {
+        let simplex = Simplex2D::new(SEED, 4.0, 0.5, 4);
+        b.iter(|| simplex.generate(SIZE))
+    });
+    group.bench_function("OpenSimplex2D", |b| {
+        let open_simplex = OpenSimplex2D::new(SEED, 4.0, 0.5, 4);
+        b.iter(|| open_simplex.generate(SIZE))
+    });
+    group.finish();
+}
+
+// Client-side cost of storing a 513×513 map compressed,
+// the MongoDB round-trip itself needs a live server and is not measured here
+fn bench_height_map_compression(c: &mut Criterion) {
+    let mut f = Fractal2D::new(513, SEED, 1.0);
+    let mut map = f.generate();
+    normalize2(&mut map);
+    let flat = flatten2(&map);
+    let packed = compress_heights(&flat).unwrap();
+    let mut group = c.benchmark_group("Height map zstd 513x513");
+    group.bench_function("compress", |b| b.iter(|| compress_heights(&flat).unwrap()));
+    group.bench_function("decompress", |b| {
+        b.iter(|| decompress_heights(&packed).unwrap())
+    });
+    group.finish();
+}
+
+// Throughput of the app's batch mode without the database:
+// 10 seeds of the default pipeline, each compressed like a stored document
+fn bench_batch_generate_10(c: &mut Criterion) {
+    c.bench_function(
+        "Batch 10 × Fractal2D 129 + erosion + flatten + zstd",
+        |b| {
+            b.iter(|| {
+                for seed in 0..10 {
+                    let mut f = Fractal2D::new(129, seed, 1.0);
+                    let mut map = f.generate();
+                    ThermalErosion2D::new(5, 1.0).apply(&mut map);
+                    normalize2(&mut map);
+                    let flat = flatten2(&map);
+                    let _packed = compress_heights(&flat).unwrap();
+                }
+            })
+        },
+    );
+}
+
+// Rayon row-parallel erosion against the sequential loop, same output
+fn bench_thermal_erosion_parallel(c: &mut Criterion) {
+    let mut f = Fractal2D::new(513, SEED, 1.0);
+    let map = f.generate();
+    let mut group = c.benchmark_group("Thermal erosion 513, 20 iters");
+    for (label, parallel) in [("sequential", false), ("rayon", true)] {
+        group.bench_function(label, |b| {
+            b.iter(|| {
+                let mut eroded = map.clone();
+                ThermalErosion2D::new(20, 0.01)
+                    .with_convergence_threshold(0.0)
+                    .with_parallel(parallel)
+                    .apply(&mut eroded);
+            })
+        });
+    }
+    group.finish();
+}
+
+// Non-square generation should cost in proportion to the cell count
+fn bench_rect_generation(c: &mut Criterion) {
+    let perlin = Perlin2D::new(SEED, 4.0, 0.5, 4);
+    c.bench_function("Perlin2D generate_rect 512×256", |b| {
+        b.iter(|| perlin.generate_rect(512, 256))
+    });
+    c.bench_function("Perlin2D generate_rect 512×512", |b| {
+        b.iter(|| perlin.generate_rect(512, 512))
+    });
+}
+
+// Sums a map through iter2 and through a plain flatten of the rows
+fn bench_height_map_iteration(c: &mut Criterion) {
+    let map = Perlin2D::new(SEED, 4.0, 0.5, 4).generate(SIZE);
+    c.bench_function("HeightMap2D sum via iter2", |b| {
+        b.iter(|| iter2(&map).map(|(_, _, v)| v).sum::<f32>())
+    });
+    c.bench_function("HeightMap2D sum via flatten", |b| {
+        b.iter(|| map.iter().flatten().sum::<f32>())
+    });
+}
+
 criterion_group!(
     terrain_benchmarks,
     bench_fractal_pipeline,
     bench_fractal_with_erosion,
+    bench_thermal_erosion_parallel,
     bench_perlin2_plain,
     bench_perlin_with_warp,
     bench_simplex_plain,
-    bench_simplex_with_warp
+    bench_simplex_with_warp,
+    bench_opensimplex_vs_simplex,
+    bench_voronoi_mesa,
+    bench_ambient_occlusion,
+    bench_ambient_occlusion_rays,
+    bench_warp_iterations,
+    bench_warp_order,
+    bench_perlin_get2_slice,
+    bench_height_map_compression,
+    bench_batch_generate_10,
+    bench_height_map_iteration,
+    bench_rect_generation
 );
 criterion_main!(terrain_benchmarks);
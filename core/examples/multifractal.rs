@@ -0,0 +1,23 @@
+// Render a MultiFractal2D next to plain Perlin fBm for comparison
+// Run with: cargo run -p core --example multifractal
+// Writes multifractal.png and fbm.png to the current directory
+
+use core::utils::{HeightMap2D, flatten2, normalize2, to_terrain_image};
+use core::{MultiFractal2D, Perlin2D};
+
+const SIZE: usize = 513;
+
+fn save(name: &str, mut map: HeightMap2D) {
+    normalize2(&mut map);
+    let img = to_terrain_image(&flatten2(&map), SIZE, SIZE, None);
+    image::save_buffer(name, &img, SIZE as u32, SIZE as u32, image::ColorType::Rgb8).unwrap();
+    println!("wrote {}", name);
+}
+
+fn main() {
+    let mf = MultiFractal2D::new(2025, 3.0, 0.5, 8, 1.0);
+    let (lo, hi) = mf.bounds();
+    println!("MultiFractal2D bounds: [{:.3}, {:.3}]", lo, hi);
+    save("multifractal.png", mf.generate(SIZE));
+    save("fbm.png", Perlin2D::new(2025, 3.0, 0.5, 8).generate(SIZE));
+}
@@ -0,0 +1,19 @@
+// Generate a Perlin2D terrain, save it and print a preview in the terminal
+// Run with: cargo run -p core --example noise_demo2d
+// Writes noise_demo2d.png to the current directory
+
+use core::Perlin2D;
+use core::utils::{flatten2, normalize2, to_terrain_image};
+
+const SIZE: usize = 256;
+
+fn main() {
+    let mut map = Perlin2D::new(2025, 3.0, 0.5, 6).generate(SIZE);
+    normalize2(&mut map);
+
+    let name = "noise_demo2d.png";
+    let img = to_terrain_image(&flatten2(&map), SIZE, SIZE, None);
+    image::save_buffer(name, &img, SIZE as u32, SIZE as u32, image::ColorType::Rgb8).unwrap();
+    println!("wrote {}", name);
+    print!("{}", map);
+}
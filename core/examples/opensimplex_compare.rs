@@ -0,0 +1,38 @@
+// Render Simplex2D and OpenSimplex2D side by side from the same seed
+// Run with: cargo run -p core --example opensimplex_compare
+// Writes opensimplex_compare.png, Simplex2D on the left and OpenSimplex2D on
+// the right. Look for Simplex2D's streaks along its diagonal skew axes
+
+use core::utils::{HeightMap2D, normalize2};
+use core::{OpenSimplex2D, Simplex2D};
+
+const SIZE: usize = 384;
+const GAP: usize = 8;
+
+fn main() {
+    let mut left = Simplex2D::new(2025, 12.0, 0.5, 1).generate(SIZE);
+    let mut right = OpenSimplex2D::new(2025, 12.0, 0.5, 1).generate(SIZE);
+    normalize2(&mut left);
+    normalize2(&mut right);
+
+    let width = SIZE * 2 + GAP;
+    let mut img = image::GrayImage::from_pixel(width as u32, SIZE as u32, image::Luma([255]));
+    for (offset, map) in [(0, &left), (SIZE + GAP, &right)] {
+        draw(&mut img, map, offset);
+    }
+    let name = "opensimplex_compare.png";
+    img.save(name).unwrap();
+    println!("wrote {}", name);
+}
+
+fn draw(img: &mut image::GrayImage, map: &HeightMap2D, offset: usize) {
+    for (y, row) in map.iter().enumerate() {
+        for (x, &h) in row.iter().enumerate() {
+            img.put_pixel(
+                (x + offset) as u32,
+                y as u32,
+                image::Luma([(h * 255.0) as u8]),
+            );
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use crate::utils::{HeightMap2D, sobel_magnitude};
+
+// Cells whose signed height `sign × h` beats every other cell within `radius`
+// (a square window) by at least `min_margin`
+fn local_extrema(
+    map: &HeightMap2D,
+    radius: usize,
+    min_margin: f32,
+    sign: f32,
+) -> Vec<(usize, usize, f32)> {
+    let h = map.len();
+    let mut found = Vec::new();
+    for y in 0..h {
+        let w = map[y].len();
+        for x in 0..w {
+            let v = sign * map[y][x];
+            let (y0, x0) = (y.saturating_sub(radius), x.saturating_sub(radius));
+            let highest_neighbour = map.raw()[y0..(y + radius + 1).min(h)]
+                .iter()
+                .enumerate()
+                .flat_map(|(dy, row)| {
+                    row[x0..(x + radius + 1).min(w)]
+                        .iter()
+                        .enumerate()
+                        .filter(move |&(dx, _)| (x0 + dx, y0 + dy) != (x, y))
+                        .map(|(_, &n)| sign * n)
+                })
+                .fold(f32::MIN, f32::max);
+            if v > highest_neighbour && v - highest_neighbour >= min_margin {
+                found.push((x, y, map[y][x]));
+            }
+        }
+    }
+    found
+}
+
+// Strict local maxima that stand at least `min_prominence` above every
+// other cell within `radius`. Returns (x, y, height), highest first
+pub fn find_peaks(
+    map: &HeightMap2D,
+    radius: usize,
+    min_prominence: f32,
+) -> Vec<(usize, usize, f32)> {
+    let mut peaks = local_extrema(map, radius, min_prominence, 1.0);
+    peaks.sort_by(|a, b| b.2.total_cmp(&a.2));
+    peaks
+}
+
+// Strict local minima that sit at least `min_depth` below every
+// other cell within `radius`. Returns (x, y, height), lowest first
+pub fn find_valleys(map: &HeightMap2D, radius: usize, min_depth: f32) -> Vec<(usize, usize, f32)> {
+    let mut valleys = local_extrema(map, radius, min_depth, -1.0);
+    valleys.sort_by(|a, b| a.2.total_cmp(&b.2));
+    valleys
+}
+
+// Mean squared height difference between cells `lag` apart, along x and y
+fn variogram(map: &HeightMap2D, lag: usize) -> f64 {
+    let mut sum = 0.0;
+    let mut count = 0usize;
+    for (y, row) in map.iter().enumerate() {
+        for (a, b) in row.iter().zip(row.iter().skip(lag)) {
+            sum += ((b - a) as f64).powi(2);
+            count += 1;
+        }
+        if let Some(below) = map.get(y + lag) {
+            for (a, b) in row.iter().zip(below) {
+                sum += ((b - a) as f64).powi(2);
+                count += 1;
+            }
+        }
+    }
+    sum / count as f64
+}
+
+// Fractal dimension of the surface (between 2 and 3) by the variogram method.
+// The variogram grows like lag^(2H), so a log-log fit over lags 1, 2, 4, ...
+// gives the Hurst exponent H and D = 3 - H. Real mountains sit around 2.1-2.3.
+// NaN for a flat map or one smaller than 4×4
+pub fn fractal_dimension(map: &HeightMap2D) -> f64 {
+    let extent = map.len().min(map.first().map_or(0, Vec::len));
+    let lags: Vec<usize> = std::iter::successors(Some(1usize), |l| Some(l * 2))
+        .take_while(|&l| l <= extent / 4)
+        .collect();
+    if lags.len() < 2 {
+        return f64::NAN;
+    }
+
+    let points: Vec<(f64, f64)> = lags
+        .iter()
+        .map(|&l| ((l as f64).ln(), variogram(map, l).ln()))
+        .collect();
+    if points.iter().any(|p| !p.1.is_finite()) {
+        return f64::NAN;
+    }
+
+    // Least-squares slope of ln(variogram) against ln(lag)
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let cov: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let var: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    3.0 - (cov / var) / 2.0
+}
+
+// Root mean square of the slope (height change per cell), NaN for a flat map
+pub fn terrain_roughness_index(map: &HeightMap2D) -> f64 {
+    // The Sobel kernels weigh 8 cells worth of difference
+    let slopes: Vec<f64> = sobel_magnitude(map)
+        .iter()
+        .flatten()
+        .map(|&g| g as f64 / 8.0)
+        .collect();
+    let mean_sq = slopes.iter().map(|s| s * s).sum::<f64>() / slopes.len() as f64;
+    if mean_sq > 0.0 {
+        mean_sq.sqrt()
+    } else {
+        f64::NAN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_peaks, find_valleys, fractal_dimension, terrain_roughness_index};
+    use crate::utils::HeightMap2D;
+
+    // Gaussian bump centered on (cx, cy)
+    fn bump(w: usize, h: usize, cx: f32, cy: f32) -> HeightMap2D {
+        (0..h)
+            .map(|y| {
+                (0..w)
+                    .map(|x| (-((x as f32 - cx).powi(2) + (y as f32 - cy).powi(2)) / 8.0).exp())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_peak_found() {
+        let map = bump(12, 9, 7.0, 3.0);
+        assert_eq!(find_peaks(&map, 2, 0.05), vec![(7, 3, 1.0)]);
+        // The bump is smooth, so the neighbours are too close for a large prominence
+        assert!(find_peaks(&map, 2, 0.5).is_empty());
+    }
+
+    #[test]
+    fn valleys_and_ordering() {
+        // Two pits of different depth
+        let mut map = HeightMap2D::filled(10, 10, 1.0);
+        map[2][2] = 0.2;
+        map[7][6] = 0.5;
+        let valleys = find_valleys(&map, 1, 0.1);
+        assert_eq!(valleys, vec![(2, 2, 0.2), (6, 7, 0.5)]);
+        assert_eq!(find_valleys(&map, 1, 0.6), vec![(2, 2, 0.2)]);
+
+        // Flat ground has no strict extrema
+        assert!(find_peaks(&HeightMap2D::filled(4, 4, 0.5), 1, 0.0).is_empty());
+    }
+
+    #[test]
+    fn fractal_dimension_plane_and_flat() {
+        // Variogram of a tilted plane grows exactly with lag², so H = 1
+        let plane: HeightMap2D = (0..32)
+            .map(|y| (0..32).map(|x| (x + 2 * y) as f32 * 0.01).collect())
+            .collect();
+        assert!((fractal_dimension(&plane) - 2.0).abs() < 1e-6);
+        assert!(terrain_roughness_index(&plane) > 0.0);
+
+        let flat = HeightMap2D::filled(32, 32, 0.3);
+        assert!(fractal_dimension(&flat).is_nan());
+        assert!(terrain_roughness_index(&flat).is_nan());
+    }
+
+    #[test]
+    fn fractal_dimension_tracks_roughness() {
+        use crate::Fractal2D;
+
+        let smooth = fractal_dimension(&Fractal2D::new(129, 7, 0.5).generate());
+        let rough = fractal_dimension(&Fractal2D::new(129, 7, 1.0).generate());
+        assert!((2.0..=3.0).contains(&smooth) && (2.0..=3.0).contains(&rough));
+        assert!(rough > smooth);
+    }
+}
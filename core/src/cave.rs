@@ -0,0 +1,84 @@
+use crate::{NoiseGenerator, utils::HeightMap2D};
+
+// Solid/empty voxel grid, access as `mask[z][y][x]`, true = solid rock
+pub type CaveMask = Vec<Vec<Vec<bool>>>;
+
+// Threshold a 3D noise field into a cave volume: cells where
+// `noise.get3() > threshold` are solid rock, the rest is open space.
+// Samples the unit cube like DomainWarp3D, `noise` must implement get3()
+pub fn gen_cave_mask(noise: &dyn NoiseGenerator, size: usize, threshold: f64) -> CaveMask {
+    let mut mask = vec![vec![vec![false; size]; size]; size];
+    for (z, layer) in mask.iter_mut().enumerate() {
+        for (y, row) in layer.iter_mut().enumerate() {
+            for (x, solid) in row.iter_mut().enumerate() {
+                let fx = x as f64 / size as f64;
+                let fy = y as f64 / size as f64;
+                let fz = z as f64 / size as f64;
+                *solid = noise.get3(fx, fy, fz) > threshold;
+            }
+        }
+    }
+    mask
+}
+
+// For every (x, y) column, the highest z where solid rock has open space
+// directly above it, scaled to [0,1] by the volume depth.
+// Columns without such a transition get 0.0
+pub fn surface_from_cave_top(mask: &CaveMask) -> HeightMap2D {
+    let depth = mask.len();
+    if depth == 0 {
+        return HeightMap2D::default();
+    }
+    let (h, w) = (mask[0].len(), mask[0].first().map_or(0, |row| row.len()));
+    let scale = (depth.max(2) - 1) as f32;
+
+    let mut map = HeightMap2D::filled(w, h, 0.0);
+    for (y, row) in map.iter_mut().enumerate() {
+        for (x, val) in row.iter_mut().enumerate() {
+            let top = (0..depth - 1)
+                .rev()
+                .find(|&z| mask[z][y][x] && !mask[z + 1][y][x]);
+            *val = top.map_or(0.0, |z| z as f32 / scale);
+        }
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gen_cave_mask, surface_from_cave_top};
+    use crate::NoiseGenerator;
+
+    // Solid below a tilted plane z < 0.25 + 0.5x
+    struct Slope3;
+    impl NoiseGenerator for Slope3 {
+        fn get3(&self, x: f64, _y: f64, z: f64) -> f64 {
+            0.25 + 0.5 * x - z
+        }
+    }
+
+    #[test]
+    fn cave_mask_threshold() {
+        let mask = gen_cave_mask(&Slope3, 4, 0.0);
+        assert_eq!(mask.len(), 4);
+        // x = 0: solid up to z < 0.25 → only z = 0
+        let column: Vec<bool> = (0..4).map(|z| mask[z][0][0]).collect();
+        assert_eq!(column, vec![true, false, false, false]);
+        // x = 3 (0.75): solid below z < 0.625 → z = 0, 1, 2
+        let column: Vec<bool> = (0..4).map(|z| mask[z][2][3]).collect();
+        assert_eq!(column, vec![true, true, true, false]);
+    }
+
+    #[test]
+    fn cave_top_surface() {
+        let mask = gen_cave_mask(&Slope3, 4, 0.0);
+        let surface = surface_from_cave_top(&mask);
+        assert_eq!(surface.len(), 4);
+        assert_eq!(surface[1][0], 0.0);
+        assert!((surface[1][3] - 2.0 / 3.0).abs() < 1e-6);
+
+        // Fully solid column has no transition
+        let solid = vec![vec![vec![true; 1]; 1]; 3];
+        assert_eq!(surface_from_cave_top(&solid), vec![vec![0.0]]);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{NoiseGenerator, Perlin2D, utils::HeightMap2D};
+
+// How much colder the terrain gets per unit of normalized height
+const LAPSE_RATE: f32 = 0.6;
+// Amplitude of the noise that breaks up perfectly straight isotherms
+const TEMPERATURE_JITTER: f32 = 0.05;
+
+// Perlin noise in [0,1] at the same sampling as Perlin2D::generate()
+fn unit_noise(seed: u64, size: usize, frequency: f64) -> HeightMap2D {
+    let perlin = Perlin2D::new(seed, frequency, 0.5, 4);
+    let mut map = HeightMap2D::filled(size, size, 0.0);
+    for (y, row) in map.iter_mut().enumerate() {
+        for (x, val) in row.iter_mut().enumerate() {
+            let v = perlin.get2(x as f64 / size as f64, y as f64 / size as f64);
+            *val = (v as f32 * 0.5 + 0.5).clamp(0.0, 1.0);
+        }
+    }
+    map
+}
+
+// Rainfall / humidity in [0,1], 0 = arid, 1 = saturated
+pub struct MoistureMap;
+
+impl MoistureMap {
+    pub fn generate(seed: u64, size: usize, frequency: f64) -> HeightMap2D {
+        unit_noise(seed ^ 0x5EED_0F4A, size, frequency)
+    }
+}
+
+// Temperature in [0,1], 0 = frozen, 1 = hottest
+pub struct TemperatureMap;
+
+impl TemperatureMap {
+    // The top and bottom rows are the poles and the middle row the equator.
+    // latitude_gradient is how much colder the poles are than the equator (0..1),
+    // on top of that every unit of height costs LAPSE_RATE degrees.
+    // `height_map` should be normalized and size×size
+    pub fn generate(
+        seed: u64,
+        size: usize,
+        height_map: &HeightMap2D,
+        latitude_gradient: f32,
+    ) -> HeightMap2D {
+        let jitter = unit_noise(seed ^ 0x7E3D_A1B2, size, 4.0);
+        let mut map = HeightMap2D::filled(size, size, 0.0);
+        for (y, row) in map.iter_mut().enumerate() {
+            // 0 at the equator, 1 at either pole
+            let latitude = (y as f32 / (size.max(2) - 1) as f32 * 2.0 - 1.0).abs();
+            for (x, val) in row.iter_mut().enumerate() {
+                let t = 1.0 - latitude_gradient * latitude - LAPSE_RATE * height_map[y][x]
+                    + TEMPERATURE_JITTER * (jitter[y][x] - 0.5);
+                *val = t.clamp(0.0, 1.0);
+            }
+        }
+        map
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BiomeType {
+    Ocean,
+    Beach,
+    Desert,
+    Grassland,
+    Forest,
+    TaigaCold,
+    Tundra,
+    Snow,
+}
+
+impl BiomeType {
+    // Map color used by to_terrain_image()
+    pub fn color(self) -> [u8; 3] {
+        match self {
+            BiomeType::Ocean => [0, 64, 160],
+            BiomeType::Beach => [220, 200, 160],
+            BiomeType::Desert => [230, 190, 110],
+            BiomeType::Grassland => [110, 180, 60],
+            BiomeType::Forest => [30, 110, 40],
+            BiomeType::TaigaCold => [60, 100, 80],
+            BiomeType::Tundra => [150, 160, 140],
+            BiomeType::Snow => [245, 245, 250],
+        }
+    }
+}
+
+// Cut-off values used by assign_biomes(), all in [0,1]
+#[derive(Clone, Debug)]
+pub struct BiomeThresholds {
+    pub sea_level: f32, // below is Ocean
+    pub beach: f32,     // below (and above sea level) is Beach
+    pub snow_line: f32, // above is Snow regardless of climate
+    pub cold: f32,      // temperature below is Tundra / Taiga
+    pub hot: f32,       // temperature above can be Desert
+    pub dry: f32,       // moisture below is Desert / Tundra
+    pub wet: f32,       // moisture above is Forest
+}
+
+impl Default for BiomeThresholds {
+    fn default() -> Self {
+        Self {
+            sea_level: 0.3,
+            beach: 0.34,
+            snow_line: 0.85,
+            cold: 0.3,
+            hot: 0.65,
+            dry: 0.35,
+            wet: 0.55,
+        }
+    }
+}
+
+// Classify every cell from its height, moisture and temperature (all [0,1])
+pub fn assign_biomes(
+    height: &HeightMap2D,
+    moisture: &HeightMap2D,
+    temperature: &HeightMap2D,
+    thresholds: &BiomeThresholds,
+) -> Vec<Vec<BiomeType>> {
+    height
+        .iter()
+        .zip(moisture)
+        .zip(temperature)
+        .map(|((h_row, m_row), t_row)| {
+            h_row
+                .iter()
+                .zip(m_row)
+                .zip(t_row)
+                .map(|((&h, &m), &t)| classify(h, m, t, thresholds))
+                .collect()
+        })
+        .collect()
+}
+
+fn classify(h: f32, m: f32, t: f32, th: &BiomeThresholds) -> BiomeType {
+    if h < th.sea_level {
+        BiomeType::Ocean
+    } else if h < th.beach {
+        BiomeType::Beach
+    } else if h > th.snow_line {
+        BiomeType::Snow
+    } else if t < th.cold {
+        if m < th.dry {
+            BiomeType::Tundra
+        } else {
+            BiomeType::TaigaCold
+        }
+    } else if t > th.hot && m < th.dry {
+        BiomeType::Desert
+    } else if m > th.wet {
+        BiomeType::Forest
+    } else {
+        BiomeType::Grassland
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BiomeThresholds, BiomeType, HeightMap2D, MoistureMap, TemperatureMap, assign_biomes,
+    };
+
+    #[test]
+    fn moisture_in_unit_range() {
+        let a = MoistureMap::generate(3, 32, 4.0);
+        assert_eq!(a, MoistureMap::generate(3, 32, 4.0));
+        assert!(a.iter().flatten().all(|v| (0.0..=1.0).contains(v)));
+    }
+
+    #[test]
+    fn temperature_latitude_and_altitude() {
+        let size = 33;
+        let flat = HeightMap2D::filled(size, size, 0.0);
+        let t = TemperatureMap::generate(1, size, &flat, 0.8);
+        // Equator warmer than the poles
+        assert!(t[size / 2][5] > t[0][5] + 0.5);
+        assert!(t[size / 2][5] > t[size - 1][5] + 0.5);
+
+        let high = HeightMap2D::filled(size, size, 1.0);
+        let t_high = TemperatureMap::generate(1, size, &high, 0.8);
+        assert!(t_high[size / 2][5] < t[size / 2][5]);
+    }
+
+    #[test]
+    fn biome_rules() {
+        let th = BiomeThresholds::default();
+        let biome = |h, m, t| {
+            let cell = |v| HeightMap2D::filled(1, 1, v);
+            assign_biomes(&cell(h), &cell(m), &cell(t), &th)[0][0]
+        };
+        assert_eq!(biome(0.1, 0.5, 0.5), BiomeType::Ocean);
+        assert_eq!(biome(0.32, 0.5, 0.5), BiomeType::Beach);
+        assert_eq!(biome(0.95, 0.5, 0.5), BiomeType::Snow);
+        assert_eq!(biome(0.5, 0.1, 0.9), BiomeType::Desert);
+        assert_eq!(biome(0.5, 0.9, 0.5), BiomeType::Forest);
+        assert_eq!(biome(0.5, 0.45, 0.5), BiomeType::Grassland);
+        assert_eq!(biome(0.5, 0.6, 0.1), BiomeType::TaigaCold);
+        assert_eq!(biome(0.5, 0.1, 0.1), BiomeType::Tundra);
+    }
+}
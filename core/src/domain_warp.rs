@@ -1,29 +1,372 @@
-use crate::{NoiseGenerator, utils::HeightMap2D};
+use crate::{
+    NoiseGenerator,
+    utils::{HeightMap2D, HeightMap3D},
+};
 
+// Defaults from Inigo Quilez's original warp demo
+pub const DEFAULT_WARP_SCALE: f64 = 3.0;
+pub const DEFAULT_WARP_OFFSET_X: f64 = 0.0;
+pub const DEFAULT_WARP_OFFSET_Y: f64 = 5.2;
+
+// How warped coordinates that leave [0, 1] are brought back onto the base
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum BoundaryMode {
+    Clamp, // pin to the edge, every point past it samples the same value
+    Wrap,  // wrap around, seamless only if the base generator tiles
+    #[default]
+    Mirror, // reflect back inside, no seam and no repeated edge values
+}
+
+impl BoundaryMode {
+    pub fn apply(self, v: f64) -> f64 {
+        match self {
+            BoundaryMode::Clamp => v.clamp(0.0, 1.0),
+            BoundaryMode::Wrap => v.rem_euclid(1.0),
+            BoundaryMode::Mirror => {
+                // Triangle wave with period 2, so large displacements fold too
+                let t = v.rem_euclid(2.0);
+                if t > 1.0 { 2.0 - t } else { t }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
 pub struct DomainWarp2D<'a> {
     pub base: &'a dyn NoiseGenerator,
     pub warp: &'a dyn NoiseGenerator,
+    // Used from the second pass onwards, falls back to `warp` if None
+    pub warp2: Option<&'a dyn NoiseGenerator>,
     pub size: usize,
     pub warp_strength: f64,
+    // Number of warp passes, 1 = classic single warp
+    pub warp_iterations: usize,
+    // Frequency multiplier for the warp field, higher = smaller, busier swirls
+    pub warp_scale: f64,
+    // Where the x and y displacements are sampled, relative to the point.
+    // The two must differ or both axes get the same displacement
+    pub warp_offset_x: f64,
+    pub warp_offset_y: f64,
+    // What happens to warped points that land outside [0, 1]
+    pub boundary: BoundaryMode,
 }
 
 impl<'a> DomainWarp2D<'a> {
     pub fn generate(&self) -> HeightMap2D {
-        let mut map = vec![vec![0.0; self.size]; self.size];
-        for y in 0..self.size {
-            for x in 0..self.size {
+        let mut map = HeightMap2D::filled(self.size, self.size, 0.0);
+        for (y, row) in map.iter_mut().enumerate() {
+            for (x, val) in row.iter_mut().enumerate() {
                 let fx = x as f64 / self.size as f64;
                 let fy = y as f64 / self.size as f64;
+                let (warped_x, warped_y) = self.warp_point(fx, fy, (fx, fy));
+                *val = self.base.get2(warped_x, warped_y) as f32;
+            }
+        }
+        map
+    }
+
+    // Iterative warp (Inigo Quilez): each pass samples the warp field
+    // at the previously warped point and displaces the original point.
+    // Every pass costs 2 extra warp samples per cell, so the total is
+    // size² × (1 + 2 × warp_iterations) noise evaluations.
+    // The first pass samples at `start`, which is (fx, fy) for a plain warp
+    fn warp_point(&self, fx: f64, fy: f64, start: (f64, f64)) -> (f64, f64) {
+        let (mut warped_x, mut warped_y) = start;
+        for pass in 0..self.warp_iterations {
+            let warp = match (pass, self.warp2) {
+                (0, _) | (_, None) => self.warp,
+                (_, Some(w)) => w,
+            };
 
-                let dx = self.warp.get2(fx * 3.0, fy * 3.0);
-                let dy = self.warp.get2((fx + 5.2) * 3.0, (fy + 5.2) * 3.0);
+            let (scale, ox, oy) = (self.warp_scale, self.warp_offset_x, self.warp_offset_y);
+            let dx = warp.get2((warped_x + ox) * scale, (warped_y + ox) * scale);
+            let dy = warp.get2((warped_x + oy) * scale, (warped_y + oy) * scale);
+
+            warped_x = self.boundary.apply(fx + dx * self.warp_strength);
+            warped_y = self.boundary.apply(fy + dy * self.warp_strength);
+        }
+        (warped_x, warped_y)
+    }
+}
 
-                let warped_x = (fx + dx * self.warp_strength).clamp(0.0, 1.0);
-                let warped_y = (fy + dy * self.warp_strength).clamp(0.0, 1.0);
+// Second-order warp f(p + warp1(p + warp0(p))): `first` finds where `second`
+// samples its warp field, and `second` displaces p from there and samples its
+// base. Only second.base and second.size are used, first.base is ignored.
+// With one pass each and shared settings this matches a two-pass DomainWarp2D
+// with warp2, but here each order keeps its own strength, scale, passes and boundary
+pub struct DomainWarp2DChained<'a> {
+    pub first: DomainWarp2D<'a>,
+    pub second: DomainWarp2D<'a>,
+}
 
-                map[y][x] = self.base.get2(warped_x, warped_y) as f32;
+impl<'a> DomainWarp2DChained<'a> {
+    pub fn generate(&self) -> HeightMap2D {
+        let size = self.second.size;
+        let mut map = HeightMap2D::filled(size, size, 0.0);
+        for (y, row) in map.iter_mut().enumerate() {
+            for (x, val) in row.iter_mut().enumerate() {
+                let fx = x as f64 / size as f64;
+                let fy = y as f64 / size as f64;
+                let pre = self.first.warp_point(fx, fy, (fx, fy));
+                let (warped_x, warped_y) = self.second.warp_point(fx, fy, pre);
+                *val = self.second.base.get2(warped_x, warped_y) as f32;
             }
         }
         map
     }
 }
+
+// Volumetric analogue of DomainWarp2D, both generators must implement get3()
+pub struct DomainWarp3D<'a> {
+    pub base: &'a dyn NoiseGenerator,
+    pub warp: &'a dyn NoiseGenerator,
+    pub size: usize,
+    pub warp_strength: f64,
+}
+
+impl<'a> DomainWarp3D<'a> {
+    // Generate a size×size×size volume, access as `map[z][y][x]`
+    pub fn generate(&self) -> HeightMap3D {
+        let mut map = vec![vec![vec![0.0; self.size]; self.size]; self.size];
+        for (z, layer) in map.iter_mut().enumerate() {
+            for (y, row) in layer.iter_mut().enumerate() {
+                for (x, val) in row.iter_mut().enumerate() {
+                    let fx = x as f64 / self.size as f64;
+                    let fy = y as f64 / self.size as f64;
+                    let fz = z as f64 / self.size as f64;
+
+                    // Each axis samples the warp field at a different offset
+                    // so the three displacements are not correlated
+                    let dx = self.warp.get3(fx * 3.0, fy * 3.0, fz * 3.0);
+                    let dy = self
+                        .warp
+                        .get3((fx + 5.2) * 3.0, (fy + 5.2) * 3.0, (fz + 5.2) * 3.0);
+                    let dz = self
+                        .warp
+                        .get3((fx + 1.7) * 3.0, (fy + 9.2) * 3.0, (fz + 8.3) * 3.0);
+
+                    let warped_x = (fx + dx * self.warp_strength).clamp(0.0, 1.0);
+                    let warped_y = (fy + dy * self.warp_strength).clamp(0.0, 1.0);
+                    let warped_z = (fz + dz * self.warp_strength).clamp(0.0, 1.0);
+
+                    *val = self.base.get3(warped_x, warped_y, warped_z) as f32;
+                }
+            }
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BoundaryMode, DEFAULT_WARP_OFFSET_X, DEFAULT_WARP_OFFSET_Y, DEFAULT_WARP_SCALE,
+        DomainWarp2D, DomainWarp2DChained, DomainWarp3D,
+    };
+    use crate::{NoiseGenerator, Perlin2D};
+
+    // Minimal 3D fields for exercising DomainWarp3D
+    struct Ramp3;
+    impl NoiseGenerator for Ramp3 {
+        fn get3(&self, x: f64, y: f64, z: f64) -> f64 {
+            x + 10.0 * y + 100.0 * z
+        }
+    }
+    struct Constant3(f64);
+    impl NoiseGenerator for Constant3 {
+        fn get3(&self, _x: f64, _y: f64, _z: f64) -> f64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn domain_warp_zero_iterations_is_base() {
+        let base = Perlin2D::new(1, 4.0, 0.5, 4);
+        let warp = Perlin2D::new(2, 4.0, 0.5, 4);
+        let map = DomainWarp2D {
+            base: &base,
+            warp: &warp,
+            warp2: None,
+            size: 16,
+            warp_strength: 0.5,
+            warp_iterations: 0,
+            warp_scale: DEFAULT_WARP_SCALE,
+            warp_offset_x: DEFAULT_WARP_OFFSET_X,
+            warp_offset_y: DEFAULT_WARP_OFFSET_Y,
+            boundary: BoundaryMode::default(),
+        }
+        .generate();
+        assert_eq!(map, base.generate(16));
+    }
+
+    #[test]
+    fn domain_warp_warp2_fallback() {
+        let base = Perlin2D::new(1, 4.0, 0.5, 4);
+        let warp = Perlin2D::new(2, 4.0, 0.5, 4);
+        let with_fallback = DomainWarp2D {
+            base: &base,
+            warp: &warp,
+            warp2: None,
+            size: 16,
+            warp_strength: 0.5,
+            warp_iterations: 3,
+            warp_scale: DEFAULT_WARP_SCALE,
+            warp_offset_x: DEFAULT_WARP_OFFSET_X,
+            warp_offset_y: DEFAULT_WARP_OFFSET_Y,
+            boundary: BoundaryMode::default(),
+        }
+        .generate();
+        let explicit = DomainWarp2D {
+            base: &base,
+            warp: &warp,
+            warp2: Some(&warp),
+            size: 16,
+            warp_strength: 0.5,
+            warp_iterations: 3,
+            warp_scale: DEFAULT_WARP_SCALE,
+            warp_offset_x: DEFAULT_WARP_OFFSET_X,
+            warp_offset_y: DEFAULT_WARP_OFFSET_Y,
+            boundary: BoundaryMode::default(),
+        }
+        .generate();
+        assert_eq!(with_fallback, explicit);
+    }
+
+    #[test]
+    fn chained_warp_is_second_order() {
+        let base = Perlin2D::new(1, 4.0, 0.5, 4);
+        let warp0 = Perlin2D::new(2, 4.0, 0.5, 4);
+        let warp1 = Perlin2D::new(3, 4.0, 0.5, 4);
+        let single = |warp| DomainWarp2D {
+            base: &base,
+            warp,
+            warp2: None,
+            size: 16,
+            warp_strength: 0.5,
+            warp_iterations: 1,
+            warp_scale: DEFAULT_WARP_SCALE,
+            warp_offset_x: DEFAULT_WARP_OFFSET_X,
+            warp_offset_y: DEFAULT_WARP_OFFSET_Y,
+            boundary: BoundaryMode::default(),
+        };
+        let chained = DomainWarp2DChained {
+            first: single(&warp0),
+            second: single(&warp1),
+        }
+        .generate();
+        assert_ne!(chained, single(&warp0).generate());
+
+        // Same thing as two passes of one warp with warp2 for the second pass
+        let two_pass = DomainWarp2D {
+            warp2: Some(&warp1),
+            warp_iterations: 2,
+            ..single(&warp0)
+        }
+        .generate();
+        assert_eq!(chained, two_pass);
+    }
+
+    #[test]
+    fn domain_warp3_constant_shift() {
+        // A constant warp moves every sample by the same amount
+        let base = Ramp3;
+        let warp = Constant3(0.1);
+        let vol = DomainWarp3D {
+            base: &base,
+            warp: &warp,
+            size: 4,
+            warp_strength: 1.0,
+        }
+        .generate();
+        assert_eq!(vol.len(), 4);
+        assert_eq!(vol[0].len(), 4);
+        assert_eq!(vol[0][0].len(), 4);
+        // (0.25, 0.5, 0.0) warped to (0.35, 0.6, 0.1)
+        assert!((vol[0][2][1] as f64 - (0.35 + 6.0 + 10.0)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn boundary_mode_apply() {
+        assert_eq!(BoundaryMode::Clamp.apply(1.25), 1.0);
+        assert_eq!(BoundaryMode::Clamp.apply(-0.25), 0.0);
+        assert_eq!(BoundaryMode::Wrap.apply(1.25), 0.25);
+        assert_eq!(BoundaryMode::Wrap.apply(-0.25), 0.75);
+        assert_eq!(BoundaryMode::Mirror.apply(1.25), 0.75);
+        assert_eq!(BoundaryMode::Mirror.apply(-0.25), 0.25);
+        assert_eq!(BoundaryMode::Mirror.apply(2.5), 0.5);
+        assert_eq!(BoundaryMode::Mirror.apply(0.4), 0.4);
+    }
+
+    // Base that is just the x coordinate
+    struct RampX;
+
+    impl NoiseGenerator for RampX {
+        fn get2(&self, x: f64, _y: f64) -> f64 {
+            x
+        }
+    }
+
+    struct Constant2(f64);
+
+    impl NoiseGenerator for Constant2 {
+        fn get2(&self, _x: f64, _y: f64) -> f64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn domain_warp_mirror_avoids_edge_repetition() {
+        // Shift everything right by 0.5, so half the row lands past x = 1
+        let warp_row = |boundary| {
+            DomainWarp2D {
+                base: &RampX,
+                warp: &Constant2(0.5),
+                warp2: None,
+                size: 8,
+                warp_strength: 1.0,
+                warp_iterations: 1,
+                warp_scale: DEFAULT_WARP_SCALE,
+                warp_offset_x: DEFAULT_WARP_OFFSET_X,
+                warp_offset_y: DEFAULT_WARP_OFFSET_Y,
+                boundary,
+            }
+            .generate()[0]
+                .clone()
+        };
+        let clamped = warp_row(BoundaryMode::Clamp);
+        assert_eq!(clamped[4..], [1.0; 4]);
+        let mirrored = warp_row(BoundaryMode::Mirror);
+        assert_eq!(mirrored[4..], [1.0, 0.875, 0.75, 0.625]);
+    }
+
+    // Symmetric in x and y
+    struct Diagonal;
+
+    impl NoiseGenerator for Diagonal {
+        fn get2(&self, x: f64, y: f64) -> f64 {
+            (x * y * 7.0).sin() + x + y
+        }
+    }
+
+    #[test]
+    fn domain_warp_equal_offsets_keep_symmetry() {
+        let map = DomainWarp2D {
+            base: &Diagonal,
+            warp: &Diagonal,
+            warp2: None,
+            size: 12,
+            warp_strength: 0.3,
+            warp_iterations: 2,
+            warp_scale: 2.0,
+            warp_offset_x: 0.0,
+            warp_offset_y: 0.0,
+            boundary: BoundaryMode::default(),
+        }
+        .generate();
+        for (y, row) in map.iter().enumerate() {
+            for (x, &v) in row.iter().enumerate() {
+                assert_eq!(v, map[x][y]);
+            }
+        }
+    }
+}
@@ -1,8 +1,20 @@
+use std::cell::Cell;
+
 use crate::utils::HeightMap2D;
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+const DEFAULT_CONVERGENCE_THRESHOLD: f32 = 1e-6;
+
 pub struct ThermalErosion2D {
     iterations: usize,
-    talus_angle: f32, // maximum stable slope before material moves
+    talus_angle: f32,              // maximum stable slope before material moves
+    convergence_threshold: f32,    // stop once a pass moves less material than this
+    iterations_taken: Cell<usize>, // passes actually run by the last apply()
+    blend_factor: f32,             // share of the eroded result kept, the rest is the input
+    #[cfg(feature = "rayon")]
+    parallel: bool, // search slopes on the rayon thread pool
 }
 
 impl ThermalErosion2D {
@@ -15,68 +27,255 @@ impl ThermalErosion2D {
         Self {
             iterations,
             talus_angle,
+            convergence_threshold: DEFAULT_CONVERGENCE_THRESHOLD,
+            iterations_taken: Cell::new(0),
+            blend_factor: 1.0,
+            #[cfg(feature = "rayon")]
+            parallel: true,
+        }
+    }
+
+    // Rows are scanned in parallel by default with the `rayon` feature.
+    // The result is bit-for-bit the same either way
+    #[cfg(feature = "rayon")]
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    // Total material moved in one pass below which the terrain counts as settled
+    pub fn with_convergence_threshold(mut self, threshold: f32) -> Self {
+        self.convergence_threshold = threshold;
+        self
+    }
+
+    // Mix eroded × factor with original × (1 - factor) once erosion is done,
+    // to take the edge off an over-smoothed result. Clamped to [0,1], 1 = off
+    pub fn with_blend_factor(mut self, factor: f32) -> Self {
+        self.blend_factor = factor.clamp(0.0, 1.0);
+        self
+    }
+
+    // How many passes the last apply() ran before converging (or hitting `iterations`)
+    pub fn iterations_taken(&self) -> usize {
+        self.iterations_taken.get()
+    }
+
+    // Report (current_iter, total_iters) after every pass
+    // Keeps ThermalErosion2D itself free of any threading / UI dependency
+    pub fn with_progress_callback<F: Fn(usize, usize)>(
+        self,
+        f: F,
+    ) -> ThermalErosion2DWithProgress<F> {
+        ThermalErosion2DWithProgress {
+            erosion: self,
+            callback: f,
         }
     }
 
     // In‐place apply erosion to the height‐map
     pub fn apply(&self, map: &mut HeightMap2D) {
+        self.apply_with(map, |_, _| {});
+    }
+
+    // Erosion loop shared by apply(), ErosionStep and the progress wrapper.
+    // Records how many passes ran for iterations_taken()
+    fn apply_with(&self, map: &mut HeightMap2D, on_iteration: impl Fn(usize, usize)) {
+        self.iterations_taken.set(self.erode(map, on_iteration));
+    }
+
+    // Run the passes, returning how many ran
+    fn erode(&self, map: &mut HeightMap2D, on_iteration: impl Fn(usize, usize)) -> usize {
+        let mut iterations_taken = 0;
+        if map.is_empty() {
+            return 0;
+        }
         let h = map.len();
         let w = map[0].len();
+        let original = (self.blend_factor < 1.0).then(|| map.clone());
 
         for _ in 0..self.iterations {
+            iterations_taken += 1;
             // Accumulate deltas here to avoid order bias
             let mut delta = vec![vec![0.0f32; w]; h];
 
-            for y in 0..h {
-                for x in 0..w {
-                    let curr = map[y][x];
-                    // Check 4‐neighbors
-                    let mut max_diff = 0.0; // Largest downhill slope
-                    let mut max_n = (0, 0); // Neighbor with the largest downhill slope
-                    // Use & for borrowing to avoid copying
-                    for &(dy, dx) in &[(0, 1), (1, 0), (0, -1), (-1, 0)] {
-                        let ny = y as isize + dy;
-                        let nx = x as isize + dx;
-                        if ny >= 0 && ny < h as isize && nx >= 0 && nx < w as isize {
-                            let v = map[ny as usize][nx as usize];
-                            let diff = curr - v; // Elevation difference
-                            if diff > max_diff {
-                                max_diff = diff;
-                                max_n = (ny as usize, nx as usize);
-                            }
-                        }
-                    }
-                    // If slope exceeds talus errode
-                    if max_diff > self.talus_angle {
-                        let amount = (max_diff - self.talus_angle) * 0.5;
+            // Finding each cell's outflow only reads `map`, so rows can be
+            // searched in parallel. The deltas are still summed in row-major
+            // order below, which keeps the f32 result identical
+            let talus_angle = self.talus_angle;
+            let outflow_row = |y: usize| -> Vec<Outflow> {
+                (0..w).map(|x| outflow(map, x, y, talus_angle)).collect()
+            };
+            #[cfg(feature = "rayon")]
+            let outflows: Vec<_> = if self.parallel {
+                (0..h).into_par_iter().map(outflow_row).collect()
+            } else {
+                (0..h).map(outflow_row).collect()
+            };
+            #[cfg(not(feature = "rayon"))]
+            let outflows: Vec<_> = (0..h).map(outflow_row).collect();
+
+            for (y, row) in outflows.iter().enumerate() {
+                for (x, flows) in row.iter().enumerate() {
+                    for &(amount, (ny, nx)) in flows.iter().flatten() {
                         delta[y][x] -= amount; // Current cell loses height
-                        delta[max_n.0][max_n.1] += amount; // The steepest downhill gain height
+                        delta[ny][nx] += amount; // Downhill neighbour gains it
                     }
                 }
             }
 
             // Apply all deltas:
+            // Every `amount` removed from a cell is added to exactly one neighbour,
+            // so the deltas sum to zero and total height (mass) is conserved.
+            // The only drift comes from f32 rounding in `map += delta`,
+            // which is on the order of f32::EPSILON × height per cell per pass.
+            let mut moved = 0.0;
             for y in 0..h {
                 for x in 0..w {
                     map[y][x] += delta[y][x];
+                    moved += delta[y][x].abs();
+                }
+            }
+
+            on_iteration(iterations_taken, self.iterations);
+
+            // Nothing (or almost nothing) slid downhill, further passes are wasted
+            if moved < self.convergence_threshold {
+                break;
+            }
+        }
+
+        if let Some(original) = original {
+            let f = self.blend_factor;
+            for (row, orig) in map.iter_mut().zip(&original) {
+                for (v, &o) in row.iter_mut().zip(orig) {
+                    *v = *v * f + o * (1.0 - f);
+                }
+            }
+        }
+        iterations_taken
+    }
+}
+
+// One stage of an ErosionPipeline
+pub trait ErosionStep {
+    fn apply(&self, map: &mut HeightMap2D);
+}
+
+impl ErosionStep for ThermalErosion2D {
+    fn apply(&self, map: &mut HeightMap2D) {
+        self.apply_with(map, |_, _| {});
+    }
+}
+
+// Erosion steps run one after another, e.g. thermal then a gentler thermal:
+// ErosionPipeline::new().with_step(ThermalErosion2D::new(20, 0.5)).with_step(...)
+#[derive(Default)]
+pub struct ErosionPipeline {
+    steps: Vec<Box<dyn ErosionStep>>,
+}
+
+impl ErosionPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_step(&mut self, step: Box<dyn ErosionStep>) -> &mut Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn with_step(mut self, step: impl ErosionStep + 'static) -> Self {
+        self.steps.push(Box::new(step));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    // Run every step in the order they were added
+    pub fn apply(&self, map: &mut HeightMap2D) {
+        for step in &self.steps {
+            step.apply(map);
+        }
+    }
+}
+
+// Material sliding off a cell to each of its 4 neighbours, as (amount, (y, x))
+type Outflow = [Option<(f32, (usize, usize))>; 4];
+
+// Material sliding off (x, y) this pass. The total is still half the excess of
+// the steepest slope over talus, but it is shared between every neighbour
+// steeper than talus in proportion to its own excess, so slopes erode evenly
+// instead of carving a channel towards a single neighbour
+fn outflow(map: &HeightMap2D, x: usize, y: usize, talus_angle: f32) -> Outflow {
+    let h = map.len();
+    let w = map[0].len();
+    let curr = map[y][x];
+    let mut flows: Outflow = [None; 4];
+    let mut max_diff = 0.0; // Largest downhill slope
+    let mut total_excess = 0.0; // Sum of (diff - talus) over eroding neighbours
+    // Check 4‐neighbors
+    for (i, &(dy, dx)) in [(0, 1), (1, 0), (0, -1), (-1, 0)].iter().enumerate() {
+        let ny = y as isize + dy;
+        let nx = x as isize + dx;
+        if ny >= 0 && ny < h as isize && nx >= 0 && nx < w as isize {
+            let v = map[ny as usize][nx as usize];
+            let diff = curr - v; // Elevation difference
+            if diff > talus_angle {
+                // Park the excess here, it becomes the share below
+                flows[i] = Some((diff - talus_angle, (ny as usize, nx as usize)));
+                total_excess += diff - talus_angle;
+                if diff > max_diff {
+                    max_diff = diff;
                 }
             }
         }
     }
+    if total_excess > 0.0 {
+        let total = (max_diff - talus_angle) * 0.5;
+        for (amount, _) in flows.iter_mut().flatten() {
+            *amount = total * *amount / total_excess;
+        }
+    }
+    flows
+}
+
+// ThermalErosion2D that calls `callback(current_iter, total_iters)` after each pass
+pub struct ThermalErosion2DWithProgress<F: Fn(usize, usize)> {
+    erosion: ThermalErosion2D,
+    callback: F,
+}
+
+impl<F: Fn(usize, usize)> ThermalErosion2DWithProgress<F> {
+    // In‐place apply erosion, reporting progress along the way
+    pub fn apply(&self, map: &mut HeightMap2D) {
+        self.erosion.apply_with(map, &self.callback);
+    }
+
+    pub fn iterations_taken(&self) -> usize {
+        self.erosion.iterations_taken()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::ThermalErosion2D;
+    use super::{ErosionPipeline, ErosionStep, HeightMap2D, ThermalErosion2D};
 
     #[test]
     fn erosion2_simple_peak() {
         // 3×3 map: peak at center
-        let mut map = vec![
+        let mut map: HeightMap2D = vec![
             vec![0.0, 0.0, 0.0],
             vec![0.0, 2.0, 0.0],
             vec![0.0, 0.0, 0.0],
-        ];
+        ]
+        .into();
         let er = ThermalErosion2D::new(1, 1.0);
         er.apply(&mut map);
         // Center should decrease, at least one neighbor should increase
@@ -86,7 +285,7 @@ mod tests {
 
     #[test]
     fn erosion2_determinism() {
-        let mut m1: Vec<Vec<f32>> = (0..5).map(|i| vec![i as f32; 5]).collect();
+        let mut m1: HeightMap2D = (0..5).map(|i| vec![i as f32; 5]).collect();
         let mut m2 = m1.clone();
         let er = ThermalErosion2D::new(3, 0.5);
         er.apply(&mut m1);
@@ -94,4 +293,197 @@ mod tests {
         er2.apply(&mut m2);
         assert_eq!(m1, m2);
     }
+
+    #[test]
+    fn erosion2_mass_conservation() {
+        use crate::Fractal2D;
+
+        let size = 65;
+        let mut map = Fractal2D::new(size, 11, 0.9).generate();
+        // Sum in f64 so the check itself does not suffer from cancellation
+        let mass = |m: &Vec<Vec<f32>>| m.iter().flatten().map(|&v| v as f64).sum::<f64>();
+        let before = mass(&map);
+
+        let er = ThermalErosion2D::new(20, 0.01);
+        er.apply(&mut map);
+        let after = mass(&map);
+
+        assert!(
+            (after - before).abs() < 1e-3 * (size * size) as f64,
+            "mass drifted from {} to {}",
+            before,
+            after
+        );
+    }
+
+    #[test]
+    fn erosion2_progress_callback() {
+        use std::cell::RefCell;
+
+        let calls = RefCell::new(Vec::new());
+        let mut map: HeightMap2D = (0..6).map(|i| vec![i as f32; 6]).collect();
+        let er = ThermalErosion2D::new(4, 0.1)
+            .with_convergence_threshold(0.0)
+            .with_progress_callback(|cur, total| calls.borrow_mut().push((cur, total)));
+        er.apply(&mut map);
+        assert_eq!(er.iterations_taken(), 4);
+        assert_eq!(calls.into_inner(), vec![(1, 4), (2, 4), (3, 4), (4, 4)]);
+    }
+
+    #[test]
+    fn erosion2_flat_converges_immediately() {
+        let mut map = HeightMap2D::filled(8, 8, 0.5);
+        let er = ThermalErosion2D::new(50, 0.1);
+        er.apply(&mut map);
+        assert_eq!(er.iterations_taken(), 1);
+    }
+
+    #[test]
+    fn erosion2_empty_map_is_noop() {
+        let mut map = HeightMap2D::default();
+        let er = ThermalErosion2D::new(5, 0.1);
+        er.apply(&mut map);
+        assert_eq!(er.iterations_taken(), 0);
+    }
+
+    #[test]
+    fn erosion2_zero_threshold_runs_all_iterations() {
+        let mut map: HeightMap2D = vec![
+            vec![0.0, 0.0, 0.0],
+            vec![0.0, 9.0, 0.0],
+            vec![0.0, 0.0, 0.0],
+        ]
+        .into();
+        let er = ThermalErosion2D::new(3, 0.5).with_convergence_threshold(0.0);
+        er.apply(&mut map);
+        assert_eq!(er.iterations_taken(), 3);
+    }
+
+    #[test]
+    fn erosion2_step_reports_iterations() {
+        // Run through the ErosionStep trait, as an ErosionPipeline does
+        let mut map = HeightMap2D::filled(5, 5, 0.0);
+        map[2][2] = 9.0;
+        let er = ThermalErosion2D::new(3, 0.5).with_convergence_threshold(0.0);
+        ErosionStep::apply(&er, &mut map);
+        assert_eq!(er.iterations_taken(), 3);
+    }
+
+    #[test]
+    fn erosion2_radially_symmetric_peak_stays_symmetric() {
+        // Cone centred on a 9×9 map
+        let mut map: HeightMap2D = (0..9)
+            .map(|y| {
+                (0..9)
+                    .map(|x| {
+                        let (dx, dy) = (x as f32 - 4.0, y as f32 - 4.0);
+                        8.0 - (dx * dx + dy * dy).sqrt()
+                    })
+                    .collect()
+            })
+            .collect();
+        let er = ThermalErosion2D::new(100, 0.3);
+        er.apply(&mut map);
+
+        let east = map[4][5];
+        for v in [map[5][4], map[4][3], map[3][4]] {
+            assert!((v - east).abs() < 1e-4, "{} != {}", v, east);
+        }
+        // A quarter turn maps the terrain onto itself
+        for (y, row) in map.iter().enumerate() {
+            for (x, &v) in row.iter().enumerate() {
+                assert!((v - map[x][8 - y]).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn erosion2_parallel_matches_sequential() {
+        use crate::Fractal2D;
+
+        let mut seq = Fractal2D::new(129, 4, 0.9).generate();
+        let mut par = seq.clone();
+        ThermalErosion2D::new(10, 0.01)
+            .with_parallel(false)
+            .apply(&mut seq);
+        ThermalErosion2D::new(10, 0.01)
+            .with_parallel(true)
+            .apply(&mut par);
+        assert_eq!(seq, par);
+    }
+
+    #[test]
+    fn erosion2_blend_factor() {
+        let peak = || {
+            let mut map = HeightMap2D::filled(5, 5, 0.0);
+            map[2][2] = 4.0;
+            map[1][2] = 2.0;
+            map
+        };
+        let run = |erosion: ThermalErosion2D| {
+            let mut map = peak();
+            erosion.apply(&mut map);
+            map
+        };
+        let full = run(ThermalErosion2D::new(10, 0.5));
+        assert_ne!(full, peak());
+        assert_eq!(
+            run(ThermalErosion2D::new(10, 0.5).with_blend_factor(1.0)),
+            full
+        );
+        assert_eq!(
+            run(ThermalErosion2D::new(10, 0.5).with_blend_factor(0.0)),
+            peak()
+        );
+
+        // Halfway sits between the two, cell by cell
+        let half = run(ThermalErosion2D::new(10, 0.5).with_blend_factor(0.5));
+        for ((h, f), o) in half
+            .iter()
+            .flatten()
+            .zip(full.iter().flatten())
+            .zip(peak().iter().flatten())
+        {
+            assert!((h - (f + o) / 2.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn erosion_pipeline_runs_steps_in_order() {
+        let peak = || {
+            let mut map = HeightMap2D::filled(7, 7, 0.0);
+            map[(3, 3)] = 6.0;
+            map
+        };
+        let mut expected = peak();
+        ThermalErosion2D::new(5, 1.0).apply(&mut expected);
+        ThermalErosion2D::new(5, 0.2).apply(&mut expected);
+
+        let mut map = peak();
+        ErosionPipeline::new()
+            .with_step(ThermalErosion2D::new(5, 1.0))
+            .with_step(ThermalErosion2D::new(5, 0.2))
+            .apply(&mut map);
+        assert_eq!(map, expected);
+
+        // add_step() builds the same pipeline in place
+        let mut pipeline = ErosionPipeline::new();
+        pipeline
+            .add_step(Box::new(ThermalErosion2D::new(5, 1.0)))
+            .add_step(Box::new(ThermalErosion2D::new(5, 0.2)));
+        let mut map = peak();
+        pipeline.apply(&mut map);
+        assert_eq!(map, expected);
+    }
+
+    #[test]
+    fn empty_erosion_pipeline_is_identity() {
+        let mut map: HeightMap2D = (0..4).map(|i| vec![i as f32; 4]).collect();
+        let original = map.clone();
+        let pipeline = ErosionPipeline::new();
+        assert!(pipeline.is_empty());
+        pipeline.apply(&mut map);
+        assert_eq!(map, original);
+    }
 }
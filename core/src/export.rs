@@ -0,0 +1,393 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use exr::prelude::{
+    AnyChannel, AnyChannels, Encoding, FlatSamples, Image, Layer, LayerAttributes, WritableImage,
+    read_first_flat_layer_from_file,
+};
+
+use crate::utils::HeightMap2D;
+
+// Start of the 80 byte binary STL header, the rest is zero padding.
+// Must not start with "solid", some readers take that to mean ASCII
+const STL_HEADER: &[u8] = b"binary STL, Natural Terrain Generator";
+
+// Names the OBJ writes into its mtllib / usemtl lines, so the .mtl and the
+// colour image have to be saved under these names next to the .obj
+pub const MTL_FILE_NAME: &str = "terrain.mtl";
+pub const TEXTURE_FILE_NAME: &str = "terrain_color.png";
+const MATERIAL_NAME: &str = "terrain";
+
+type Triangle = [[f32; 3]; 3];
+
+// Closed solid for the height-map: the terrain on top, a flat bottom at z = 0
+// and side walls joining the two. Vertices are (x, y, z_scale * h) with x the
+// column and y counted up from the last row, so seen from above the model
+// looks like the image rather than its mirror. Every triangle winds
+// counter-clockwise seen from outside, as STL expects.
+// Heights should be >= 0 (normalized maps are), or the top dips through the bottom
+fn solid_triangles(map: &HeightMap2D, z_scale: f32) -> Vec<Triangle> {
+    let h = map.len();
+    let w = if h > 0 { map[0].len() } else { 0 };
+    if w < 2 || h < 2 {
+        return Vec::new();
+    }
+    let top = |x: usize, y: usize| [x as f32, y as f32, z_scale * map[h - 1 - y][x]];
+    let bottom = |x: usize, y: usize| [x as f32, y as f32, 0.0];
+
+    let mut tris = Vec::with_capacity(4 * (w - 1) * (h - 1) + 4 * (w + h));
+    for y in 0..h - 1 {
+        for x in 0..w - 1 {
+            let (a, b, c, d) = (top(x, y), top(x + 1, y), top(x + 1, y + 1), top(x, y + 1));
+            tris.push([a, b, c]);
+            tris.push([a, c, d]);
+            // Bottom faces down, so the winding is reversed
+            let (a, b, c, d) = (
+                bottom(x, y),
+                bottom(x + 1, y),
+                bottom(x + 1, y + 1),
+                bottom(x, y + 1),
+            );
+            tris.push([a, c, b]);
+            tris.push([a, d, c]);
+        }
+    }
+
+    // Walk the border counter-clockwise seen from above, the outside is on the right
+    let mut border = Vec::with_capacity(2 * (w + h));
+    border.extend((0..w - 1).map(|x| (x, 0)));
+    border.extend((0..h - 1).map(|y| (w - 1, y)));
+    border.extend((1..w).rev().map(|x| (x, h - 1)));
+    border.extend((1..h).rev().map(|y| (0, y)));
+    for (i, &(px, py)) in border.iter().enumerate() {
+        let (qx, qy) = border[(i + 1) % border.len()];
+        let (p0, q0, qt, pt) = (bottom(px, py), bottom(qx, qy), top(qx, qy), top(px, py));
+        tris.push([p0, q0, qt]);
+        tris.push([p0, qt, pt]);
+    }
+    tris
+}
+
+// Unit face normal from the winding, zero for degenerate triangles
+// (walls where the terrain touches z = 0)
+fn face_normal([a, b, c]: &Triangle) -> [f32; 3] {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let n = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+    if len > 0.0 {
+        [n[0] / len, n[1] / len, n[2] / len]
+    } else {
+        [0.0; 3]
+    }
+}
+
+// Binary STL of the height-map as a watertight solid, ready for slicing.
+// Units are cells, z_scale is how many cells one unit of height stands for
+pub fn to_stl(map: &HeightMap2D, z_scale: f32, writer: &mut impl Write) -> io::Result<()> {
+    let tris = solid_triangles(map, z_scale);
+    let mut header = [0u8; 80];
+    header[..STL_HEADER.len()].copy_from_slice(STL_HEADER);
+    writer.write_all(&header)?;
+    writer.write_all(&(tris.len() as u32).to_le_bytes())?;
+
+    // 50 bytes per triangle: normal, 3 vertices, 2 byte attribute count
+    let mut buf = Vec::with_capacity(50);
+    for tri in &tris {
+        buf.clear();
+        for v in std::iter::once(&face_normal(tri)).chain(tri) {
+            for c in v {
+                buf.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        writer.write_all(&buf)?;
+    }
+    Ok(())
+}
+
+// Same solid as to_stl() in the much larger ASCII format, handy for debugging
+pub fn to_stl_ascii(map: &HeightMap2D, z_scale: f32, writer: &mut impl Write) -> io::Result<()> {
+    writeln!(writer, "solid terrain")?;
+    for tri in &solid_triangles(map, z_scale) {
+        let [nx, ny, nz] = face_normal(tri);
+        writeln!(writer, "  facet normal {} {} {}", nx, ny, nz)?;
+        writeln!(writer, "    outer loop")?;
+        for [x, y, z] in tri {
+            writeln!(writer, "      vertex {} {} {}", x, y, z)?;
+        }
+        writeln!(writer, "    endloop")?;
+        writeln!(writer, "  endfacet")?;
+    }
+    writeln!(writer, "endsolid terrain")
+}
+
+// Unit vertex normal of the surface y = z_scale * h(x, z), from central
+// differences (one-sided along the border)
+fn vertex_normal(map: &HeightMap2D, z_scale: f32, x: usize, y: usize) -> [f32; 3] {
+    let (w, h) = (map[0].len(), map.len());
+    let slope = |a: f32, b: f32, span: usize| z_scale * (b - a) / span as f32;
+    let (x0, x1) = (x.saturating_sub(1), (x + 1).min(w - 1));
+    let (y0, y1) = (y.saturating_sub(1), (y + 1).min(h - 1));
+    let dx = slope(map[y][x0], map[y][x1], x1 - x0);
+    let dz = slope(map[y0][x], map[y1][x], y1 - y0);
+    let len = (dx * dx + 1.0 + dz * dz).sqrt();
+    [-dx / len, 1.0 / len, -dz / len]
+}
+
+// The terrain surface as an OBJ mesh. Y is up, as most OBJ readers expect:
+// vertices are (x, z_scale * h, y) with x the column and y the row, one
+// position, UV and normal per cell, all sharing the same index
+fn write_obj_mesh(
+    map: &HeightMap2D,
+    z_scale: f32,
+    mtllib: Option<&str>,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(writer, "# Natural Terrain Generator")?;
+    if let Some(mtl) = mtllib {
+        writeln!(writer, "mtllib {}", mtl)?;
+    }
+    let h = map.len();
+    let w = if h > 0 { map[0].len() } else { 0 };
+    if w < 2 || h < 2 {
+        return Ok(());
+    }
+
+    for (y, row) in map.iter().enumerate() {
+        for (x, &v) in row.iter().enumerate() {
+            writeln!(writer, "v {} {} {}", x, z_scale * v, y)?;
+        }
+    }
+    // OBJ texture v runs bottom-up, so row 0 (the image's top row) gets v = 1
+    for y in 0..h {
+        for x in 0..w {
+            let u = x as f32 / (w - 1) as f32;
+            let v = 1.0 - y as f32 / (h - 1) as f32;
+            writeln!(writer, "vt {} {}", u, v)?;
+        }
+    }
+    for y in 0..h {
+        for x in 0..w {
+            let [nx, ny, nz] = vertex_normal(map, z_scale, x, y);
+            writeln!(writer, "vn {} {} {}", nx, ny, nz)?;
+        }
+    }
+
+    if mtllib.is_some() {
+        writeln!(writer, "usemtl {}", MATERIAL_NAME)?;
+    }
+    // 1-based, counter-clockwise seen from above
+    let index = |x: usize, y: usize| y * w + x + 1;
+    for y in 0..h - 1 {
+        for x in 0..w - 1 {
+            let (a, b, c, d) = (
+                index(x, y),
+                index(x + 1, y),
+                index(x + 1, y + 1),
+                index(x, y + 1),
+            );
+            for [i, j, k] in [[a, d, c], [a, c, b]] {
+                writeln!(writer, "f {i}/{i}/{i} {j}/{j}/{j} {k}/{k}/{k}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// The height-map's top surface as a Wavefront OBJ with UVs and normals.
+// Units are cells, z_scale is how many cells one unit of height stands for
+pub fn to_obj(map: &HeightMap2D, z_scale: f32, writer: &mut impl Write) -> io::Result<()> {
+    write_obj_mesh(map, z_scale, None, writer)
+}
+
+// to_obj() plus a material textured with the colour image, for Blender or
+// Unity. The material goes to `mtl_writer`, to be saved as MTL_FILE_NAME
+// with the image as TEXTURE_FILE_NAME, both next to the OBJ
+pub fn to_obj_with_mtl(
+    map: &HeightMap2D,
+    z_scale: f32,
+    obj_writer: &mut impl Write,
+    mtl_writer: &mut impl Write,
+) -> io::Result<()> {
+    write_obj_mesh(map, z_scale, Some(MTL_FILE_NAME), obj_writer)?;
+    writeln!(mtl_writer, "newmtl {}", MATERIAL_NAME)?;
+    writeln!(mtl_writer, "Ka 1 1 1")?;
+    writeln!(mtl_writer, "Kd 1 1 1")?;
+    writeln!(mtl_writer, "Ks 0 0 0")?;
+    writeln!(mtl_writer, "d 1")?;
+    writeln!(mtl_writer, "illum 1")?;
+    writeln!(mtl_writer, "map_Kd {}", TEXTURE_FILE_NAME)
+}
+
+// Single channel "Y" of 32-bit floats, the layout engines such as Unreal
+// import as a heightmap. Heights are written as they are, not normalized
+pub fn to_exr(map: &HeightMap2D, path: &Path) -> exr::error::Result<()> {
+    let h = map.len();
+    let w = if h > 0 { map[0].len() } else { 0 };
+    let samples = FlatSamples::F32(map.iter().flatten().copied().collect());
+    let channels = AnyChannels::sort(vec![AnyChannel::new("Y", samples)].into());
+    let layer = Layer::new(
+        (w, h),
+        LayerAttributes::default(),
+        Encoding::FAST_LOSSLESS,
+        channels,
+    );
+    Image::from_layer(layer).write().to_file(path)
+}
+
+// Read the "Y" channel of the first layer back as heights, any sample type
+pub fn from_exr(path: &Path) -> exr::error::Result<HeightMap2D> {
+    let image = read_first_flat_layer_from_file(path)?;
+    let layer = image.layer_data;
+    let channel = layer
+        .channel_data
+        .list
+        .iter()
+        .find(|c| c.name.eq("Y"))
+        .ok_or_else(|| exr::error::Error::Invalid("no Y channel".into()))?;
+    let w = layer.size.width();
+    let values: Vec<f32> = channel.sample_data.values_as_f32().collect();
+    Ok(values.chunks(w.max(1)).map(<[f32]>::to_vec).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        HeightMap2D, MTL_FILE_NAME, STL_HEADER, TEXTURE_FILE_NAME, from_exr, solid_triangles,
+        to_exr, to_obj, to_obj_with_mtl, to_stl, to_stl_ascii,
+    };
+    use std::collections::HashMap;
+
+    fn bump() -> HeightMap2D {
+        vec![
+            vec![0.1, 0.2, 0.1, 0.0],
+            vec![0.2, 0.9, 0.4, 0.1],
+            vec![0.1, 0.3, 0.2, 0.1],
+        ]
+        .into()
+    }
+
+    #[test]
+    fn stl_binary_header_and_count() {
+        let mut out = Vec::new();
+        to_stl(&bump(), 2.0, &mut out).unwrap();
+        assert_eq!(&out[..STL_HEADER.len()], STL_HEADER);
+        assert!(out[STL_HEADER.len()..80].iter().all(|&b| b == 0));
+
+        // 3×2 quads top and bottom, 2 × (3 + 2) border edges for the walls
+        let count = u32::from_le_bytes([out[80], out[81], out[82], out[83]]);
+        assert_eq!(count, 2 * 6 * 2 + 2 * 10);
+        assert_eq!(out.len(), 84 + 50 * count as usize);
+    }
+
+    #[test]
+    fn stl_solid_is_watertight() {
+        // Every directed edge must be matched by the same edge reversed,
+        // which holds only for a closed, consistently wound surface
+        let key = |v: [f32; 3]| v.map(f32::to_bits);
+        let mut edges: HashMap<_, i32> = HashMap::new();
+        for tri in solid_triangles(&bump(), 2.0) {
+            for i in 0..3 {
+                let (a, b) = (key(tri[i]), key(tri[(i + 1) % 3]));
+                *edges.entry((a, b)).or_default() += 1;
+                *edges.entry((b, a)).or_default() -= 1;
+            }
+        }
+        assert!(edges.values().all(|&n| n == 0));
+    }
+
+    #[test]
+    fn stl_ascii_matches_binary_facets() {
+        let mut text = Vec::new();
+        to_stl_ascii(&bump(), 2.0, &mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.starts_with("solid terrain\n"));
+        assert!(text.trim_end().ends_with("endsolid terrain"));
+        assert_eq!(text.matches("facet normal").count(), 44);
+    }
+
+    #[test]
+    fn obj_with_mtl_loads_in_tobj() {
+        let (mut obj, mut mtl) = (Vec::new(), Vec::new());
+        to_obj_with_mtl(&bump(), 2.0, &mut obj, &mut mtl).unwrap();
+
+        let (models, materials) =
+            tobj::load_obj_buf(&mut obj.as_slice(), &tobj::LoadOptions::default(), |path| {
+                assert_eq!(path.to_str(), Some(MTL_FILE_NAME));
+                tobj::load_mtl_buf(&mut mtl.as_slice())
+            })
+            .unwrap();
+        let materials = materials.unwrap();
+        assert_eq!(materials.len(), 1);
+        assert_eq!(
+            materials[0].diffuse_texture.as_deref(),
+            Some(TEXTURE_FILE_NAME)
+        );
+
+        // 4×3 vertices, 3×2 quads of two triangles
+        let mesh = &models[0].mesh;
+        assert_eq!(mesh.material_id, Some(0));
+        assert_eq!(mesh.positions.len(), 12 * 3);
+        assert_eq!(mesh.texcoords.len(), 12 * 2);
+        assert_eq!(mesh.normals.len(), 12 * 3);
+        assert_eq!(mesh.indices.len(), 12 * 3);
+        assert_eq!(mesh.texcoord_indices, mesh.indices);
+        // Top-left corner is (0, 1) in UV space, bottom-right (1, 0)
+        assert_eq!(&mesh.texcoords[..2], &[0.0, 1.0]);
+        assert_eq!(&mesh.texcoords[22..], &[1.0, 0.0]);
+    }
+
+    #[test]
+    fn obj_faces_point_up() {
+        let mut obj = Vec::new();
+        to_obj(&bump(), 2.0, &mut obj).unwrap();
+        let (models, _) =
+            tobj::load_obj_buf(&mut obj.as_slice(), &tobj::LoadOptions::default(), |_| {
+                unreachable!("to_obj() has no mtllib")
+            })
+            .unwrap();
+        let mesh = &models[0].mesh;
+        let p = |i: u32| {
+            let i = i as usize * 3;
+            [
+                mesh.positions[i],
+                mesh.positions[i + 1],
+                mesh.positions[i + 2],
+            ]
+        };
+        for tri in mesh.indices.chunks_exact(3) {
+            let (a, b, c) = (p(tri[0]), p(tri[1]), p(tri[2]));
+            // y component of (b - a) × (c - a)
+            let ny = (b[2] - a[2]) * (c[0] - a[0]) - (b[0] - a[0]) * (c[2] - a[2]);
+            assert!(ny > 0.0);
+        }
+        assert!(mesh.normals.chunks_exact(3).all(|n| n[1] > 0.0));
+    }
+
+    #[test]
+    fn exr_roundtrip_keeps_raw_floats() {
+        // Raw, un-normalized heights including values outside [0,1]
+        let map: HeightMap2D = (0..5)
+            .map(|y| {
+                (0..7)
+                    .map(|x| (x as f32 * 0.37 - y as f32).sin() * 1e3 + 1e-7)
+                    .collect()
+            })
+            .collect();
+        let path = std::env::temp_dir().join("export_roundtrip.exr");
+        to_exr(&map, &path).unwrap();
+        let back = from_exr(&path);
+        let _ = std::fs::remove_file(&path);
+        let back = back.unwrap();
+        assert_eq!(back.len(), 5);
+        for (row, orig) in back.iter().zip(&map) {
+            let bits = |r: &[f32]| r.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+            assert_eq!(bits(row), bits(orig));
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::encoder::{TiffEncoder, colortype::Gray32Float};
+use tiff::tags::Tag;
+
+use crate::utils::HeightMap2D;
+
+// Metres covered by one degree of latitude (and longitude at the equator)
+const METRES_PER_DEGREE: f64 = 111_320.0;
+
+// GeoTIFF key ids and values (GeoTIFF spec 1.0, section 6)
+const GT_MODEL_TYPE_GEO_KEY: u16 = 1024;
+const GT_RASTER_TYPE_GEO_KEY: u16 = 1025;
+const GEOGRAPHIC_TYPE_GEO_KEY: u16 = 2048;
+const MODEL_TYPE_GEOGRAPHIC: u16 = 2;
+const RASTER_PIXEL_IS_AREA: u16 = 1;
+const EPSG_WGS84: u16 = 4326;
+
+fn to_io_error(e: tiff::TiffError) -> io::Error {
+    io::Error::other(e)
+}
+
+// Write the height-map as a single channel 32-bit float GeoTIFF
+// in WGS84 (EPSG:4326), readable by QGIS / ArcGIS / GDAL
+// pixel_size_m - ground size of one cell in metres
+// origin - (longitude, latitude) in degrees of the top-left corner
+pub fn to_geotiff(
+    map: &HeightMap2D,
+    path: &Path,
+    pixel_size_m: f64,
+    origin: (f64, f64),
+) -> io::Result<()> {
+    let h = map.len();
+    let w = if h > 0 { map[0].len() } else { 0 };
+    let (lon, lat) = origin;
+
+    // WGS84 is in degrees, so convert the metre cell size.
+    // A degree of longitude shrinks with cos(latitude)
+    let scale_y = pixel_size_m / METRES_PER_DEGREE;
+    let scale_x = scale_y / lat.to_radians().cos().max(1e-6);
+
+    // Together the pixel scale and tie point form the GeoTransform:
+    // lon = origin.0 + col * scale_x, lat = origin.1 - row * scale_y
+    let pixel_scale = [scale_x, scale_y, 0.0];
+    let tie_point = [0.0, 0.0, 0.0, lon, lat, 0.0];
+    // Header: directory version, revision, minor revision, number of keys
+    // then one (key id, tag location, count, value) row per key
+    #[rustfmt::skip]
+    let geo_keys: [u16; 16] = [
+        1, 1, 0, 3,
+        GT_MODEL_TYPE_GEO_KEY, 0, 1, MODEL_TYPE_GEOGRAPHIC,
+        GT_RASTER_TYPE_GEO_KEY, 0, 1, RASTER_PIXEL_IS_AREA,
+        GEOGRAPHIC_TYPE_GEO_KEY, 0, 1, EPSG_WGS84,
+    ];
+
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = TiffEncoder::new(writer).map_err(to_io_error)?;
+    let mut image = encoder
+        .new_image::<Gray32Float>(w as u32, h as u32)
+        .map_err(to_io_error)?;
+    let dir = image.encoder();
+    dir.write_tag(Tag::ModelPixelScaleTag, &pixel_scale[..])
+        .map_err(to_io_error)?;
+    dir.write_tag(Tag::ModelTiepointTag, &tie_point[..])
+        .map_err(to_io_error)?;
+    dir.write_tag(Tag::GeoKeyDirectoryTag, &geo_keys[..])
+        .map_err(to_io_error)?;
+
+    let flat: Vec<f32> = map.iter().flat_map(|row| row.iter().cloned()).collect();
+    image.write_data(&flat).map_err(to_io_error)
+}
+
+// Read the first sample of the first channel of a (Geo)TIFF into a height-map
+// Geo metadata is ignored, integer samples are returned as raw values
+pub fn from_geotiff(path: &Path) -> io::Result<HeightMap2D> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut decoder = Decoder::new(reader).map_err(to_io_error)?;
+    let (w, h) = decoder.dimensions().map_err(to_io_error)?;
+    let (w, h) = (w as usize, h as usize);
+
+    // Number of interleaved samples per pixel, we only keep the first
+    let channels = match decoder.colortype().map_err(to_io_error)? {
+        tiff::ColorType::GrayA(_) => 2,
+        tiff::ColorType::RGB(_) | tiff::ColorType::YCbCr(_) => 3,
+        tiff::ColorType::RGBA(_) | tiff::ColorType::CMYK(_) => 4,
+        _ => 1,
+    };
+
+    let samples: Vec<f32> = match decoder.read_image().map_err(to_io_error)? {
+        DecodingResult::U8(v) => v.into_iter().map(|s| s as f32).collect(),
+        DecodingResult::U16(v) => v.into_iter().map(|s| s as f32).collect(),
+        DecodingResult::U32(v) => v.into_iter().map(|s| s as f32).collect(),
+        DecodingResult::U64(v) => v.into_iter().map(|s| s as f32).collect(),
+        DecodingResult::F32(v) => v,
+        DecodingResult::F64(v) => v.into_iter().map(|s| s as f32).collect(),
+        DecodingResult::I8(v) => v.into_iter().map(|s| s as f32).collect(),
+        DecodingResult::I16(v) => v.into_iter().map(|s| s as f32).collect(),
+        DecodingResult::I32(v) => v.into_iter().map(|s| s as f32).collect(),
+        DecodingResult::I64(v) => v.into_iter().map(|s| s as f32).collect(),
+    };
+
+    if samples.len() < w * h * channels {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "TIFF holds fewer samples than its dimensions",
+        ));
+    }
+
+    Ok((0..h)
+        .map(|y| (0..w).map(|x| samples[(y * w + x) * channels]).collect())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeightMap2D, from_geotiff, to_geotiff};
+
+    #[test]
+    fn geotiff_roundtrip_ramp() {
+        let map: HeightMap2D = (0..33)
+            .map(|y| (0..33).map(|x| (x + y) as f32 / 64.0).collect())
+            .collect();
+        let path = std::env::temp_dir().join("core_geotiff_roundtrip.tif");
+
+        to_geotiff(&map, &path, 30.0, (-3.2, 55.9)).unwrap();
+        let read = from_geotiff(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(read, map);
+    }
+}
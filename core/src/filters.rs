@@ -0,0 +1,117 @@
+use crate::utils::HeightMap2D;
+
+// Post-processing filters that reshape an already generated height-map
+// They expect heights in [0.0, 1.0], so run them after normalize2()
+
+// Uniform terracing: quantize heights into equally spaced steps
+pub struct Terrace {
+    num_steps: u32, // number of flat plateaus
+    smoothing: f32, // 0 = hard steps, 1 = original terrain
+}
+
+impl Terrace {
+    pub fn new(num_steps: u32, smoothing: f32) -> Self {
+        Self {
+            num_steps,
+            smoothing: smoothing.clamp(0.0, 1.0),
+        }
+    }
+
+    // In-place quantize every height to floor(h * n) / n
+    // and blend the step back towards the original height
+    pub fn apply(&self, map: &mut HeightMap2D) {
+        // Zero steps would divide by zero, leave the map untouched
+        if self.num_steps == 0 {
+            return;
+        }
+        let steps = self.num_steps as f32;
+
+        for row in map.iter_mut() {
+            for val in row.iter_mut() {
+                let stepped = (*val * steps).floor() / steps;
+                *val = stepped + (*val - stepped) * self.smoothing;
+            }
+        }
+    }
+}
+
+// Non-uniform terracing: plateaus sit at user-chosen control heights
+pub struct TerraceCurve {
+    points: Vec<f32>, // sorted control heights
+}
+
+impl TerraceCurve {
+    // points - control heights, e.g. [0.0, 0.3, 0.35, 0.7, 1.0]
+    // Closely spaced points give steep cliffs, distant points wide slopes
+    pub fn new(mut points: Vec<f32>) -> Self {
+        points.sort_by(|a, b| a.total_cmp(b));
+        points.dedup();
+        Self { points }
+    }
+
+    // Map a single height onto the curve
+    fn terrace(&self, h: f32) -> f32 {
+        // Need at least one segment to interpolate along
+        if self.points.len() < 2 {
+            return h;
+        }
+
+        // Find the first control point above h (clamped to a valid segment)
+        let upper = self
+            .points
+            .iter()
+            .position(|&p| p > h)
+            .unwrap_or(self.points.len() - 1)
+            .max(1);
+        let lo = self.points[upper - 1];
+        let hi = self.points[upper];
+
+        // Position within the segment, squared so the terrain
+        // stays flat near the lower point and rises sharply at the upper one
+        let t = ((h - lo) / (hi - lo)).clamp(0.0, 1.0);
+        lo + (hi - lo) * t * t
+    }
+
+    // In-place apply the curve to the height-map
+    pub fn apply(&self, map: &mut HeightMap2D) {
+        for row in map.iter_mut() {
+            for val in row.iter_mut() {
+                *val = self.terrace(*val);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeightMap2D, Terrace, TerraceCurve};
+
+    #[test]
+    fn terrace_hard_steps() {
+        let mut map: HeightMap2D = vec![vec![0.05, 0.3, 0.55, 0.99]].into();
+        Terrace::new(4, 0.0).apply(&mut map);
+        assert_eq!(map, vec![vec![0.0, 0.25, 0.5, 0.75]]);
+    }
+
+    #[test]
+    fn terrace_full_smoothing_is_passthrough() {
+        let original: HeightMap2D = vec![vec![0.1, 0.42, 0.77], vec![0.0, 0.5, 1.0]].into();
+        let mut map = original.clone();
+        Terrace::new(5, 1.0).apply(&mut map);
+        for (a, b) in map.iter().flatten().zip(original.iter().flatten()) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn terrace_curve_keeps_control_points() {
+        let curve = TerraceCurve::new(vec![1.0, 0.0, 0.5]);
+        let mut map: HeightMap2D = vec![vec![0.0, 0.5, 1.0, 0.25]].into();
+        curve.apply(&mut map);
+        assert_eq!(map[0][0], 0.0);
+        assert_eq!(map[0][1], 0.5);
+        assert_eq!(map[0][2], 1.0);
+        // Halfway through a segment lands a quarter of the way up
+        assert!((map[0][3] - 0.125).abs() < 1e-6);
+    }
+}
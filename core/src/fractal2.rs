@@ -1,14 +1,49 @@
+use serde::{Deserialize, Serialize};
+
 use crate::NoiseGenerator;
-use crate::utils::HeightMap2D;
+use crate::rng::XorShiftRng;
+use crate::utils::{HeightMap2D, crop, sample_bilinear};
 
 // 2D fractal terrain generator using the Diamond–Square algorithm
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "Fractal2DParams")]
 pub struct Fractal2D {
     size: usize, // must be 2^n + 1, e.g. 129, 257
     seed: u64,
     roughness: f64, // controls how much random offset decreases each step
+    tileable: bool, // wrap the square step so opposite edges match
+    // Local roughness, stretched over the whole terrain, replaces `roughness` when set
+    roughness_map: Option<HeightMap2D>,
+    #[serde(skip)]
     map: HeightMap2D,
 }
 
+// What gets serialized for a Fractal2D. The height-map is regenerated
+// on load, so get2() works straight away and matches the original
+#[derive(Deserialize)]
+struct Fractal2DParams {
+    size: usize,
+    seed: u64,
+    roughness: f64,
+    tileable: bool,
+    roughness_map: Option<HeightMap2D>,
+}
+
+// Checked here, new() would panic on a bad size
+impl TryFrom<Fractal2DParams> for Fractal2D {
+    type Error = String;
+
+    fn try_from(p: Fractal2DParams) -> Result<Self, String> {
+        if p.size < 3 || !(p.size - 1).is_power_of_two() {
+            return Err(format!("size must be 2^n+1, got {}", p.size));
+        }
+        let mut fractal = Fractal2D::new(p.size, p.seed, p.roughness).with_tileable(p.tileable);
+        fractal.roughness_map = p.roughness_map;
+        fractal.generate();
+        Ok(fractal)
+    }
+}
+
 impl Fractal2D {
     pub fn new(size: usize, seed: u64, roughness: f64) -> Self {
         assert!(
@@ -20,38 +55,64 @@ impl Fractal2D {
             size,
             seed,
             roughness,
-            map: vec![vec![0.0f32; size]; size],
+            tileable: false,
+            roughness_map: None,
+            map: HeightMap2D::filled(size, size, 0.0),
         }
     }
 
+    // Edge midpoints wrap around to the opposite edge in the square step,
+    // so the first row/column equals the last and the map tiles seamlessly
+    pub fn with_tileable(mut self, tileable: bool) -> Self {
+        self.tileable = tileable;
+        self
+    }
+
+    // Vary roughness across the terrain, e.g. high in mountains and low in plains.
+    // The map may be any size, it is sampled bilinearly at each new point, and at
+    // step k the random offset there is r^(1.5 k). So 0 gives smooth interpolation
+    // between the corners and 1 keeps the full offset at every step
+    pub fn with_roughness_map(mut self, map: HeightMap2D) -> Self {
+        self.roughness_map = Some(map);
+        self
+    }
+
     // Generate and return a size×size height‐map with values in roughly [−1, +1]
     pub fn generate(&mut self) -> HeightMap2D {
-        let mut map = vec![vec![0.0f32; self.size]; self.size];
-        // Simple xorshift RNG for reproducible randomness
-        let mut x = self.seed ^ 0xCAFEBABE12345678;
-        let mut rng = || {
-            x ^= x << 13;
-            x ^= x >> 7;
-            x ^= x << 17;
-            // map to [−1, +1]
-            ((x as f64 / u64::MAX as f64) * 2.0 - 1.0) as f32
-        };
+        let mut map = HeightMap2D::filled(self.size, self.size, 0.0);
+        // Simple xorshift RNG for reproducible randomness, in [−1, +1]
+        let mut xorshift = XorShiftRng::new(self.seed ^ 0xCAFEBABE12345678);
+        let mut rng = || xorshift.next_f64_signed() as f32;
 
         // Initialize corners
         map[0][0] = rng();
         map[0][self.size - 1] = rng();
         map[self.size - 1][0] = rng();
         map[self.size - 1][self.size - 1] = rng();
+        if self.tileable {
+            // All four corners are the same point on a torus
+            let corner = map[0][0];
+            map[0][self.size - 1] = corner;
+            map[self.size - 1][0] = corner;
+            map[self.size - 1][self.size - 1] = corner;
+        }
+        // Period of the wrapped grid, the last row/column repeats the first
+        let period = self.size - 1;
 
         // Step is the current distance between 2 know points
         let mut step = self.size - 1;
         // Offset is the amplitude of the random noise
         // It decreases with each step to create a fractal pattern
         let mut offset = 1.0;
+        // Subdivision level, only used with a roughness map
+        let mut level = 0;
 
         while step > 1 {
             // Distance to the center from a corner
             let half = step / 2;
+            level += 1;
+            // Noise amplitude at (x, y) for this step
+            let local_offset = |x: usize, y: usize| self.local_offset(x, y, level, offset, period);
 
             // Diamond step
             for y in (0..self.size - 1).step_by(step) {
@@ -61,13 +122,33 @@ impl Fractal2D {
                         (map[y][x] + map[y][x + step] + map[y + step][x] + map[y + step][x + step])
                             * 0.25;
                     // Set the center point to the average plus some random offset
-                    map[y + half][x + half] = avg + rng() * offset;
+                    map[y + half][x + half] = avg + rng() * local_offset(x + half, y + half);
                 }
             }
 
-            // Square step
+            // Square step: edge midpoints, which sit half a step off the
+            // lattice in x on even rows and on the lattice on odd rows
             for y in (0..self.size).step_by(half) {
-                for x in (0..self.size).step_by(step) {
+                let x_start = if (y / half).is_multiple_of(2) {
+                    half
+                } else {
+                    0
+                };
+                for x in (x_start..self.size).step_by(step) {
+                    if self.tileable {
+                        // The last row/column is copied from the first below
+                        if x == period || y == period {
+                            continue;
+                        }
+                        let avg = (map[y][(x + period - half) % period]
+                            + map[y][(x + half) % period]
+                            + map[(y + period - half) % period][x]
+                            + map[(y + half) % period][x])
+                            * 0.25;
+                        map[y][x] = avg + rng() * local_offset(x, y);
+                        continue;
+                    }
+
                     let mut sum = 0.0;
                     let mut cnt = 0;
                     if x >= half {
@@ -87,8 +168,15 @@ impl Fractal2D {
                         cnt += 1;
                     }
                     let avg = sum / cnt as f32;
-                    map[y][x] = avg + rng() * offset;
+                    map[y][x] = avg + rng() * local_offset(x, y);
+                }
+            }
+
+            if self.tileable {
+                for row in map.iter_mut() {
+                    row[period] = row[0];
                 }
+                map[period] = map[0].clone();
             }
 
             step = half;
@@ -99,6 +187,221 @@ impl Fractal2D {
         self.map = map.clone();
         map
     }
+
+    // Noise amplitude at (x, y) on subdivision `level`, `offset` unless a
+    // roughness map is set. `period` is the map size - 1
+    fn local_offset(&self, x: usize, y: usize, level: u32, offset: f32, period: usize) -> f32 {
+        match &self.roughness_map {
+            Some(rough) => {
+                let scale_x = (rough[0].len() - 1) as f32 / period as f32;
+                let scale_y = (rough.len() - 1) as f32 / period as f32;
+                let r = sample_bilinear(rough, x as f32 * scale_x, y as f32 * scale_y);
+                r.max(0.0).powf(1.5 * level as f32)
+            }
+            None => offset,
+        }
+    }
+
+    // The width × height block of generate() starting at (x, y). The whole
+    // map is still computed, as every point depends on the ones before it
+    pub fn generate_region(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> HeightMap2D {
+        let map = self.generate();
+        crop(&map, x, y, width, height)
+    }
+
+    // A width × height block at full resolution without computing the rest.
+    // A coarse 65×65 terrain fixes the shape of the whole map, then only the
+    // coarse cells under the block are subdivided. Each cell is refined on its
+    // own with noise hashed from the position, and its edge points only look
+    // along the edge, so neighbouring blocks agree wherever they overlap.
+    // Not the same terrain as generate(), whose random offsets come in sequence
+    pub fn generate_hierarchical(
+        &self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> HeightMap2D {
+        assert!(
+            x + width <= self.size && y + height <= self.size,
+            "region must lie inside the map"
+        );
+        if self.size <= COARSE_SIZE {
+            return self.clone().generate_region(x, y, width, height);
+        }
+
+        let mut coarse =
+            Fractal2D::new(COARSE_SIZE, self.seed, self.roughness).with_tileable(self.tileable);
+        coarse.roughness_map = self.roughness_map.clone();
+        let coarse = coarse.generate();
+
+        let mut out = HeightMap2D::filled(width, height, 0.0);
+        if width == 0 || height == 0 {
+            return out;
+        }
+        let cell = (self.size - 1) / (COARSE_SIZE - 1);
+        let last = COARSE_SIZE - 2;
+        for cy in (y / cell).min(last)..=((y + height - 1) / cell).min(last) {
+            for cx in (x / cell).min(last)..=((x + width - 1) / cell).min(last) {
+                let patch = self.refine_cell(&coarse, cx, cy, cell);
+                for (py, row) in patch.iter().enumerate() {
+                    let gy = cy * cell + py;
+                    if gy < y || gy >= y + height {
+                        continue;
+                    }
+                    for (px, &h) in row.iter().enumerate() {
+                        let gx = cx * cell + px;
+                        if gx >= x && gx < x + width {
+                            out[(gx - x, gy - y)] = h;
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    // Diamond-square inside coarse cell (cx, cy), `cell` + 1 points per side
+    // with the coarse values at the corners
+    fn refine_cell(&self, coarse: &HeightMap2D, cx: usize, cy: usize, cell: usize) -> HeightMap2D {
+        let period = self.size - 1;
+        let (ox, oy) = (cx * cell, cy * cell);
+        // Same noise for the same map point whichever cell computes it
+        let noise = |x: usize, y: usize| {
+            let (gx, gy) = (ox + x, oy + y);
+            if self.tileable {
+                point_noise(self.seed, gx % period, gy % period)
+            } else {
+                point_noise(self.seed, gx, gy)
+            }
+        };
+
+        let mut patch = HeightMap2D::filled(cell + 1, cell + 1, 0.0);
+        patch[(0, 0)] = coarse[(cx, cy)];
+        patch[(cell, 0)] = coarse[(cx + 1, cy)];
+        patch[(0, cell)] = coarse[(cx, cy + 1)];
+        patch[(cell, cell)] = coarse[(cx + 1, cy + 1)];
+
+        // Carry on from where the coarse pass stopped
+        let mut level = (COARSE_SIZE - 1).trailing_zeros();
+        let mut offset = self.roughness.powf(1.5 * level as f64) as f32;
+        let mut step = cell;
+        while step > 1 {
+            let half = step / 2;
+            level += 1;
+
+            // Diamond step, every centre is inside the cell
+            for y in (0..cell).step_by(step) {
+                for x in (0..cell).step_by(step) {
+                    let avg = (patch[(x, y)]
+                        + patch[(x + step, y)]
+                        + patch[(x, y + step)]
+                        + patch[(x + step, y + step)])
+                        * 0.25;
+                    let amplitude =
+                        self.local_offset(ox + x + half, oy + y + half, level, offset, period);
+                    patch[(x + half, y + half)] = avg + noise(x + half, y + half) * amplitude;
+                }
+            }
+
+            // Square step. Points on the cell's edge average only their two
+            // neighbours along it, which the cell next door shares
+            for y in (0..=cell).step_by(half) {
+                let x_start = if (y / half).is_multiple_of(2) {
+                    half
+                } else {
+                    0
+                };
+                for x in (x_start..=cell).step_by(step) {
+                    let avg = if y == 0 || y == cell {
+                        (patch[(x - half, y)] + patch[(x + half, y)]) * 0.5
+                    } else if x == 0 || x == cell {
+                        (patch[(x, y - half)] + patch[(x, y + half)]) * 0.5
+                    } else {
+                        (patch[(x - half, y)]
+                            + patch[(x + half, y)]
+                            + patch[(x, y - half)]
+                            + patch[(x, y + half)])
+                            * 0.25
+                    };
+                    let amplitude = self.local_offset(ox + x, oy + y, level, offset, period);
+                    patch[(x, y)] = avg + noise(x, y) * amplitude;
+                }
+            }
+
+            step = half;
+            offset *= self.roughness.powf(1.5) as f32;
+        }
+        patch
+    }
+}
+
+// Side of the coarse terrain generate_hierarchical() starts from
+const COARSE_SIZE: usize = 65;
+
+// Random offset in [-1, 1] for map point (x, y), splitmix64 over the position
+fn point_noise(seed: u64, x: usize, y: usize) -> f32 {
+    let mut z = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    ((z as f64 / u64::MAX as f64) * 2.0 - 1.0) as f32
+}
+
+// Named-argument alternative to Fractal2D::new(), e.g.
+// Fractal2DBuilder::default().size(257).roughness(0.8).build()
+pub struct Fractal2DBuilder {
+    size: usize,
+    seed: u64,
+    roughness: f64,
+    tileable: bool,
+}
+
+impl Default for Fractal2DBuilder {
+    // Same defaults as the app sliders (2^7 + 1 = 129)
+    fn default() -> Self {
+        Self {
+            size: 129,
+            seed: 2025,
+            roughness: 1.0,
+            tileable: false,
+        }
+    }
+}
+
+impl Fractal2DBuilder {
+    // must be 2^n + 1, checked in build()
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn roughness(mut self, roughness: f64) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    pub fn tileable(mut self, tileable: bool) -> Self {
+        self.tileable = tileable;
+        self
+    }
+
+    pub fn build(self) -> Fractal2D {
+        Fractal2D::new(self.size, self.seed, self.roughness).with_tileable(self.tileable)
+    }
 }
 
 impl NoiseGenerator for Fractal2D {
@@ -106,15 +409,13 @@ impl NoiseGenerator for Fractal2D {
         // Use bilinear sampling from a pre-generated map:
         let fx = x * (self.size - 1) as f64;
         let fy = y * (self.size - 1) as f64;
-        let xi = fx.floor() as usize;
-        let yi = fy.floor() as usize;
+        // Clamp to the last cell so samples at x or y = 1.0 use the edge
+        // row/column instead of falling off the map
+        let xi = (fx.floor() as usize).min(self.size - 2);
+        let yi = (fy.floor() as usize).min(self.size - 2);
 
-        if xi + 1 >= self.size || yi + 1 >= self.size {
-            return 0.0;
-        }
-
-        let tx = (fx - xi as f64) as f32;
-        let ty = (fy - yi as f64) as f32;
+        let tx = (fx - xi as f64).clamp(0.0, 1.0) as f32;
+        let ty = (fy - yi as f64).clamp(0.0, 1.0) as f32;
 
         let a = self.map[yi][xi];
         let b = self.map[yi][xi + 1];
@@ -131,7 +432,8 @@ impl NoiseGenerator for Fractal2D {
 
 #[cfg(test)]
 mod tests {
-    use super::Fractal2D;
+    use super::{Fractal2D, Fractal2DBuilder, HeightMap2D};
+    use crate::NoiseGenerator;
 
     #[test]
     fn fractal2_dimensions() {
@@ -148,14 +450,161 @@ mod tests {
         assert_eq!(f1.generate(), f2.generate());
     }
 
+    #[test]
+    fn fractal2_builder_matches_new() {
+        let mut built = Fractal2DBuilder::default()
+            .size(33)
+            .seed(9)
+            .roughness(0.7)
+            .build();
+        let mut direct = Fractal2D::new(33, 9, 0.7);
+        assert_eq!(built.generate(), direct.generate());
+    }
+
+    #[test]
+    fn fractal2_get2_near_right_edge() {
+        let mut f = Fractal2D::new(33, 5, 0.7);
+        f.generate();
+        let v = f.get2(1.0 - f64::EPSILON, 0.5);
+        assert!(v.is_finite());
+        assert_ne!(v, 0.0);
+    }
+
+    #[test]
+    fn fractal2_get2_boundary_uses_edge_cells() {
+        // Used to return 0.0 for anything on the right/bottom edge
+        let mut f = Fractal2D::new(17, 12, 0.9);
+        let m = f.generate();
+        assert_eq!(f.get2(1.0, 1.0), m[16][16] as f64);
+        assert_eq!(f.get2(1.0, 0.0), m[0][16] as f64);
+        assert_eq!(f.get2(0.0, 1.0), m[16][0] as f64);
+    }
+
     #[test]
     fn fractal2_value_range() {
         let mut f = Fractal2D::new(33, 7, 0.5);
         let m = f.generate();
         for row in &m {
             for &v in row {
-                assert!(v >= -2.0 && v <= 2.0, "value {} out of expected range", v);
+                assert!(
+                    (-2.0..=2.0).contains(&v),
+                    "value {} out of expected range",
+                    v
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fractal2_tiling_left_right_match() {
+        let mut f = Fractal2DBuilder::default()
+            .size(65)
+            .seed(3)
+            .roughness(0.8)
+            .tileable(true)
+            .build();
+        let m = f.generate();
+        for row in &m {
+            assert_eq!(row[0], row[64]);
+        }
+        assert_eq!(m[0], m[64]);
+        // Wrapping changes the result, not just the edges
+        assert_ne!(m, Fractal2D::new(65, 3, 0.8).generate());
+    }
+
+    #[test]
+    fn fractal2_roughness_map() {
+        fn spread(m: &[Vec<f32>]) -> f32 {
+            let n = (m.len() * m.len()) as f32;
+            let mean = m.iter().flatten().sum::<f32>() / n;
+            (m.iter().flatten().map(|v| (v - mean).powi(2)).sum::<f32>() / n).sqrt()
+        }
+
+        // Zero roughness adds no offsets, only the corners are random and
+        // every other cell is a weighted average that stays between them
+        let mut smooth =
+            Fractal2D::new(17, 5, 1.0).with_roughness_map(HeightMap2D::filled(4, 4, 0.0));
+        let m = smooth.generate();
+        let corners = [m[0][0], m[0][16], m[16][0], m[16][16]];
+        let lo = corners.iter().copied().fold(f32::MAX, f32::min) - 1e-6;
+        let hi = corners.iter().copied().fold(f32::MIN, f32::max) + 1e-6;
+        assert!(m.iter().flatten().all(|v| (lo..=hi).contains(v)));
+
+        let baseline = spread(&Fractal2D::new(33, 5, 1.0).generate());
+        let mut rough =
+            Fractal2D::new(33, 5, 1.0).with_roughness_map(HeightMap2D::filled(2, 2, 2.0));
+        assert!(spread(&rough.generate()) > baseline);
+    }
+
+    #[test]
+    fn fractal2_serde_roundtrip() {
+        let mut original = Fractal2D::new(33, 12, 0.8).with_tileable(true);
+        original.generate();
+        let json = serde_json::to_string(&original).unwrap();
+        assert!(!json.contains("\"map\""));
+
+        let loaded: Fractal2D = serde_json::from_str(&json).unwrap();
+        for &(x, y) in &[(0.1, 0.2), (0.5, 0.5), (0.77, 0.31)] {
+            assert_eq!(original.get2(x, y), loaded.get2(x, y));
+        }
+    }
+
+    #[test]
+    fn fractal2_deserialize_rejects_bad_size() {
+        let json = r#"{"size":100,"seed":1,"roughness":1.0,"tileable":false,"roughness_map":null}"#;
+        let err = serde_json::from_str::<Fractal2D>(json).err().unwrap();
+        assert!(err.to_string().contains("2^n+1"));
+    }
+
+    #[test]
+    fn fractal2_clone_copies_map() {
+        let mut original = Fractal2D::new(17, 6, 0.9);
+        original.generate();
+        let mut copy = original.clone();
+        assert_eq!(copy.get2(0.3, 0.6), original.get2(0.3, 0.6));
+
+        // Regenerating the clone with another roughness must not touch the original's map
+        let before = original.get2(0.3, 0.6);
+        copy.roughness = 0.2;
+        copy.generate();
+        assert_ne!(copy.get2(0.3, 0.6), before);
+        assert_eq!(original.get2(0.3, 0.6), before);
+    }
+
+    #[test]
+    fn fractal2_region_matches_full_map() {
+        let mut fractal = Fractal2D::new(129, 61, 0.8);
+        let full = fractal.generate();
+        let region = fractal.generate_region(20, 90, 50, 39);
+        assert_eq!(region.len(), 39);
+        for (y, row) in region.iter().enumerate() {
+            assert_eq!(row[..], full[90 + y][20..70]);
+        }
+    }
+
+    #[test]
+    fn fractal2_hierarchical_regions_agree() {
+        let fractal = Fractal2D::new(257, 8, 0.9);
+        let full = fractal.generate_hierarchical(0, 0, 257, 257);
+        // Blocks that don't line up with the coarse cells, one on the far edge
+        for (x, y, w, h) in [(37, 101, 60, 45), (200, 250, 57, 7)] {
+            let region = fractal.generate_hierarchical(x, y, w, h);
+            for (ry, row) in region.iter().enumerate() {
+                assert_eq!(row[..], full[y + ry][x..x + w], "row {}", y + ry);
+            }
+        }
+        // Every 4th point is the coarse 65×65 terrain
+        let coarse = Fractal2D::new(65, 8, 0.9).generate();
+        for (cy, row) in coarse.iter().enumerate() {
+            for (cx, &h) in row.iter().enumerate() {
+                assert_eq!(full[(cx * 4, cy * 4)], h);
             }
         }
+        // Refinement adds detail, midpoints are off the straight line between
+        let bumps = (0..64).filter(|&k| {
+            let line = (full[(k * 4, 8)] + full[(k * 4 + 4, 8)]) * 0.5;
+            (full[(k * 4 + 2, 8)] - line).abs() > 1e-4
+        });
+        assert!(bumps.count() > 48);
     }
 }
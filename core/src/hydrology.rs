@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use crate::utils::HeightMap2D;
+
+// 4-connected neighbours of (x, y) inside a w×h map
+fn neighbours(x: usize, y: usize, w: usize, h: usize) -> impl Iterator<Item = (usize, usize)> {
+    [(0, -1), (1, 0), (0, 1), (-1, 0)]
+        .into_iter()
+        .map(move |(dx, dy)| (x as isize + dx, y as isize + dy))
+        .filter(move |&(nx, ny)| nx >= 0 && ny >= 0 && nx < w as isize && ny < h as isize)
+        .map(|(nx, ny)| (nx as usize, ny as usize))
+}
+
+// Lowest of the 8 neighbours of (x, y), if it is strictly lower than the cell itself
+pub(crate) fn downhill_neighbour(map: &HeightMap2D, x: usize, y: usize) -> Option<(usize, usize)> {
+    let h = map.len();
+    let w = map[0].len();
+    let window = (y.saturating_sub(1)..(y + 2).min(h))
+        .flat_map(|ny| (x.saturating_sub(1)..(x + 2).min(w)).map(move |nx| (nx, ny)));
+    let mut best = None;
+    let mut lowest = map[y][x];
+    for (nx, ny) in window {
+        if map[ny][nx] < lowest {
+            lowest = map[ny][nx];
+            best = Some((nx, ny));
+        }
+    }
+    best
+}
+
+// D8 flow accumulation: every cell drains into its lowest neighbour, and the
+// result counts how many cells (itself included) drain through each cell
+pub fn flow_accumulation(map: &HeightMap2D) -> Vec<Vec<usize>> {
+    let h = map.len();
+    let w = map.first().map_or(0, Vec::len);
+    let mut flow = vec![vec![1; w]; h];
+
+    // Highest cells first, so each cell is complete before it passes its flow on
+    let mut order: Vec<(usize, usize)> = (0..h).flat_map(|y| (0..w).map(move |x| (x, y))).collect();
+    order.sort_by(|&(ax, ay), &(bx, by)| map[by][bx].total_cmp(&map[ay][ax]));
+    for (x, y) in order {
+        if let Some((nx, ny)) = downhill_neighbour(map, x, y) {
+            flow[ny][nx] += flow[y][x];
+        }
+    }
+    flow
+}
+
+// Flood every cell at or below `sea_level` from `seeds`, marking them in `flooded`.
+// Returns the cells reached, in BFS order
+fn flood(
+    map: &HeightMap2D,
+    sea_level: f32,
+    seeds: impl IntoIterator<Item = (usize, usize)>,
+    flooded: &mut [Vec<bool>],
+) -> Vec<(usize, usize)> {
+    let h = map.len();
+    let w = map[0].len();
+    let mut queue = VecDeque::new();
+    for (x, y) in seeds {
+        if !flooded[y][x] && map[y][x] <= sea_level {
+            flooded[y][x] = true;
+            queue.push_back((x, y));
+        }
+    }
+
+    let mut reached = Vec::new();
+    while let Some((x, y)) = queue.pop_front() {
+        reached.push((x, y));
+        for (nx, ny) in neighbours(x, y, w, h) {
+            if !flooded[ny][nx] && map[ny][nx] <= sea_level {
+                flooded[ny][nx] = true;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    reached
+}
+
+// Cells the sea reaches: BFS from every border cell at or below `sea_level`.
+// Basins cut off from the border by higher ground stay dry, see find_interior_lakes()
+pub fn flood_fill_water(map: &HeightMap2D, sea_level: f32) -> Vec<Vec<bool>> {
+    let h = map.len();
+    let w = map.first().map_or(0, Vec::len);
+    let mut flooded = vec![vec![false; w]; h];
+    if w == 0 {
+        return flooded;
+    }
+
+    let border = (0..w)
+        .flat_map(|x| [(x, 0), (x, h - 1)])
+        .chain((0..h).flat_map(|y| [(0, y), (w - 1, y)]));
+    flood(map, sea_level, border, &mut flooded);
+    flooded
+}
+
+// Connected groups of cells at or below `sea_level` that the sea can't reach,
+// i.e. depressions that would hold a lake. Each lake lists its cells as (x, y)
+pub fn find_interior_lakes(map: &HeightMap2D, sea_level: f32) -> Vec<Vec<(usize, usize)>> {
+    // Start from the sea so border-connected water is skipped
+    let mut visited = flood_fill_water(map, sea_level);
+    let mut lakes = Vec::new();
+    for y in 0..map.len() {
+        for x in 0..map[y].len() {
+            let lake = flood(map, sea_level, [(x, y)], &mut visited);
+            if !lake.is_empty() {
+                lakes.push(lake);
+            }
+        }
+    }
+    lakes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_interior_lakes, flood_fill_water, flow_accumulation};
+    use crate::utils::HeightMap2D;
+
+    // 9×9 bowl: lowest in the center, rising towards the border
+    fn bowl() -> HeightMap2D {
+        (0..9)
+            .map(|y| {
+                (0..9)
+                    .map(|x| ((x as f32 - 4.0).powi(2) + (y as f32 - 4.0).powi(2)).sqrt() / 4.0)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bowl_is_an_interior_lake() {
+        let map = bowl();
+        let sea = flood_fill_water(&map, 0.5);
+        assert!(sea.iter().flatten().all(|&f| !f));
+
+        let lakes = find_interior_lakes(&map, 0.5);
+        assert_eq!(lakes.len(), 1);
+        // Every cell within 2 of the center is under water
+        assert_eq!(lakes[0].len(), 13);
+        assert!(lakes[0].contains(&(4, 4)));
+    }
+
+    #[test]
+    fn breached_bowl_drains_to_the_sea() {
+        let mut map = bowl();
+        // Cut a channel from the center to the left border
+        for cell in &mut map[4][..4] {
+            *cell = 0.1;
+        }
+        let sea = flood_fill_water(&map, 0.5);
+        assert!(sea[4][0] && sea[4][4]);
+        assert!(!sea[0][0]);
+        assert!(find_interior_lakes(&map, 0.5).is_empty());
+
+        // Everything floods above the highest cell
+        assert!(flood_fill_water(&map, 2.0).iter().flatten().all(|&f| f));
+    }
+
+    #[test]
+    fn flow_accumulates_downhill() {
+        // Tilted plane sloping down towards x = 0
+        let map: HeightMap2D = (0..3)
+            .map(|y| (0..4).map(|x| x as f32 + y as f32 * 0.1).collect())
+            .collect();
+        let flow = flow_accumulation(&map);
+        // Every cell ends up in the lowest corner
+        assert_eq!(flow[0][0], 12);
+        assert_eq!(flow[2][3], 1);
+    }
+}
@@ -1,21 +1,46 @@
 // core holds all the noise, fractal, erosion algorithms
+pub mod analysis;
+pub mod cave;
+pub mod chunked;
+pub mod climate;
 pub mod domain_warp;
 pub mod erosion2;
+pub mod export;
+pub mod export_geo;
+pub mod filters;
 pub mod fractal2;
+pub mod hydrology;
+pub mod marching_squares;
+pub mod modifiers;
+pub mod multifractal2;
+pub mod opensimplex2;
+pub mod pathfinding;
 pub mod perlin2;
+pub mod rng;
 pub mod simplex2;
+pub mod spectral;
 pub mod utils;
+pub mod voronoi;
 
-pub use domain_warp::DomainWarp2D;
-pub use erosion2::ThermalErosion2D;
-pub use fractal2::Fractal2D;
-pub use perlin2::Perlin2D;
-pub use simplex2::Simplex2D;
+pub use chunked::ChunkedTerrain;
+pub use domain_warp::{BoundaryMode, DomainWarp2D, DomainWarp2DChained, DomainWarp3D};
+pub use erosion2::{ErosionPipeline, ErosionStep, ThermalErosion2D, ThermalErosion2DWithProgress};
+pub use filters::{Terrace, TerraceCurve};
+pub use fractal2::{Fractal2D, Fractal2DBuilder};
+pub use modifiers::{RotateNoise, ScaleNoise, TranslateNoise};
+pub use multifractal2::MultiFractal2D;
+pub use opensimplex2::OpenSimplex2D;
+pub use perlin2::{Perlin2D, Perlin2DBuilder};
+pub use rng::XorShiftRng;
+pub use simplex2::{Simplex2D, Simplex2DBuilder};
+pub use spectral::SpectralTerrain;
 pub use utils::flatten2;
+pub use voronoi::{DistanceMode, VoronoiDiagram};
 
 // noise generator that can sample 2D or 3D points
 // 2D‐only implementations override `get2(...)`.
-// 3D‐only implementations override `get3(...)`.
+// 3D implementations override `get3(...)`, and may also override `get2(x, y)`
+// as `get3(x, y, 0.0)` so they can stand in for a 2D generator as a z = 0 slice.
 pub trait NoiseGenerator {
     // Sample 2D noise at (x, y).
     fn get2(&self, _x: f64, _y: f64) -> f64 {
@@ -26,4 +51,23 @@ pub trait NoiseGenerator {
     fn get3(&self, _x: f64, _y: f64, _z: f64) -> f64 {
         panic!("get3 not implemented for this generator");
     }
+
+    // Sample many 2D points at once, `out` is cleared and refilled
+    // Generators can override this with a tighter (or SIMD) loop
+    fn get2_slice(&self, points: &[(f64, f64)], out: &mut Vec<f64>) {
+        out.clear();
+        out.reserve(points.len());
+        for &(x, y) in points {
+            out.push(self.get2(x, y));
+        }
+    }
+
+    // Sample many 3D points at once, `out` is cleared and refilled
+    fn get3_slice(&self, points: &[(f64, f64, f64)], out: &mut Vec<f64>) {
+        out.clear();
+        out.reserve(points.len());
+        for &(x, y, z) in points {
+            out.push(self.get3(x, y, z));
+        }
+    }
 }
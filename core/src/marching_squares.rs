@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use crate::utils::HeightMap2D;
+
+// Line segment (x1, y1, x2, y2) in cell coordinates, x = column and y = row
+pub type Segment = (f32, f32, f32, f32);
+
+// Connected contour points in cell coordinates
+pub type Polyline = Vec<(f32, f32)>;
+
+// Cell edges, named by the two corners they join
+#[derive(Clone, Copy)]
+enum Edge {
+    Top,    // top-left → top-right
+    Right,  // top-right → bottom-right
+    Bottom, // bottom-left → bottom-right
+    Left,   // top-left → bottom-left
+}
+
+use Edge::{Bottom, Left, Right, Top};
+
+// Edge pairs crossed by the contour for each case.
+// Case bits: top-left = 8, top-right = 4, bottom-right = 2, bottom-left = 1,
+// set when the corner is above `level`. The saddles 5 and 10 are listed with
+// the center below the level, march() swaps them when the center is above
+const CASES: [&[(Edge, Edge)]; 16] = [
+    &[],
+    &[(Left, Bottom)],
+    &[(Bottom, Right)],
+    &[(Left, Right)],
+    &[(Top, Right)],
+    &[(Left, Bottom), (Top, Right)],
+    &[(Top, Bottom)],
+    &[(Left, Top)],
+    &[(Left, Top)],
+    &[(Top, Bottom)],
+    &[(Left, Top), (Bottom, Right)],
+    &[(Top, Right)],
+    &[(Left, Right)],
+    &[(Bottom, Right)],
+    &[(Left, Bottom)],
+    &[],
+];
+
+// Contour line segments where the map crosses `level`.
+// Crossing points are linearly interpolated along the cell edges
+pub fn march(map: &HeightMap2D, level: f32) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    for y in 0..map.len().saturating_sub(1) {
+        for x in 0..map[y].len().saturating_sub(1) {
+            let tl = map[y][x];
+            let tr = map[y][x + 1];
+            let br = map[y + 1][x + 1];
+            let bl = map[y + 1][x];
+
+            let above = |v: f32| (v > level) as usize;
+            let mut case = above(tl) << 3 | above(tr) << 2 | above(br) << 1 | above(bl);
+            // Saddle: if the center is above the level the two high corners connect
+            if (case == 5 || case == 10) && (tl + tr + br + bl) * 0.25 > level {
+                case = 15 - case;
+            }
+
+            // Crossing point between two corners
+            let lerp = |(ax, ay, va): (f32, f32, f32), (bx, by, vb): (f32, f32, f32)| {
+                let t = (level - va) / (vb - va);
+                (ax + (bx - ax) * t, ay + (by - ay) * t)
+            };
+            let (fx, fy) = (x as f32, y as f32);
+            let point = |edge| match edge {
+                Top => lerp((fx, fy, tl), (fx + 1.0, fy, tr)),
+                Right => lerp((fx + 1.0, fy, tr), (fx + 1.0, fy + 1.0, br)),
+                Bottom => lerp((fx, fy + 1.0, bl), (fx + 1.0, fy + 1.0, br)),
+                Left => lerp((fx, fy, tl), (fx, fy + 1.0, bl)),
+            };
+
+            for &(a, b) in CASES[case] {
+                let (x1, y1) = point(a);
+                let (x2, y2) = point(b);
+                segments.push((x1, y1, x2, y2));
+            }
+        }
+    }
+    segments
+}
+
+// Endpoints closer than this are treated as the same point
+const STITCH_PRECISION: f32 = 1e-4;
+
+fn key((x, y): (f32, f32)) -> (i64, i64) {
+    (
+        (x / STITCH_PRECISION).round() as i64,
+        (y / STITCH_PRECISION).round() as i64,
+    )
+}
+
+// Chain segments that share endpoints into polylines.
+// A closed contour comes back with its first point repeated at the end
+pub fn stitch_segments(segs: &[Segment]) -> Vec<Polyline> {
+    // Endpoint → indices of the segments touching it
+    let mut touching: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (i, &(x1, y1, x2, y2)) in segs.iter().enumerate() {
+        touching.entry(key((x1, y1))).or_default().push(i);
+        touching.entry(key((x2, y2))).or_default().push(i);
+    }
+
+    let mut used = vec![false; segs.len()];
+    // Next unused segment at `point`, returned with its far endpoint
+    let mut take_next = |point: (f32, f32), used: &mut Vec<bool>| {
+        let &i = touching.get_mut(&key(point))?.iter().find(|&&i| !used[i])?;
+        used[i] = true;
+        let (x1, y1, x2, y2) = segs[i];
+        if key((x1, y1)) == key(point) {
+            Some((x2, y2))
+        } else {
+            Some((x1, y1))
+        }
+    };
+
+    let mut lines = Vec::new();
+    for start in 0..segs.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let (x1, y1, x2, y2) = segs[start];
+        let mut line = vec![(x1, y1), (x2, y2)];
+
+        // Grow forwards, then backwards for open lines that started mid-way
+        while let Some(next) = take_next(*line.last().unwrap(), &mut used) {
+            line.push(next);
+        }
+        let mut head = Vec::new();
+        while let Some(prev) = take_next(*head.last().unwrap_or(&line[0]), &mut used) {
+            head.push(prev);
+        }
+        head.reverse();
+        head.extend(line);
+        lines.push(head);
+    }
+    lines
+}
+
+// Polylines for each of `levels`, in the same order, e.g. for drawing a contour map
+pub fn extract_contours(map: &HeightMap2D, levels: &[f32]) -> Vec<(f32, Vec<Polyline>)> {
+    levels
+        .iter()
+        .map(|&level| (level, stitch_segments(&march(map, level))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_contours, key, march, stitch_segments};
+    use crate::utils::HeightMap2D;
+
+    // 5×5 gaussian bump peaking at 1.0 in the center
+    fn peak() -> HeightMap2D {
+        (0..5)
+            .map(|y| {
+                (0..5)
+                    .map(|x| {
+                        let d2 = (x as f32 - 2.0).powi(2) + (y as f32 - 2.0).powi(2);
+                        (-d2 / 2.0).exp()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn peak_contour_is_closed_loop() {
+        let map = peak();
+        let min = map.iter().flatten().cloned().fold(f32::MAX, f32::min);
+        let segs = march(&map, (min + 1.0) / 2.0);
+        assert!(!segs.is_empty());
+
+        let lines = stitch_segments(&segs);
+        assert_eq!(lines.len(), 1);
+        let line = &lines[0];
+        assert_eq!(line.len(), segs.len() + 1);
+        assert_eq!(key(line[0]), key(*line.last().unwrap()));
+        // Every point lies around the peak, inside the map
+        for &(x, y) in line {
+            let d = (x - 2.0).hypot(y - 2.0);
+            assert!(d > 1.0 && d < 1.5, "point ({x}, {y}) off the contour");
+        }
+    }
+
+    #[test]
+    fn extract_contours_nested_rings() {
+        let contours = extract_contours(&peak(), &[0.2, 0.5, 0.8, 1.5]);
+        assert_eq!(contours.len(), 4);
+        // Higher levels sit closer to the peak
+        let radius = |line: &Vec<(f32, f32)>| (line[0].0 - 2.0).hypot(line[0].1 - 2.0);
+        let mut last = f32::MAX;
+        for (level, lines) in &contours[..3] {
+            assert_eq!(lines.len(), 1, "level {}", level);
+            assert!(radius(&lines[0]) < last);
+            last = radius(&lines[0]);
+        }
+        // Above the peak there is nothing to draw
+        assert!(contours[3].1.is_empty());
+    }
+
+    #[test]
+    fn flat_map_and_straight_edge() {
+        assert!(march(&HeightMap2D::filled(4, 4, 0.2), 0.5).is_empty());
+
+        // Ramp along x crossing 0.5 halfway between columns 1 and 2
+        let ramp: HeightMap2D = (0..3)
+            .map(|_| (0..4).map(|x| x as f32 / 3.0).collect())
+            .collect();
+        let lines = stitch_segments(&march(&ramp, 0.5));
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].len(), 3);
+        assert!(lines[0].iter().all(|&(x, _)| (x - 1.5).abs() < 1e-5));
+    }
+}
@@ -0,0 +1,140 @@
+use crate::NoiseGenerator;
+
+// Coordinate transforms applied before sampling an inner generator.
+// The 3D variants only touch x and y, so z passes through unchanged
+
+// Multiplies the sample coordinates, a scale above 1 packs more features into the same area
+pub struct ScaleNoise<N: NoiseGenerator> {
+    inner: N,
+    scale_x: f64,
+    scale_y: f64,
+}
+
+impl<N: NoiseGenerator> ScaleNoise<N> {
+    pub fn new(inner: N, scale_x: f64, scale_y: f64) -> Self {
+        Self {
+            inner,
+            scale_x,
+            scale_y,
+        }
+    }
+}
+
+impl<N: NoiseGenerator> NoiseGenerator for ScaleNoise<N> {
+    fn get2(&self, x: f64, y: f64) -> f64 {
+        self.inner.get2(x * self.scale_x, y * self.scale_y)
+    }
+
+    fn get3(&self, x: f64, y: f64, z: f64) -> f64 {
+        self.inner.get3(x * self.scale_x, y * self.scale_y, z)
+    }
+}
+
+// Shifts the sample coordinates by a fixed offset
+pub struct TranslateNoise<N: NoiseGenerator> {
+    inner: N,
+    offset_x: f64,
+    offset_y: f64,
+}
+
+impl<N: NoiseGenerator> TranslateNoise<N> {
+    pub fn new(inner: N, offset_x: f64, offset_y: f64) -> Self {
+        Self {
+            inner,
+            offset_x,
+            offset_y,
+        }
+    }
+}
+
+impl<N: NoiseGenerator> NoiseGenerator for TranslateNoise<N> {
+    fn get2(&self, x: f64, y: f64) -> f64 {
+        self.inner.get2(x + self.offset_x, y + self.offset_y)
+    }
+
+    fn get3(&self, x: f64, y: f64, z: f64) -> f64 {
+        self.inner.get3(x + self.offset_x, y + self.offset_y, z)
+    }
+}
+
+// Rotates the sample coordinates counter-clockwise around the origin
+// (around the z axis for get3)
+pub struct RotateNoise<N: NoiseGenerator> {
+    inner: N,
+    sin: f64,
+    cos: f64,
+}
+
+impl<N: NoiseGenerator> RotateNoise<N> {
+    pub fn new(inner: N, angle_radians: f64) -> Self {
+        let (sin, cos) = angle_radians.sin_cos();
+        Self { inner, sin, cos }
+    }
+
+    fn rotate(&self, x: f64, y: f64) -> (f64, f64) {
+        (x * self.cos - y * self.sin, x * self.sin + y * self.cos)
+    }
+}
+
+impl<N: NoiseGenerator> NoiseGenerator for RotateNoise<N> {
+    fn get2(&self, x: f64, y: f64) -> f64 {
+        let (rx, ry) = self.rotate(x, y);
+        self.inner.get2(rx, ry)
+    }
+
+    fn get3(&self, x: f64, y: f64, z: f64) -> f64 {
+        let (rx, ry) = self.rotate(x, y);
+        self.inner.get3(rx, ry, z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RotateNoise, ScaleNoise, TranslateNoise};
+    use crate::{NoiseGenerator, Perlin2D};
+    use std::f64::consts::PI;
+
+    // Returns the coordinates it was sampled at, encoded as x + 10y + 100z
+    struct Coords;
+
+    impl NoiseGenerator for Coords {
+        fn get2(&self, x: f64, y: f64) -> f64 {
+            x + 10.0 * y
+        }
+
+        fn get3(&self, x: f64, y: f64, z: f64) -> f64 {
+            x + 10.0 * y + 100.0 * z
+        }
+    }
+
+    #[test]
+    fn rotate_quarter_turn_matches_perlin() {
+        let perlin = Perlin2D::new(42, 1.0, 0.5, 4);
+        let rotated = RotateNoise::new(Perlin2D::new(42, 1.0, 0.5, 4), PI / 2.0);
+        // (x, y) rotated by 90° lands on (-y, x)
+        for &(x, y) in &[(0.3, 0.7), (1.25, -0.4), (2.6, 3.1)] {
+            let expected = perlin.get2(-y, x);
+            assert!((rotated.get2(x, y) - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn scale_and_translate() {
+        let scaled = ScaleNoise::new(Coords, 2.0, 3.0);
+        assert_eq!(scaled.get2(1.0, 1.0), 2.0 + 30.0);
+        assert_eq!(scaled.get3(1.0, 1.0, 1.0), 2.0 + 30.0 + 100.0);
+
+        let moved = TranslateNoise::new(Coords, 0.5, -1.0);
+        assert_eq!(moved.get2(0.0, 0.0), 0.5 - 10.0);
+        assert_eq!(moved.get3(0.0, 0.0, 2.0), 0.5 - 10.0 + 200.0);
+    }
+
+    #[test]
+    fn wrappers_compose() {
+        // Translate then scale: the offset is applied to the scaled coordinates
+        let noise = ScaleNoise::new(TranslateNoise::new(Coords, 1.0, 0.0), 2.0, 2.0);
+        assert_eq!(noise.get2(1.0, 0.0), 3.0);
+        let spun = RotateNoise::new(Coords, PI);
+        assert!((spun.get3(1.0, 0.0, 1.0) - 99.0).abs() < 1e-9);
+    }
+}
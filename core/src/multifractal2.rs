@@ -0,0 +1,128 @@
+use crate::utils::HeightMap2D;
+use crate::{NoiseGenerator, Perlin2D};
+
+// Multiplicative multifractal (Musgrave / Inigo Quilez)
+// Instead of summing octaves like fBm, every octave scales the running value:
+//     value *= offset + noise × amplitude
+// so detail is strong where the terrain is already high (craggy peaks)
+// and damped where it is low (broad smooth valleys).
+pub struct MultiFractal2D {
+    seed: u64,
+    frequency: f64, // frequency of the first octave
+    gain: f64,      // amplitude falloff per octave
+    octaves: usize,
+    offset: f64,     // roughness threshold, 1.0 = plain `1 + noise × amplitude`
+    basis: Perlin2D, // single-octave Perlin used for every octave
+}
+
+impl MultiFractal2D {
+    pub fn new(seed: u64, frequency: f64, gain: f64, octaves: usize, offset: f64) -> Self {
+        Self {
+            seed,
+            frequency,
+            gain,
+            octaves,
+            offset,
+            basis: Perlin2D::new(seed, 1.0, 0.5, 1),
+        }
+    }
+
+    // Seed this generator was built with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Output bounds of get2(), assuming the basis noise stays in [-1, 1].
+    // Each octave multiplies by a factor in [offset - a, offset + a],
+    // so the bounds are the interval product of those factors.
+    // Useful to map the output to [0,1] without a min/max pass.
+    pub fn bounds(&self) -> (f64, f64) {
+        let mut lo = 1.0f64;
+        let mut hi = 1.0f64;
+        let mut amplitude = 1.0;
+        for _ in 0..self.octaves {
+            let (a, b) = (self.offset - amplitude, self.offset + amplitude);
+            let products = [lo * a, lo * b, hi * a, hi * b];
+            lo = products.iter().copied().fold(f64::INFINITY, f64::min);
+            hi = products.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            amplitude *= self.gain;
+        }
+        (lo, hi)
+    }
+
+    pub fn generate(&self, size: usize) -> HeightMap2D {
+        let mut data = HeightMap2D::filled(size, size, 0.0);
+        for (y, row) in data.iter_mut().enumerate() {
+            for (x, val) in row.iter_mut().enumerate() {
+                let nx = x as f64 / size as f64;
+                let ny = y as f64 / size as f64;
+                *val = self.get2(nx, ny) as f32;
+            }
+        }
+        data
+    }
+}
+
+impl NoiseGenerator for MultiFractal2D {
+    // Unnormalized, see bounds() for the output range
+    fn get2(&self, x: f64, y: f64) -> f64 {
+        let mut value = 1.0;
+        let mut amplitude = 1.0;
+        let mut freq = self.frequency;
+
+        for _ in 0..self.octaves {
+            let n = self.basis.get2(x * freq, y * freq).clamp(-1.0, 1.0);
+            value *= self.offset + n * amplitude;
+            amplitude *= self.gain;
+            freq *= 2.0;
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::NoiseGenerator;
+
+    use super::MultiFractal2D;
+
+    #[test]
+    fn multifractal2_determinism() {
+        let a = MultiFractal2D::new(77, 4.0, 0.5, 6, 1.0);
+        let b = MultiFractal2D::new(77, 4.0, 0.5, 6, 1.0);
+        assert_eq!(a.generate(32), b.generate(32));
+        assert_ne!(
+            a.generate(32),
+            MultiFractal2D::new(78, 4.0, 0.5, 6, 1.0).generate(32)
+        );
+    }
+
+    #[test]
+    fn multifractal2_within_bounds() {
+        let mf = MultiFractal2D::new(5, 4.0, 0.5, 6, 1.0);
+        let (lo, hi) = mf.bounds();
+        // 1 ± 1 for the first octave makes the lower bound exactly 0
+        assert_eq!(lo, 0.0);
+        assert!(hi > 1.0);
+        for row in mf.generate(64) {
+            for v in row {
+                assert!(
+                    (lo..=hi).contains(&(v as f64)),
+                    "{} outside {}..{}",
+                    v,
+                    lo,
+                    hi
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn multifractal2_single_octave_is_offset_plus_noise() {
+        let mf = MultiFractal2D::new(9, 3.0, 0.5, 1, 1.5);
+        let (lo, hi) = mf.bounds();
+        assert_eq!((lo, hi), (0.5, 2.5));
+        let v = mf.get2(0.3, 0.7);
+        assert!((0.5..=2.5).contains(&v));
+    }
+}
@@ -0,0 +1,106 @@
+use crate::hydrology::{downhill_neighbour, flow_accumulation};
+use crate::utils::HeightMap2D;
+
+// Follow the steepest descent from (start_x, start_y), stepping to the lowest
+// of the 8 neighbours until no neighbour is lower. The path includes the start
+// and ends on a local minimum. Heights strictly decrease, so no cell repeats
+pub fn steepest_descent(map: &HeightMap2D, start_x: usize, start_y: usize) -> Vec<(usize, usize)> {
+    let mut path = vec![(start_x, start_y)];
+    let (mut x, mut y) = (start_x, start_y);
+    while let Some((nx, ny)) = downhill_neighbour(map, x, y) {
+        path.push((nx, ny));
+        (x, y) = (nx, ny);
+    }
+    path
+}
+
+// Descent paths for every channel, i.e. cells draining more than `min_flow` cells.
+// Tracing starts at the channel heads, the channel cells no other channel
+// drains into, so a river network gives one path per tributary
+pub fn all_descent_paths(map: &HeightMap2D, min_flow: usize) -> Vec<Vec<(usize, usize)>> {
+    let flow = flow_accumulation(map);
+    let channels: Vec<(usize, usize)> = flow
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|&(_, &f)| f > min_flow)
+                .map(move |(x, _)| (x, y))
+        })
+        .collect();
+
+    // Mark channel cells that receive flow from another channel cell
+    let mut fed = vec![vec![false; flow.first().map_or(0, Vec::len)]; flow.len()];
+    for &(x, y) in &channels {
+        if let Some((nx, ny)) = downhill_neighbour(map, x, y) {
+            fed[ny][nx] = true;
+        }
+    }
+
+    channels
+        .into_iter()
+        .filter(|&(x, y)| !fed[y][x])
+        .map(|(x, y)| steepest_descent(map, x, y))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{all_descent_paths, steepest_descent};
+    use crate::utils::HeightMap2D;
+    use std::collections::HashSet;
+
+    // Two bumps on a slope, with a basin in the middle
+    fn hills() -> HeightMap2D {
+        (0..16)
+            .map(|y| {
+                (0..16)
+                    .map(|x| {
+                        let (fx, fy) = (x as f32, y as f32);
+                        let bump = |cx: f32, cy: f32| {
+                            (-((fx - cx).powi(2) + (fy - cy).powi(2)) / 6.0).exp()
+                        };
+                        bump(3.0, 3.0) + bump(12.0, 10.0) - 0.5 * bump(8.0, 7.0) + fx * 0.01
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn is_local_min(map: &HeightMap2D, x: usize, y: usize) -> bool {
+        (y.saturating_sub(1)..(y + 2).min(map.len())).all(|ny| {
+            (x.saturating_sub(1)..(x + 2).min(map[0].len())).all(|nx| map[ny][nx] >= map[y][x])
+        })
+    }
+
+    #[test]
+    fn descent_from_peak_ends_in_minimum() {
+        let map = hills();
+        for (sx, sy) in [(3, 3), (12, 10), (0, 15)] {
+            let path = steepest_descent(&map, sx, sy);
+            assert_eq!(path[0], (sx, sy));
+            let &(ex, ey) = path.last().unwrap();
+            assert!(is_local_min(&map, ex, ey));
+
+            let unique: HashSet<_> = path.iter().collect();
+            assert_eq!(unique.len(), path.len());
+            for pair in path.windows(2) {
+                assert!(map[pair[1].1][pair[1].0] < map[pair[0].1][pair[0].0]);
+            }
+        }
+    }
+
+    #[test]
+    fn descent_paths_follow_channels() {
+        let map = hills();
+        let paths = all_descent_paths(&map, 8);
+        assert!(!paths.is_empty());
+        for path in &paths {
+            let &(ex, ey) = path.last().unwrap();
+            assert!(is_local_min(&map, ex, ey));
+        }
+        // A threshold above the map size leaves no channels
+        assert!(all_descent_paths(&map, 16 * 16).is_empty());
+    }
+}
@@ -1,33 +1,81 @@
+use serde::{Deserialize, Serialize};
+
 use crate::NoiseGenerator;
+use crate::rng::XorShiftRng;
+use crate::utils::HeightMap2D;
+
+// Ken Perlin's improved (2002) gradient set with z dropped: the 12 cube edge
+// directions plus 4 repeats that pad it to 16, so a 4-bit hash picks one.
+// Covers both axes and both diagonals
+const GRADIENTS: [(f64, f64); 16] = [
+    (1.0, 1.0),
+    (-1.0, 1.0),
+    (1.0, -1.0),
+    (-1.0, -1.0),
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+    (1.0, 1.0),
+    (0.0, -1.0),
+    (-1.0, 1.0),
+    (0.0, -1.0),
+];
 
 // 2D Perlin Noise generator with support for multiple octaves
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "Perlin2DParams")]
 pub struct Perlin2D {
     seed: u64,        // Arbitrary u64 seed
     frequency: f64,   // Controls the "zoom level" of the noise pattern
     persistence: f64, // Controls amplitude scaling per octave
     octaves: usize,   // number of octaves to sum
-    perm: [u8; 512],  // permutation table (256 duplicated)
+    #[serde(skip)]
+    perm: [u8; 512], // permutation table (256 duplicated)
+    amplitudes: Option<Vec<f64>>, // explicit per-octave weights, overrides persistence
+}
+
+// What gets serialized for a Perlin2D, the permutation table is rebuilt by new() on load
+#[derive(Deserialize)]
+struct Perlin2DParams {
+    seed: u64,
+    frequency: f64,
+    persistence: f64,
+    octaves: usize,
+    amplitudes: Option<Vec<f64>>,
+}
+
+// Checked here, new() would panic on 0 octaves
+impl TryFrom<Perlin2DParams> for Perlin2D {
+    type Error = String;
+
+    fn try_from(p: Perlin2DParams) -> Result<Self, String> {
+        if p.octaves == 0 {
+            return Err("octaves must be at least 1".into());
+        }
+        let generator = Perlin2D::new(p.seed, p.frequency, p.persistence, p.octaves);
+        Ok(match p.amplitudes {
+            Some(amplitudes) => generator.with_amplitudes(amplitudes),
+            None => generator,
+        })
+    }
 }
 
 impl Perlin2D {
     pub fn new(seed: u64, frequency: f64, persistence: f64, octaves: usize) -> Self {
+        assert!(octaves > 0, "octaves must be at least 1");
         // build a pseudorandom permutation table of size 256, duplicated into 512
         let mut p: Vec<u8> = (0..256).map(|i| i as u8).collect();
-        // Simple xorshift-based (with a large constant) RNG for shuffling
-        let mut x = seed ^ 0xDEADBEEFCAFEBABE_u64;
-        let mut rng = || {
-            // These values are known to work well for shuffling
-            // Mulitple shift + XOR for better results
-            x ^= x << 13;
-            x ^= x >> 7;
-            x ^= x << 17;
-            // Bitmasking the lowest 8 bits
-            (x & 0xFF) as u8
-        };
+        // xorshift RNG for shuffling, the seed mixed with a large constant
+        let mut rng = XorShiftRng::new(seed ^ 0xDEADBEEFCAFEBABE_u64);
         // Fisher–Yates shuffle p[0..256]
         for i in (1..256).rev() {
-            // mod (i + 1) to constrain it to [0..i]
-            let j = (rng() as usize) % (i + 1);
+            // Lowest 8 bits, mod (i + 1) to constrain it to [0..i]
+            let j = (rng.next_u64() & 0xFF) as usize % (i + 1);
             // to place each element in a random position
             p.swap(i, j);
         }
@@ -45,6 +93,7 @@ impl Perlin2D {
             persistence,
             octaves,
             perm,
+            amplitudes: None,
         }
     }
 
@@ -63,23 +112,25 @@ impl Perlin2D {
         a + t * (b - a)
     }
 
-    // Gradient function for 2D: based on the hashed value hi, choose a gradient direction
+    // Gradient function for 2D: dot product of the hashed gradient with (x, y)
+    // Gives scalar influence of the gradient direction on the point (x, y)
     #[inline]
     fn grad(hash: u8, x: f64, y: f64) -> f64 {
-        // Convert low 4 bits of hash into 12 gradient directions
-        let h = (hash & 0xF) as usize;
-        // Predefined gradients (permute among (1,1), (1,-1), (-1,1), (-1,-1), (1,0), (-1,0), (0,1), (0,-1))
-        let u = if h < 8 { x } else { y };
-        let v = if h < 8 { y } else { x };
-        let sign_u = if (h & 1) == 0 { u } else { -u };
-        let sign_v = if (h & 2) == 0 { v } else { -v };
-        // Equivalent ot a simplified dot product
-        // Gives scalar influence of the gradient direction on the point (x, y)
-        sign_u + sign_v
-    }
-
-    // Raw single‐octave Perlin noise at (x, y)
-    // Returns in range ≈ [−√2, √2]
+        let (gx, gy) = GRADIENTS[(hash & 0xF) as usize];
+        gx * x + gy * y
+    }
+
+    // Raw single‐octave Perlin noise at (x, y), always within [−1, 1].
+    // With fractional offsets (xf, yf) and fades u = fade(xf), v = fade(yf), the
+    // result is the sum over the four corners of weight × (gradient · offset),
+    // the weights (1-u)(1-v), u(1-v), (1-u)v, uv adding up to 1. Every gradient
+    // here has components in {-1, 0, 1}, so a corner's term is at most |dx| + |dy|,
+    // and summing the weighted |dx| over the corners gives
+    //   (1-u)·xf + u·(1-xf) = xf + fade(xf)(1 - 2 xf) ≤ 2 xf (1 - xf) ≤ 1/2
+    // using fade(t) ≤ t for t ≤ 1/2 (mirrored above it). The same goes for |dy|,
+    // so |noise| ≤ 1. It is reached at a cell centre when all four corners hold
+    // the diagonal pointing at (or away from) it. The √2/2 often quoted is for
+    // unit gradients, these diagonals are √2 long
     fn noise(&self, x: f64, y: f64) -> f64 {
         // Find unit square that contains point (Which square to sample?)
         let xi = x.floor() as i32 & 255;
@@ -109,45 +160,128 @@ impl Perlin2D {
         Self::lerp(x1, x2, v)
     }
 
-    pub fn generate(&self, size: usize) -> Vec<Vec<f32>> {
-        let mut data = vec![vec![0.0; size]; size];
-        for y in 0..size {
-            for x in 0..size {
-                let nx = x as f64 / size as f64;
-                let ny = y as f64 / size as f64;
-                data[y][x] = self.get2(nx, ny) as f32;
+    // Use an explicit amplitude for each octave instead of `amplitude *= persistence`
+    // The number of octaves is taken from the length of the slice
+    pub fn with_amplitudes(mut self, amplitudes: Vec<f64>) -> Self {
+        self.octaves = amplitudes.len();
+        self.amplitudes = Some(amplitudes);
+        self
+    }
+
+    // Seed this generator was built with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn generate(&self, size: usize) -> HeightMap2D {
+        self.generate_rect(size, size)
+    }
+
+    // width × height grid, indexed [y][x]. Both axes share the longer side's
+    // scale, so the result is the matching corner of generate(width.max(height))
+    pub fn generate_rect(&self, width: usize, height: usize) -> HeightMap2D {
+        let scale = width.max(height) as f64;
+        let mut data = HeightMap2D::filled(width, height, 0.0);
+        for (y, row) in data.iter_mut().enumerate() {
+            for (x, val) in row.iter_mut().enumerate() {
+                let nx = x as f64 / scale;
+                let ny = y as f64 / scale;
+                *val = self.get2(nx, ny) as f32;
             }
         }
         data
     }
 }
 
+// Named-argument alternative to Perlin2D::new(), e.g.
+// Perlin2DBuilder::default().seed(7).octaves(6).build()
+pub struct Perlin2DBuilder {
+    seed: u64,
+    frequency: f64,
+    persistence: f64,
+    octaves: usize,
+}
+
+impl Default for Perlin2DBuilder {
+    // Same defaults as the app sliders
+    fn default() -> Self {
+        Self {
+            seed: 2025,
+            frequency: 1.0,
+            persistence: 0.5,
+            octaves: 4,
+        }
+    }
+}
+
+impl Perlin2DBuilder {
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn frequency(mut self, frequency: f64) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    pub fn persistence(mut self, persistence: f64) -> Self {
+        self.persistence = persistence;
+        self
+    }
+
+    pub fn octaves(mut self, octaves: usize) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    pub fn build(self) -> Perlin2D {
+        Perlin2D::new(self.seed, self.frequency, self.persistence, self.octaves)
+    }
+}
+
 impl NoiseGenerator for Perlin2D {
     // Return a multi-octave Perlin noise value at (x, y) (Fractal Brownian Motion)
-    // The result is roughly in [−1.0, +1.0] after normalization
+    // Each octave is within [−1, 1] (see noise()), so the result is bounded by
+    // sum(|w_i|) / |sum(w_i)|. That is exactly [−1, 1] for the default decay and
+    // any non-negative with_amplitudes() weights, wider if some weights are negative.
+    // Octaves rarely peak together, so a whole map usually spans less than this,
+    // but a single octave does reach ±1
     fn get2(&self, x: f64, y: f64) -> f64 {
         let mut amplitude = 1.0; // Weight of the current octave
         let mut freq = self.frequency; // How zoomed in we are on the noise pattern
         let mut total = 0.0; // Accumulated noise value
         let mut max_amp = 0.0; // Maximum possible amplitude to normalize the result
 
-        for _ in 0..self.octaves {
-            total += self.noise(x * freq, y * freq) * amplitude;
-            max_amp += amplitude;
+        for i in 0..self.octaves {
+            // Hand-tuned weights take priority over the geometric decay
+            let weight = self.amplitudes.as_ref().map_or(amplitude, |a| a[i]);
+            total += self.noise(x * freq, y * freq) * weight;
+            max_amp += weight;
             amplitude *= self.persistence;
             freq *= 2.0;
         }
 
+        // with_amplitudes() weights can sum to zero, leaving nothing to normalize
+        if max_amp == 0.0 {
+            return 0.0;
+        }
+
         // Normalize to [−1, +1] to keep the output consistent
         total / max_amp
     }
+    // Statically dispatched batch sampling, no vtable lookup per point
+    fn get2_slice(&self, points: &[(f64, f64)], out: &mut Vec<f64>) {
+        out.clear();
+        out.extend(points.iter().map(|&(x, y)| Perlin2D::get2(self, x, y)));
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::NoiseGenerator;
 
-    use super::Perlin2D;
+    use super::{Perlin2D, Perlin2DBuilder};
 
     #[test]
     fn perlin2_determinism() {
@@ -165,10 +299,89 @@ mod tests {
         let p = Perlin2D::new(0, 0.1, 0.5, 6);
         for &pt in &[(0.0, 0.0), (5.3, -1.2), (100.1, 200.2)] {
             let v = p.get2(pt.0, pt.1);
-            assert!(v >= -1.0 - 1e-6 && v <= 1.0 + 1e-6);
+            assert!((-1.0..=1.0).contains(&v));
+        }
+    }
+
+    #[test]
+    // The bound derived on noise() holds with no slack, and is tight: a single
+    // octave reaches ±1 at cell centres, so it can't be narrowed to e.g. ±0.75
+    fn perlin2_tight_range() {
+        let p = Perlin2D::new(42, 1.0, 0.5, 1);
+        let (mut lo, mut hi) = (f64::MAX, f64::MIN);
+        for cy in 0..256 {
+            for cx in 0..256 {
+                for (ox, oy) in [(0.5, 0.5), (0.25, 0.5), (0.5, 0.75), (0.1, 0.9)] {
+                    let v = p.get2(cx as f64 + ox, cy as f64 + oy);
+                    lo = lo.min(v);
+                    hi = hi.max(v);
+                }
+                // Zero on the lattice itself
+                assert_eq!(p.get2(cx as f64, cy as f64), 0.0);
+            }
+        }
+        assert!(lo >= -1.0 && hi <= 1.0, "range [{}, {}]", lo, hi);
+        assert_eq!((lo, hi), (-1.0, 1.0));
+
+        // Negative weights widen the bound to sum(|w|) / |sum(w)| = 3
+        let mixed = Perlin2D::new(42, 1.0, 0.5, 1).with_amplitudes(vec![1.0, -0.5]);
+        let peak = (0..64)
+            .flat_map(|y| (0..64).map(move |x| (x as f64 * 0.37, y as f64 * 0.41)))
+            .map(|(x, y)| mixed.get2(x, y).abs())
+            .fold(0.0, f64::max);
+        assert!(peak > 1.0 && peak <= 3.0);
+    }
+
+    #[test]
+    fn perlin2_generate_rect_is_corner_of_square() {
+        let p = Perlin2D::new(8, 3.0, 0.5, 3);
+        let square = p.generate(12);
+        let wide = p.generate_rect(12, 5);
+        assert_eq!(wide.len(), 5);
+        assert_eq!(wide.raw(), &square.raw()[..5]);
+        let tall = p.generate_rect(4, 12);
+        assert!(tall.iter().zip(&square).all(|(t, s)| t[..] == s[..4]));
+    }
+
+    #[test]
+    #[should_panic(expected = "octaves must be at least 1")]
+    fn perlin2_zero_octaves_panics() {
+        Perlin2D::new(0, 1.0, 0.5, 0).get2(0.5, 0.5);
+    }
+
+    #[test]
+    fn perlin2_amplitudes_single_octave() {
+        let single = Perlin2D::new(77, 2.0, 0.5, 1);
+        let weighted = Perlin2D::new(77, 2.0, 0.5, 6).with_amplitudes(vec![1.0, 0.0, 0.0, 0.0]);
+        for &(x, y) in &[(0.1, 0.2), (3.7, -1.4), (42.0, 17.5)] {
+            assert_eq!(single.get2(x, y), weighted.get2(x, y));
         }
     }
 
+    #[test]
+    fn perlin2_slice_matches_get2() {
+        let g = Perlin2D::new(5, 3.0, 0.5, 4);
+        let points = [(0.0, 0.0), (0.3, 0.7), (12.5, -4.25)];
+        let mut out = vec![99.0];
+        g.get2_slice(&points, &mut out);
+        assert_eq!(out.len(), points.len());
+        for (&(x, y), &v) in points.iter().zip(&out) {
+            assert_eq!(g.get2(x, y), v);
+        }
+    }
+
+    #[test]
+    fn perlin2_builder_matches_new() {
+        let built = Perlin2DBuilder::default()
+            .seed(31)
+            .frequency(2.5)
+            .persistence(0.6)
+            .octaves(5)
+            .build();
+        let direct = Perlin2D::new(31, 2.5, 0.6, 5);
+        assert_eq!(built.get2(0.37, 0.81), direct.get2(0.37, 0.81));
+    }
+
     #[test]
     #[should_panic]
     fn perlin2_get3_panic() {
@@ -176,4 +389,68 @@ mod tests {
         // Calling get3 on a 2D-only generator should panic
         let _ = p.get3(1.0, 2.0, 3.0);
     }
+
+    #[test]
+    fn perlin2_isotropy() {
+        // Histogram of slope directions over a 256×256 image, 8 bins over half
+        // a turn, weighted by slope. Summed over a few seeds to smooth it out
+        let size = 256;
+        let mut bins = [0.0; 8];
+        for seed in 0..4 {
+            let p = Perlin2D::new(seed, 32.0, 0.5, 1);
+            let grid: Vec<Vec<f64>> = (0..size)
+                .map(|y| {
+                    (0..size)
+                        .map(|x| p.get2(x as f64 / size as f64, y as f64 / size as f64))
+                        .collect()
+                })
+                .collect();
+            for y in 0..size - 1 {
+                for x in 0..size - 1 {
+                    let dx = grid[y][x + 1] - grid[y][x];
+                    let dy = grid[y + 1][x] - grid[y][x];
+                    let angle = dy.atan2(dx).rem_euclid(std::f64::consts::PI);
+                    let bin = ((angle / std::f64::consts::PI * 8.0) as usize).min(7);
+                    bins[bin] += dx.hypot(dy);
+                }
+            }
+        }
+        let max = bins.iter().cloned().fold(f64::MIN, f64::max);
+        let min = bins.iter().cloned().fold(f64::MAX, f64::min);
+        // The old diagonal-only gradients scored about 1.9 here
+        assert!(max / min < 1.7, "anisotropic slopes {:?}", bins);
+    }
+
+    #[test]
+    fn perlin2_serde_roundtrip() {
+        let plain = Perlin2D::new(8, 2.0, 0.6, 5);
+        let weighted = Perlin2D::new(8, 2.0, 0.6, 5).with_amplitudes(vec![1.0, 0.3, 0.1]);
+        for original in [plain, weighted] {
+            let json = serde_json::to_string(&original).unwrap();
+            let loaded: Perlin2D = serde_json::from_str(&json).unwrap();
+            for &(x, y) in &[(0.1, 0.2), (3.7, -1.4), (42.0, 17.5)] {
+                assert_eq!(original.get2(x, y), loaded.get2(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn perlin2_deserialize_rejects_zero_octaves() {
+        let json = r#"{"seed":1,"frequency":2.0,"persistence":0.5,"octaves":0,"amplitudes":null}"#;
+        let err = serde_json::from_str::<Perlin2D>(json).err().unwrap();
+        assert!(err.to_string().contains("octaves"));
+    }
+
+    #[test]
+    fn perlin2_clone_is_independent() {
+        let original = Perlin2D::new(21, 3.0, 0.5, 4);
+        let before = original.get2(0.4, 0.9);
+        let copy = original.clone();
+        assert_eq!(copy.get2(0.4, 0.9), before);
+
+        // Changing the clone leaves the original alone
+        let copy = copy.with_amplitudes(vec![0.0, 1.0]);
+        assert_ne!(copy.get2(0.4, 0.9), before);
+        assert_eq!(original.get2(0.4, 0.9), before);
+    }
 }
@@ -1,31 +1,70 @@
+use serde::{Deserialize, Serialize};
+
 use crate::NoiseGenerator;
+use crate::rng::XorShiftRng;
+use crate::utils::HeightMap2D;
+
+// Scales raw_noise() to exactly [-1, 1]. A corner only contributes within
+// r² < 0.5, as t⁴ (g · d) with t = 0.5 - r². The sum peaks at the midpoint of
+// the diagonal simplex edge: its two corners are 1/√6 away (t = 1/3) and the
+// third is at r² = 0.5 (t = 0). With the (1, 1)/√2 gradients along that edge
+// the sum is 2 · (1/3)⁴ · (1/√6) = 2 / (81√6) ≈ 0.0100802. A search over the
+// whole simplex and every choice of the 12 gradients at each corner finds
+// nothing larger, so the scale is 81√6 / 2
+const OUTPUT_SCALE: f64 = 99.204_334_582_718_7;
 
 // 2D Simplex noise generator with multiple octaves
 // Based on Ken Perlin's Simplex algorithm
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "Simplex2DParams")]
 pub struct Simplex2D {
     seed: u64,
     frequency: f64,
     persistence: f64,
     octaves: usize,
+    #[serde(skip)]
     perm: [u8; 512],
     // Simplex divides space into triangles, rather than squares
     // This results in better isotropy (uniformity in all directions)
-    grad3: [(i8, i8); 12],
+    #[serde(skip)]
+    grad3: [(f64, f64); 12],
+    amplitudes: Option<Vec<f64>>, // explicit per-octave weights, overrides persistence
+}
+
+// What gets serialized for a Simplex2D, the lookup tables are rebuilt by new() on load
+#[derive(Deserialize)]
+struct Simplex2DParams {
+    seed: u64,
+    frequency: f64,
+    persistence: f64,
+    octaves: usize,
+    amplitudes: Option<Vec<f64>>,
+}
+
+// Checked here, new() would panic on 0 octaves
+impl TryFrom<Simplex2DParams> for Simplex2D {
+    type Error = String;
+
+    fn try_from(p: Simplex2DParams) -> Result<Self, String> {
+        if p.octaves == 0 {
+            return Err("octaves must be at least 1".into());
+        }
+        let generator = Simplex2D::new(p.seed, p.frequency, p.persistence, p.octaves);
+        Ok(match p.amplitudes {
+            Some(amplitudes) => generator.with_amplitudes(amplitudes),
+            None => generator,
+        })
+    }
 }
 
 impl Simplex2D {
     pub fn new(seed: u64, frequency: f64, persistence: f64, octaves: usize) -> Self {
+        assert!(octaves > 0, "octaves must be at least 1");
         // Same permutation‐table construction as Perlin2D:
         let mut p: Vec<u8> = (0..256).map(|i| i as u8).collect();
-        let mut x = seed ^ 0x1234_5678_9ABC_DEF0_u64;
-        let mut rng = || {
-            x ^= x << 13;
-            x ^= x >> 7;
-            x ^= x << 17;
-            (x & 0xFF) as u8
-        };
+        let mut rng = XorShiftRng::new(seed ^ 0x1234_5678_9ABC_DEF0_u64);
         for i in (1..256).rev() {
-            let j = (rng() as usize) % (i + 1);
+            let j = (rng.next_u64() & 0xFF) as usize % (i + 1);
             p.swap(i, j);
         }
         let mut perm = [0u8; 512];
@@ -33,21 +72,26 @@ impl Simplex2D {
             perm[i] = p[i & 255];
         }
 
-        // Predefined 2D gradient directions (normalized to length ≈1.0):
+        // Predefined 2D gradient directions, scaled to unit length so that no
+        // direction contributes more than another ((1, 2) is √5 long as written)
         let grad3 = [
-            (1, 1),
-            (-1, 1),
-            (1, -1),
-            (-1, -1),
-            (1, 0),
-            (-1, 0),
-            (0, 1),
-            (0, -1),
-            (1, 2),
-            (-1, 2),
-            (1, -2),
-            (-1, -2),
-        ];
+            (1.0, 1.0),
+            (-1.0, 1.0),
+            (1.0, -1.0),
+            (-1.0, -1.0),
+            (1.0, 0.0),
+            (-1.0, 0.0),
+            (0.0, 1.0),
+            (0.0, -1.0),
+            (1.0, 2.0),
+            (-1.0, 2.0),
+            (1.0, -2.0),
+            (-1.0, -2.0),
+        ]
+        .map(|(x, y): (f64, f64)| {
+            let len = x.hypot(y);
+            (x / len, y / len)
+        });
 
         Self {
             seed,
@@ -55,21 +99,22 @@ impl Simplex2D {
             persistence,
             octaves,
             perm,
+            amplitudes: None,
             grad3,
         }
     }
 
     // Dot product helper chooses a gradient from grad3[hash % 12]
     #[inline]
-    fn dot(g: (i8, i8), x: f64, y: f64) -> f64 {
-        (g.0 as f64) * x + (g.1 as f64) * y
+    fn dot(g: (f64, f64), x: f64, y: f64) -> f64 {
+        g.0 * x + g.1 * y
     }
 
     // Raw 2D Simplex noise at (xin, yin)
     // Returns in range [−1.0, +1.0], roughly
     fn raw_noise(&self, xin: f64, yin: f64) -> f64 {
         // Approximate value of sqrt(3)
-        const SQRT_3: f64 = 1.732_050_807_568_877_293_5;
+        const SQRT_3: f64 = 1.732_050_807_568_877_2;
         // Skewing/Unskewing factors for 2D simplex
         const F2: f64 = 0.5 * (SQRT_3 - 1.0); // comresses the square into a rhombus made of equilateral triangles
         const G2: f64 = (3.0 - SQRT_3) / 6.0; // reverses the skewing
@@ -125,23 +170,90 @@ impl Simplex2D {
             n2 = t2_sq * t2_sq * Self::dot(self.grad3[gi2], x2, y2);
         }
 
-        // The result is scaled to return roughly [-1,1] to make it consistent
-        70.0 * (n0 + n1 + n2)
+        // Scaled so the largest possible sum is exactly 1, see OUTPUT_SCALE
+        OUTPUT_SCALE * (n0 + n1 + n2)
+    }
+
+    // Use an explicit amplitude for each octave instead of `amplitude *= persistence`
+    // The number of octaves is taken from the length of the slice
+    pub fn with_amplitudes(mut self, amplitudes: Vec<f64>) -> Self {
+        self.octaves = amplitudes.len();
+        self.amplitudes = Some(amplitudes);
+        self
+    }
+
+    // Seed this generator was built with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn generate(&self, size: usize) -> HeightMap2D {
+        self.generate_rect(size, size)
     }
 
-    pub fn generate(&self, size: usize) -> Vec<Vec<f32>> {
-        let mut data = vec![vec![0.0; size]; size];
-        for y in 0..size {
-            for x in 0..size {
-                let nx = x as f64 / size as f64;
-                let ny = y as f64 / size as f64;
-                data[y][x] = self.get2(nx, ny) as f32;
+    // width × height grid, indexed [y][x]. Both axes share the longer side's
+    // scale, so the result is the matching corner of generate(width.max(height))
+    pub fn generate_rect(&self, width: usize, height: usize) -> HeightMap2D {
+        let scale = width.max(height) as f64;
+        let mut data = HeightMap2D::filled(width, height, 0.0);
+        for (y, row) in data.iter_mut().enumerate() {
+            for (x, val) in row.iter_mut().enumerate() {
+                let nx = x as f64 / scale;
+                let ny = y as f64 / scale;
+                *val = self.get2(nx, ny) as f32;
             }
         }
         data
     }
 }
 
+// Named-argument alternative to Simplex2D::new(), e.g.
+// Simplex2DBuilder::default().seed(7).octaves(6).build()
+pub struct Simplex2DBuilder {
+    seed: u64,
+    frequency: f64,
+    persistence: f64,
+    octaves: usize,
+}
+
+impl Default for Simplex2DBuilder {
+    // Same defaults as the app sliders
+    fn default() -> Self {
+        Self {
+            seed: 2025,
+            frequency: 1.0,
+            persistence: 0.5,
+            octaves: 4,
+        }
+    }
+}
+
+impl Simplex2DBuilder {
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn frequency(mut self, frequency: f64) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    pub fn persistence(mut self, persistence: f64) -> Self {
+        self.persistence = persistence;
+        self
+    }
+
+    pub fn octaves(mut self, octaves: usize) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    pub fn build(self) -> Simplex2D {
+        Simplex2D::new(self.seed, self.frequency, self.persistence, self.octaves)
+    }
+}
+
 impl NoiseGenerator for Simplex2D {
     fn get2(&self, x: f64, y: f64) -> f64 {
         let mut amplitude = 1.0;
@@ -149,23 +261,35 @@ impl NoiseGenerator for Simplex2D {
         let mut total = 0.0;
         let mut max_amp = 0.0;
 
-        for _ in 0..self.octaves {
-            total += self.raw_noise(x * freq, y * freq) * amplitude;
-            max_amp += amplitude;
+        for i in 0..self.octaves {
+            // Hand-tuned weights take priority over the geometric decay
+            let weight = self.amplitudes.as_ref().map_or(amplitude, |a| a[i]);
+            total += self.raw_noise(x * freq, y * freq) * weight;
+            max_amp += weight;
             amplitude *= self.persistence;
             freq *= 2.0;
         }
 
+        // with_amplitudes() weights can sum to zero, leaving nothing to normalize
+        if max_amp == 0.0 {
+            return 0.0;
+        }
+
         // Normalize to [-1, 1]
         total / max_amp
     }
+    // Statically dispatched batch sampling, no vtable lookup per point
+    fn get2_slice(&self, points: &[(f64, f64)], out: &mut Vec<f64>) {
+        out.clear();
+        out.extend(points.iter().map(|&(x, y)| Simplex2D::get2(self, x, y)));
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::NoiseGenerator;
 
-    use super::Simplex2D;
+    use super::{Simplex2D, Simplex2DBuilder};
 
     #[test]
     fn simplex2_determinism() {
@@ -181,14 +305,99 @@ mod tests {
         let s = Simplex2D::new(0, 0.1, 0.5, 6);
         for &(x, y) in &[(0.0, 0.0), (5.5, -5.5), (100.1, 100.1)] {
             let v = s.get2(x, y);
-            assert!(v >= -1.0 - 1e-6 && v <= 1.0 + 1e-6);
+            assert!((-1.0 - 1e-6..=1.0 + 1e-6).contains(&v));
+        }
+    }
+
+    #[test]
+    fn simplex2_scale_bound_is_tight() {
+        // One octave reaches within 1% of the derived bound but never past it
+        let s = Simplex2D::new(2025, 1.0, 0.5, 1);
+        let mut peak: f64 = 0.0;
+        for y in 0..600 {
+            for x in 0..600 {
+                peak = peak.max(s.get2(x as f64 * 0.0613, y as f64 * 0.0617).abs());
+            }
+        }
+        assert!(peak > 0.99 && peak <= 1.0, "peak {}", peak);
+    }
+
+    #[test]
+    fn simplex2_statistics_unbiased() {
+        // One octave on a 1024×1024 grid, a few hundred simplex cells per side
+        let s = Simplex2D::new(5, 1.0, 0.5, 1);
+        let n = 1024 * 1024;
+        let (mut sum, mut sum_sq) = (0.0, 0.0);
+        for y in 0..1024 {
+            for x in 0..1024 {
+                let v = s.get2(x as f64 * 0.29, y as f64 * 0.31);
+                sum += v;
+                sum_sq += v * v;
+            }
+        }
+        let mean = sum / n as f64;
+        let std = (sum_sq / n as f64 - mean * mean).sqrt();
+        assert!(mean.abs() < 0.01, "mean {}", mean);
+        assert!((std - 0.54).abs() < 0.05, "std {}", std);
+    }
+
+    #[test]
+    fn simplex2_amplitudes_single_octave() {
+        let single = Simplex2D::new(77, 2.0, 0.5, 1);
+        let weighted = Simplex2D::new(77, 2.0, 0.5, 6).with_amplitudes(vec![1.0, 0.0, 0.0, 0.0]);
+        for &(x, y) in &[(0.1, 0.2), (3.7, -1.4), (42.0, 17.5)] {
+            assert_eq!(single.get2(x, y), weighted.get2(x, y));
         }
     }
 
+    #[test]
+    fn simplex2_slice_matches_get2() {
+        let g = Simplex2D::new(5, 3.0, 0.5, 4);
+        let points = [(0.0, 0.0), (0.3, 0.7), (12.5, -4.25)];
+        let mut out = vec![99.0];
+        g.get2_slice(&points, &mut out);
+        assert_eq!(out.len(), points.len());
+        for (&(x, y), &v) in points.iter().zip(&out) {
+            assert_eq!(g.get2(x, y), v);
+        }
+    }
+
+    #[test]
+    fn simplex2_builder_matches_new() {
+        let built = Simplex2DBuilder::default()
+            .seed(31)
+            .frequency(2.5)
+            .persistence(0.6)
+            .octaves(5)
+            .build();
+        let direct = Simplex2D::new(31, 2.5, 0.6, 5);
+        assert_eq!(built.get2(0.37, 0.81), direct.get2(0.37, 0.81));
+    }
+
     #[test]
     #[should_panic]
     fn simplex2_get3_panic() {
         let s = Simplex2D::new(0, 0.1, 0.5, 4);
         let _ = s.get3(1.0, 2.0, 3.0);
     }
+
+    #[test]
+    fn simplex2_serde_roundtrip() {
+        let plain = Simplex2D::new(8, 2.0, 0.6, 5);
+        let weighted = Simplex2D::new(8, 2.0, 0.6, 5).with_amplitudes(vec![1.0, 0.3, 0.1]);
+        for original in [plain, weighted] {
+            let json = serde_json::to_string(&original).unwrap();
+            let loaded: Simplex2D = serde_json::from_str(&json).unwrap();
+            for &(x, y) in &[(0.1, 0.2), (3.7, -1.4), (42.0, 17.5)] {
+                assert_eq!(original.get2(x, y), loaded.get2(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn simplex2_deserialize_rejects_zero_octaves() {
+        let json = r#"{"seed":1,"frequency":2.0,"persistence":0.5,"octaves":0,"amplitudes":null}"#;
+        let err = serde_json::from_str::<Simplex2D>(json).err().unwrap();
+        assert!(err.to_string().contains("octaves"));
+    }
 }
@@ -0,0 +1,218 @@
+use rustfft::FftPlanner;
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+
+use crate::NoiseGenerator;
+use crate::rng::XorShiftRng;
+use crate::utils::{HeightMap2D, sample_bilinear};
+
+// Spectral synthesis: random phases with a power-law amplitude in frequency
+// space, brought back with an inverse FFT. The result tiles seamlessly since
+// the FFT is periodic
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "SpectralTerrainParams")]
+pub struct SpectralTerrain {
+    size: usize, // must be a power of two, e.g. 128, 256
+    seed: u64,
+    // Amplitude falls off as f^(-exponent/2), ~2.0 looks like natural terrain.
+    // Higher is smoother, the surface's fractal dimension is (8 - exponent) / 2
+    spectral_exponent: f64,
+    // Frequencies below this (in cycles per map) are dropped, removing the
+    // largest features. 0.0 only removes the mean
+    cutoff_frequency: f64,
+    #[serde(skip)]
+    map: HeightMap2D,
+}
+
+// What gets serialized for a SpectralTerrain, the grid is rebuilt on load
+#[derive(Deserialize)]
+struct SpectralTerrainParams {
+    size: usize,
+    seed: u64,
+    spectral_exponent: f64,
+    cutoff_frequency: f64,
+}
+
+// Checked here, new() would panic on a bad size
+impl TryFrom<SpectralTerrainParams> for SpectralTerrain {
+    type Error = String;
+
+    fn try_from(p: SpectralTerrainParams) -> Result<Self, String> {
+        if p.size < 2 || !p.size.is_power_of_two() {
+            return Err(format!("size must be a power of two, got {}", p.size));
+        }
+        Ok(SpectralTerrain::new(
+            p.size,
+            p.seed,
+            p.spectral_exponent,
+            p.cutoff_frequency,
+        ))
+    }
+}
+
+impl SpectralTerrain {
+    pub fn new(size: usize, seed: u64, spectral_exponent: f64, cutoff_frequency: f64) -> Self {
+        assert!(
+            size >= 2 && size.is_power_of_two(),
+            "size must be a power of two"
+        );
+
+        let map = synthesize(size, seed, spectral_exponent, cutoff_frequency);
+        Self {
+            size,
+            seed,
+            spectral_exponent,
+            cutoff_frequency,
+            map,
+        }
+    }
+
+    // The size×size height-map, values in [−1, +1]
+    pub fn generate(&self) -> HeightMap2D {
+        self.map.clone()
+    }
+}
+
+// Signed frequency of FFT bin `k`, bins past the middle are negative
+fn signed_frequency(k: usize, n: usize) -> f64 {
+    if k <= n / 2 {
+        k as f64
+    } else {
+        k as f64 - n as f64
+    }
+}
+
+fn synthesize(n: usize, seed: u64, exponent: f64, cutoff: f64) -> HeightMap2D {
+    let mut rng = XorShiftRng::new(seed ^ 0x5EC7_2A1F_0F7E_11A5_u64);
+    let mut spectrum = vec![Complex::new(0.0f64, 0.0); n * n];
+
+    for v in 0..n {
+        for u in 0..n {
+            // Each bin's mirror (-u, -v) gets the conjugate, so the inverse
+            // transform comes out real. Filled once, from whichever comes first
+            let (mu, mv) = ((n - u) % n, (n - v) % n);
+            let (i, mirror) = (v * n + u, mv * n + mu);
+            if mirror < i {
+                spectrum[i] = spectrum[mirror].conj();
+                continue;
+            }
+
+            let f = signed_frequency(u, n).hypot(signed_frequency(v, n));
+            let phase = rng.next_f64() * std::f64::consts::TAU;
+            if f == 0.0 || f < cutoff {
+                continue;
+            }
+            let amplitude = f.powf(-exponent / 2.0);
+            // Bins that are their own mirror (the Nyquist ones) must be real
+            spectrum[i] = if mirror == i {
+                Complex::new(amplitude * phase.cos().signum(), 0.0)
+            } else {
+                Complex::from_polar(amplitude, phase)
+            };
+        }
+    }
+
+    // 2D inverse FFT: every row, then every column through a transpose
+    let fft = FftPlanner::new().plan_fft_inverse(n);
+    fft.process(&mut spectrum);
+    let mut columns = vec![Complex::new(0.0, 0.0); n * n];
+    transpose(&spectrum, &mut columns, n);
+    fft.process(&mut columns);
+    transpose(&columns, &mut spectrum, n);
+
+    // Stretch to [−1, +1]
+    let (min, max) = spectrum.iter().fold((f64::MAX, f64::MIN), |(lo, hi), c| {
+        (lo.min(c.re), hi.max(c.re))
+    });
+    let range = (max - min).max(f64::EPSILON);
+    spectrum
+        .chunks(n)
+        .map(|row| {
+            row.iter()
+                .map(|c| ((c.re - min) / range * 2.0 - 1.0) as f32)
+                .collect()
+        })
+        .collect()
+}
+
+fn transpose(src: &[Complex<f64>], dst: &mut [Complex<f64>], n: usize) {
+    for y in 0..n {
+        for x in 0..n {
+            dst[x * n + y] = src[y * n + x];
+        }
+    }
+}
+
+impl NoiseGenerator for SpectralTerrain {
+    // (x, y) in [0, 1] covers the whole grid
+    fn get2(&self, x: f64, y: f64) -> f64 {
+        let scale = (self.size - 1) as f64;
+        sample_bilinear(&self.map, (x * scale) as f32, (y * scale) as f32) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpectralTerrain;
+    use crate::NoiseGenerator;
+
+    // Mean absolute difference between horizontal neighbours
+    fn roughness(map: &[Vec<f32>]) -> f32 {
+        let mut total = 0.0;
+        let mut count = 0;
+        for row in map {
+            for pair in row.windows(2) {
+                total += (pair[1] - pair[0]).abs();
+                count += 1;
+            }
+        }
+        total / count as f32
+    }
+
+    #[test]
+    fn spectral_is_deterministic_and_in_range() {
+        let a = SpectralTerrain::new(64, 7, 2.0, 0.0).generate();
+        let b = SpectralTerrain::new(64, 7, 2.0, 0.0).generate();
+        let c = SpectralTerrain::new(64, 8, 2.0, 0.0).generate();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!((a.len(), a[0].len()), (64, 64));
+        for &v in a.iter().flatten() {
+            assert!((-1.0..=1.0).contains(&v), "value {} out of range", v);
+        }
+
+        let terrain = SpectralTerrain::new(64, 7, 2.0, 0.0);
+        assert_eq!(terrain.get2(0.0, 0.0), a[0][0] as f64);
+        assert_eq!(terrain.get2(1.0, 1.0), a[63][63] as f64);
+    }
+
+    #[test]
+    fn spectral_exponent_and_cutoff_shape_the_terrain() {
+        let smooth = SpectralTerrain::new(128, 3, 3.0, 0.0).generate();
+        let rough = SpectralTerrain::new(128, 3, 1.0, 0.0).generate();
+        assert!(roughness(&smooth) < roughness(&rough) * 0.5);
+
+        // Without its lowest frequencies each row averages out near zero
+        let filtered = SpectralTerrain::new(128, 3, 2.0, 8.0).generate();
+        let unfiltered = SpectralTerrain::new(128, 3, 2.0, 0.0).generate();
+        let row_mean_spread = |map: &[Vec<f32>]| {
+            let means: Vec<f32> = map.iter().map(|r| r.iter().sum::<f32>() / 128.0).collect();
+            means.iter().fold(f32::MIN, |m, &v| m.max(v))
+                - means.iter().fold(f32::MAX, |m, &v| m.min(v))
+        };
+        assert!(row_mean_spread(&filtered) < row_mean_spread(&unfiltered));
+    }
+
+    #[test]
+    fn spectral_deserialize_rejects_bad_size() {
+        let json = r#"{"size":100,"seed":1,"spectral_exponent":2.0,"cutoff_frequency":0.0}"#;
+        let err = serde_json::from_str::<SpectralTerrain>(json).err().unwrap();
+        assert!(err.to_string().contains("power of two"));
+    }
+
+    #[test]
+    #[should_panic(expected = "power of two")]
+    fn spectral_rejects_non_power_of_two() {
+        SpectralTerrain::new(100, 1, 2.0, 0.0);
+    }
+}
@@ -1,18 +1,567 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut, Index, IndexMut};
+use std::path::Path;
+
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::climate::BiomeType;
+
 const GAMMA_CORRECTION: f32 = 1.2;
-const WATER_THRESHOLD: f32 = 0.3;
+pub const WATER_THRESHOLD: f32 = 0.3;
 const SAND_THRESHOLD: f32 = 0.4;
 const GRASS_THRESHOLD: f32 = 0.6;
 const ROCK_THRESHOLD: f32 = 0.8;
 
-// 2D height map: row‐major Vec<Vec<f32>> of size N×N
-// access as `map[y][x]`.
-pub type HeightMap2D = Vec<Vec<f32>>;
+// 2D height map: row‐major rows of width cells, height rows.
+// `map[(x, y)]` is the cell at column x, row y. `map[y]` is row y, so
+// `map[y][x]` still works, and the rows deref to the Vec<Vec<f32>> inside
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HeightMap2D(pub Vec<Vec<f32>>);
+
+impl HeightMap2D {
+    // width × height cells, all `value`
+    pub fn filled(width: usize, height: usize, value: f32) -> Self {
+        Self(vec![vec![value; width]; height])
+    }
+
+    // The underlying rows
+    pub fn raw(&self) -> &Vec<Vec<f32>> {
+        &self.0
+    }
+
+    pub fn into_raw(self) -> Vec<Vec<f32>> {
+        self.0
+    }
+
+    // Inverse of flatten2, e.g. for maps loaded back from MongoDB
+    // Panics unless `flat` holds exactly width × height values
+    pub fn from_flat(flat: Vec<f32>, width: usize, height: usize) -> Self {
+        assert_eq!(
+            flat.len(),
+            width * height,
+            "flat length must be width × height"
+        );
+        if width == 0 {
+            return Self(vec![Vec::new(); height]);
+        }
+        flat.chunks_exact(width).map(|row| row.to_vec()).collect()
+    }
+
+    // `for (x, y, h) in map.iter2() { ... }`, row-major
+    // `for row in &map` still walks rows, like the Vec<Vec<f32>> it wraps
+    pub fn iter2(&self) -> HeightMap2DIter<'_> {
+        iter2(self)
+    }
+
+    // Text preview at most width × height characters, one of ASCII_LEVELS per
+    // cell from dark (0) to bright (1). Larger maps keep every n-th row/column
+    pub fn to_ascii(&self, width: usize, height: usize) -> String {
+        let rows = self.0.len();
+        let cols = self.0.first().map_or(0, Vec::len);
+        let step_x = cols.div_ceil(width.max(1)).max(1);
+        let step_y = rows.div_ceil(height.max(1)).max(1);
+        let levels: Vec<char> = ASCII_LEVELS.chars().collect();
+
+        let mut out = String::new();
+        for row in self.0.iter().step_by(step_y) {
+            for &h in row.iter().step_by(step_x) {
+                out.push(levels[(h.clamp(0.0, 1.0) * 9.0) as usize]);
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+// Ten height bands for to_ascii(), lowest first
+const ASCII_LEVELS: &str = " .:-=+*#%@";
+
+// ASCII art of the map in at most 64×32 characters, for terminal debugging
+impl fmt::Display for HeightMap2D {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_ascii(64, 32))
+    }
+}
+
+impl Deref for HeightMap2D {
+    type Target = Vec<Vec<f32>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for HeightMap2D {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Index<(usize, usize)> for HeightMap2D {
+    type Output = f32;
+
+    fn index(&self, (x, y): (usize, usize)) -> &f32 {
+        &self.0[y][x]
+    }
+}
+
+impl IndexMut<(usize, usize)> for HeightMap2D {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut f32 {
+        &mut self.0[y][x]
+    }
+}
+
+// Row access, needed alongside the (x, y) impl as indexing doesn't deref
+// once the type has an Index impl of its own
+impl Index<usize> for HeightMap2D {
+    type Output = Vec<f32>;
+
+    fn index(&self, y: usize) -> &Vec<f32> {
+        &self.0[y]
+    }
+}
+
+impl IndexMut<usize> for HeightMap2D {
+    fn index_mut(&mut self, y: usize) -> &mut Vec<f32> {
+        &mut self.0[y]
+    }
+}
+
+impl From<Vec<Vec<f32>>> for HeightMap2D {
+    fn from(rows: Vec<Vec<f32>>) -> Self {
+        Self(rows)
+    }
+}
+
+// (flat data, size) for a size × size map, see HeightMap2D::from_flat()
+impl From<(Vec<f32>, usize)> for HeightMap2D {
+    fn from((flat, size): (Vec<f32>, usize)) -> Self {
+        Self::from_flat(flat, size, size)
+    }
+}
+
+impl From<HeightMap2D> for Vec<Vec<f32>> {
+    fn from(map: HeightMap2D) -> Self {
+        map.0
+    }
+}
+
+impl FromIterator<Vec<f32>> for HeightMap2D {
+    fn from_iter<I: IntoIterator<Item = Vec<f32>>>(rows: I) -> Self {
+        Self(rows.into_iter().collect())
+    }
+}
+
+impl IntoIterator for HeightMap2D {
+    type Item = Vec<f32>;
+    type IntoIter = std::vec::IntoIter<Vec<f32>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a HeightMap2D {
+    type Item = &'a Vec<f32>;
+    type IntoIter = std::slice::Iter<'a, Vec<f32>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut HeightMap2D {
+    type Item = &'a mut Vec<f32>;
+    type IntoIter = std::slice::IterMut<'a, Vec<f32>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
+impl PartialEq<Vec<Vec<f32>>> for HeightMap2D {
+    fn eq(&self, other: &Vec<Vec<f32>>) -> bool {
+        &self.0 == other
+    }
+}
+
+impl PartialEq<HeightMap2D> for Vec<Vec<f32>> {
+    fn eq(&self, other: &HeightMap2D) -> bool {
+        self == &other.0
+    }
+}
+
+// 3D volume: Vec<Vec<Vec<f32>>> of size depth×height×width
+// access as `map[z][y][x]`.
+pub type HeightMap3D = Vec<Vec<Vec<f32>>>;
 
 // flatten a 2D height map (row‐major) into a single Vec<f32>
 // For storing into MongoDB as a flat array
 // For converting to an image buffer (e.g. grayscale u8) in the UI
 pub fn flatten2(map: &HeightMap2D) -> Vec<f32> {
-    map.iter().flat_map(|row| row.iter().cloned()).collect()
+    iter2(map).map(|(_, _, v)| v).collect()
+}
+
+// Row-major walk over a 2D height map, yielding (x, y, value)
+pub struct HeightMap2DIter<'a> {
+    map: &'a HeightMap2D,
+    x: usize,
+    y: usize,
+}
+
+impl Iterator for HeightMap2DIter<'_> {
+    type Item = (usize, usize, f32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Skip past the end of each row, empty rows included
+        while let Some(row) = self.map.get(self.y) {
+            if let Some(&v) = row.get(self.x) {
+                let item = (self.x, self.y, v);
+                self.x += 1;
+                return Some(item);
+            }
+            self.x = 0;
+            self.y += 1;
+        }
+        None
+    }
+}
+
+// `for (x, y, h) in iter2(&map) { ... }`
+pub fn iter2(map: &HeightMap2D) -> HeightMap2DIter<'_> {
+    HeightMap2DIter { map, x: 0, y: 0 }
+}
+
+// Same as HeightMap2D::from_flat()
+pub fn from_flat(flat: Vec<f32>, width: usize, height: usize) -> HeightMap2D {
+    HeightMap2D::from_flat(flat, width, height)
+}
+
+// flatten a 3D volume into a single Vec<f32>, z then y then x
+pub fn flatten3(map: &HeightMap3D) -> Vec<f32> {
+    map.iter()
+        .flat_map(|layer| layer.iter().flat_map(|row| row.iter().cloned()))
+        .collect()
+}
+
+// Horizontal slice at depth z, indexed [y][x]
+pub fn slice_xy(map: &HeightMap3D, z: usize) -> HeightMap2D {
+    HeightMap2D(map[z].clone())
+}
+
+// Vertical slice at row y, indexed [z][x]
+pub fn slice_xz(map: &HeightMap3D, y: usize) -> HeightMap2D {
+    map.iter().map(|layer| layer[y].clone()).collect()
+}
+
+// Vertical slice at column x, indexed [z][y]
+pub fn slice_yz(map: &HeightMap3D, x: usize) -> HeightMap2D {
+    map.iter()
+        .map(|layer| layer.iter().map(|row| row[x]).collect())
+        .collect()
+}
+
+// The width × height block whose top-left cell is (x, y)
+// Panics if the block doesn't fit inside `map`
+pub fn crop(map: &HeightMap2D, x: usize, y: usize, width: usize, height: usize) -> HeightMap2D {
+    assert!(
+        y + height <= map.len() && x + width <= map.first().map_or(0, Vec::len),
+        "crop region must lie inside the map"
+    );
+    map.raw()[y..y + height]
+        .iter()
+        .map(|row| row[x..x + width].to_vec())
+        .collect()
+}
+
+// Load an externally authored heightmap (WorldMachine, Houdini, hand drawn, ...)
+// 8-bit and 16-bit grayscale PNGs are scaled by their full range into [0,1],
+// colour images are converted to luminance first.
+// Non-square images are returned with their own width and height
+pub fn from_png_heightmap(path: &Path) -> image::ImageResult<HeightMap2D> {
+    let img = image::open(path)?;
+    let (w, h) = (img.width() as usize, img.height() as usize);
+
+    let flat: Vec<f32> = match img {
+        DynamicImage::ImageLuma8(buf) => buf.into_raw().iter().map(|&v| v as f32 / 255.0).collect(),
+        DynamicImage::ImageLuma16(buf) => {
+            buf.into_raw().iter().map(|&v| v as f32 / 65535.0).collect()
+        }
+        other => other.to_luma32f().into_raw(),
+    };
+
+    Ok(flat
+        .chunks(w.max(1))
+        .take(h)
+        .map(|row| row.to_vec())
+        .collect())
+}
+
+// Mirror left <-> right
+pub fn flip_horizontal(map: &HeightMap2D) -> HeightMap2D {
+    map.iter()
+        .map(|row| row.iter().rev().cloned().collect())
+        .collect()
+}
+
+// Mirror top <-> bottom
+pub fn flip_vertical(map: &HeightMap2D) -> HeightMap2D {
+    map.iter().rev().cloned().collect()
+}
+
+// Rotate 90° clockwise, an h×w map becomes w×h
+pub fn rotate90_cw(map: &HeightMap2D) -> HeightMap2D {
+    let h = map.len();
+    let w = if h > 0 { map[0].len() } else { 0 };
+    // New row y is old column y read bottom to top
+    (0..w)
+        .map(|x| (0..h).rev().map(|y| map[y][x]).collect())
+        .collect()
+}
+
+// Rotate 90° counter-clockwise, an h×w map becomes w×h
+pub fn rotate90_ccw(map: &HeightMap2D) -> HeightMap2D {
+    let h = map.len();
+    let w = if h > 0 { map[0].len() } else { 0 };
+    // New row y is old column (w - 1 - y) read top to bottom
+    (0..w)
+        .rev()
+        .map(|x| (0..h).map(|y| map[y][x]).collect())
+        .collect()
+}
+
+// Rotate 180°, same as flipping both ways
+pub fn rotate180(map: &HeightMap2D) -> HeightMap2D {
+    map.iter()
+        .rev()
+        .map(|row| row.iter().rev().cloned().collect())
+        .collect()
+}
+
+// Height at the fractional cell position (x, y), bilinearly interpolated
+// Positions outside the map are clamped to the nearest edge
+pub fn sample_bilinear(map: &HeightMap2D, x: f32, y: f32) -> f32 {
+    let h = map.len();
+    let w = map[0].len();
+    let x = x.clamp(0.0, (w - 1) as f32);
+    let y = y.clamp(0.0, (h - 1) as f32);
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+    let top = map[y0][x0] + (map[y0][x1] - map[y0][x0]) * tx;
+    let bottom = map[y1][x0] + (map[y1][x1] - map[y1][x0]) * tx;
+    top + (bottom - top) * ty
+}
+
+// How blend_maps() combines a layer with the map below it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlendMode {
+    #[default]
+    Add,
+    Multiply,
+    Screen,
+    Max,
+}
+
+// Blend `layer` onto `base` in place, both the same size
+// weight fades from base (0) to the full blend (1), for Add that is
+// base + weight × layer. Screen and Multiply expect heights in [0,1]
+pub fn blend_maps(base: &mut HeightMap2D, layer: &HeightMap2D, mode: BlendMode, weight: f32) {
+    for (row, layer_row) in base.iter_mut().zip(layer) {
+        for (b, &l) in row.iter_mut().zip(layer_row) {
+            let blended = match mode {
+                BlendMode::Add => *b + l,
+                BlendMode::Multiply => *b * l,
+                BlendMode::Screen => 1.0 - (1.0 - *b) * (1.0 - l),
+                BlendMode::Max => b.max(l),
+            };
+            *b += (blended - *b) * weight;
+        }
+    }
+}
+
+// Weighted average of same-size maps, each cell is Σ wᵢ·mapᵢ / Σ wᵢ
+// Panics if the sizes differ or the weights add up to zero
+pub fn blend_weighted(maps: &[(&HeightMap2D, f32)]) -> HeightMap2D {
+    let Some(&(first, _)) = maps.first() else {
+        return HeightMap2D::default();
+    };
+    let same_size = |m: &HeightMap2D| {
+        m.len() == first.len() && m.iter().zip(first).all(|(r, f)| r.len() == f.len())
+    };
+    assert!(
+        maps.iter().all(|(m, _)| same_size(m)),
+        "blend_weighted needs maps of the same size"
+    );
+    let total: f32 = maps.iter().map(|&(_, w)| w).sum();
+    assert!(total != 0.0, "blend_weighted weights add up to zero");
+
+    let mut out = HeightMap2D::filled(first.first().map_or(0, Vec::len), first.len(), 0.0);
+    for &(map, weight) in maps {
+        for (row, src) in out.iter_mut().zip(map) {
+            for (o, &v) in row.iter_mut().zip(src) {
+                *o += v * weight;
+            }
+        }
+    }
+    for v in out.iter_mut().flatten() {
+        *v /= total;
+    }
+    out
+}
+
+// 0 below edge0, 1 above edge1, a smooth S-curve in between
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// `a` where mask < 0.5 and `b` where it is above, the switch softened
+// over a band `feather` wide around 0.5. All three maps the same size,
+// feather <= 0 gives a hard edge
+pub fn blend_smooth(
+    a: &HeightMap2D,
+    b: &HeightMap2D,
+    mask: &HeightMap2D,
+    feather: f32,
+) -> HeightMap2D {
+    let half = feather.max(0.0) / 2.0;
+    a.iter()
+        .zip(b)
+        .zip(mask)
+        .map(|((ra, rb), rm)| {
+            ra.iter()
+                .zip(rb)
+                .zip(rm)
+                .map(|((&x, &y), &m)| {
+                    let t = if half > 0.0 {
+                        smoothstep(0.5 - half, 0.5 + half, m)
+                    } else if m < 0.5 {
+                        0.0
+                    } else {
+                        1.0
+                    };
+                    x + (y - x) * t
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Cell at (x + dx, y + dy), clamped to the map edges
+fn clamped(map: &HeightMap2D, x: usize, y: usize, dx: isize, dy: isize) -> f32 {
+    let h = map.len() as isize;
+    let w = map[0].len() as isize;
+    let sx = (x as isize + dx).clamp(0, w - 1);
+    let sy = (y as isize + dy).clamp(0, h - 1);
+    map[sy as usize][sx as usize]
+}
+
+// Gradient magnitude sqrt(Gx² + Gy²) from the 3×3 Sobel operator
+// Edges are clamped, so a flat map gives all zeros
+pub fn sobel_magnitude(map: &HeightMap2D) -> HeightMap2D {
+    let mut out = HeightMap2D::filled(map.first().map_or(0, Vec::len), map.len(), 0.0);
+    for (y, row) in out.iter_mut().enumerate() {
+        for (x, val) in row.iter_mut().enumerate() {
+            let p = |dx, dy| clamped(map, x, y, dx, dy);
+            let gx = (p(1, -1) + 2.0 * p(1, 0) + p(1, 1)) - (p(-1, -1) + 2.0 * p(-1, 0) + p(-1, 1));
+            let gy = (p(-1, 1) + 2.0 * p(0, 1) + p(1, 1)) - (p(-1, -1) + 2.0 * p(0, -1) + p(1, -1));
+            *val = (gx * gx + gy * gy).sqrt();
+        }
+    }
+    out
+}
+
+// Gaussian blur with standard deviation `radius` cells, done as two 1D passes
+// The kernel is cut off at 3 × radius, radius <= 0 returns a copy
+pub fn gaussian_blur(map: &HeightMap2D, radius: f32) -> HeightMap2D {
+    if radius <= 0.0 || map.is_empty() {
+        return map.clone();
+    }
+    let reach = (radius * 3.0).ceil() as isize;
+    let weights: Vec<f32> = (-reach..=reach)
+        .map(|i| (-(i * i) as f32 / (2.0 * radius * radius)).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+
+    let pass = |src: &HeightMap2D, horizontal: bool| -> HeightMap2D {
+        let mut out = src.clone();
+        for (y, row) in out.iter_mut().enumerate() {
+            for (x, val) in row.iter_mut().enumerate() {
+                *val = (-reach..=reach)
+                    .zip(&weights)
+                    .map(|(i, w)| {
+                        let (dx, dy) = if horizontal { (i, 0) } else { (0, i) };
+                        w * clamped(src, x, y, dx, dy)
+                    })
+                    .sum::<f32>()
+                    / total;
+            }
+        }
+        out
+    };
+    pass(&pass(map, true), false)
+}
+
+// Sharpen by adding back the detail a blur removes:
+// original + amount × (original - gaussian_blur(original, radius))
+pub fn unsharp_mask(map: &HeightMap2D, radius: f32, amount: f32) -> HeightMap2D {
+    let blurred = gaussian_blur(map, radius);
+    map.iter()
+        .zip(&blurred)
+        .map(|(row, b_row)| {
+            row.iter()
+                .zip(b_row)
+                .map(|(&v, &b)| v + amount * (v - b))
+                .collect()
+        })
+        .collect()
+}
+
+// Hillshade in [0,1] lit from `azimuth` degrees (clockwise from north, the top
+// row) and `altitude` degrees above the horizon. Scaled so flat ground is 0.5,
+// slopes facing the light get brighter and slopes facing away darker.
+// z_scale - how many cell widths one unit of height represents
+pub fn hillshade(map: &HeightMap2D, z_scale: f32, azimuth: f32, altitude: f32) -> Vec<Vec<f32>> {
+    let (az, alt) = (azimuth.to_radians(), altitude.to_radians());
+    let light = [az.sin() * alt.cos(), -az.cos() * alt.cos(), alt.sin()];
+    // Lambert term of a flat cell, mapped to 0.5
+    let flat = light[2].max(f32::EPSILON);
+
+    let mut out = vec![vec![0.0; map.first().map_or(0, Vec::len)]; map.len()];
+    for (y, row) in out.iter_mut().enumerate() {
+        for (x, val) in row.iter_mut().enumerate() {
+            let p = |dx, dy| clamped(map, x, y, dx, dy);
+            let dzdx = (p(1, 0) - p(-1, 0)) * 0.5 * z_scale;
+            let dzdy = (p(0, 1) - p(0, -1)) * 0.5 * z_scale;
+            let n = [-dzdx, -dzdy, 1.0];
+            let len = (n[0] * n[0] + n[1] * n[1] + 1.0).sqrt();
+            let lambert = (n[0] * light[0] + n[1] * light[1] + n[2] * light[2]) / len;
+            *val = (0.5 * lambert / flat).clamp(0.0, 1.0);
+        }
+    }
+    out
+}
+
+// Like to_terrain_image(), with each color scaled by the matching `shade` value
+// (e.g. a flattened hillshade(), 1.0 leaves the color unchanged)
+pub fn to_terrain_image_shaded(
+    flat: &[f32],
+    shade: &[f32],
+    width: usize,
+    height: usize,
+) -> Vec<u8> {
+    debug_assert_eq!(flat.len(), width * height);
+    debug_assert_eq!(shade.len(), width * height);
+    let mut buf = Vec::with_capacity(flat.len() * 3);
+    for (&h, &s) in flat.iter().zip(shade) {
+        let rgb = height_to_rgb(h).map(|c| (c as f32 * s).clamp(0.0, 255.0) as u8);
+        buf.extend_from_slice(&rgb);
+    }
+    buf
 }
 
 // Linearly interpolate between two RGB triples
@@ -25,7 +574,7 @@ fn lerp_color(a: [u8; 3], b: [u8; 3], t: f32) -> [u8; 3] {
 }
 
 // Map a height in [0.0,1.0] to a realistic terrain color
-fn height_to_rgb(h: f32) -> [u8; 3] {
+pub fn height_to_rgb(h: f32) -> [u8; 3] {
     match h {
         x if x < WATER_THRESHOLD => {
             let t = x / WATER_THRESHOLD;
@@ -40,7 +589,7 @@ fn height_to_rgb(h: f32) -> [u8; 3] {
             lerp_color([34, 139, 34], [50, 205, 50], t) // grass
         }
         x if x < ROCK_THRESHOLD => {
-            let t = (x - SAND_THRESHOLD) / (ROCK_THRESHOLD - GRASS_THRESHOLD);
+            let t = (x - GRASS_THRESHOLD) / (ROCK_THRESHOLD - GRASS_THRESHOLD);
             lerp_color([128, 128, 128], [192, 192, 192], t) // rock
         }
         x => {
@@ -50,36 +599,630 @@ fn height_to_rgb(h: f32) -> [u8; 3] {
     }
 }
 
-// Convert a flat &[f32] into an RGB byte buffer
-pub fn to_terrain_image(flat: &[f32], _size: usize) -> Vec<u8> {
+// Convert a flat, row-major width × height &[f32] into an RGB byte buffer
+// With a biome map (indexed [y][x], see climate::assign_biomes) cells take
+// their biome color, shaded by height, instead of the height bands
+pub fn to_terrain_image(
+    flat: &[f32],
+    width: usize,
+    height: usize,
+    biomes: Option<&[Vec<BiomeType>]>,
+) -> Vec<u8> {
+    debug_assert_eq!(flat.len(), width * height);
+    let mut buf = Vec::with_capacity(flat.len() * 3);
+    for (i, &h) in flat.iter().enumerate() {
+        let rgb = match biomes {
+            Some(biomes) => {
+                let shade = 0.75 + 0.25 * h.clamp(0.0, 1.0);
+                biomes[i / width][i % width]
+                    .color()
+                    .map(|c| (c as f32 * shade) as u8)
+            }
+            None => height_to_rgb(h),
+        };
+        buf.extend_from_slice(&rgb);
+    }
+    buf
+}
+
+// User editable version of the height_to_rgb() bands
+// Default matches height_to_rgb() exactly
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TerrainPalette {
+    // Upper height of water, sand, grass and rock, snow runs up to 1.0
+    pub thresholds: [f32; 4],
+    // (low, high) color of water, sand, grass, rock and snow
+    pub colors: [[[u8; 3]; 2]; 5],
+}
+
+impl Default for TerrainPalette {
+    fn default() -> Self {
+        Self {
+            thresholds: [
+                WATER_THRESHOLD,
+                SAND_THRESHOLD,
+                GRASS_THRESHOLD,
+                ROCK_THRESHOLD,
+            ],
+            colors: [
+                [[0, 0, 128], [0, 128, 255]],
+                [[194, 178, 128], [220, 200, 160]],
+                [[34, 139, 34], [50, 205, 50]],
+                [[128, 128, 128], [192, 192, 192]],
+                [[220, 220, 220], [255, 255, 255]],
+            ],
+        }
+    }
+}
+
+impl TerrainPalette {
+    // Band a height falls in: 0 water, 1 sand, 2 grass, 3 rock, 4 snow
+    pub fn band(&self, h: f32) -> usize {
+        self.thresholds
+            .iter()
+            .position(|&t| h < t)
+            .unwrap_or(self.thresholds.len())
+    }
+
+    // Color of a height in [0.0,1.0]
+    pub fn color(&self, h: f32) -> [u8; 3] {
+        let band = self.band(h);
+        let lo = if band == 0 {
+            0.0
+        } else {
+            self.thresholds[band - 1]
+        };
+        let hi = self.thresholds.get(band).copied().unwrap_or(1.0);
+        let t = if hi > lo { (h - lo) / (hi - lo) } else { 0.0 };
+        let [a, b] = self.colors[band];
+        lerp_color(a, b, t)
+    }
+}
+
+// to_terrain_image() with custom band colors and thresholds
+pub fn to_terrain_image_with_palette(flat: &[f32], palette: &TerrainPalette) -> Vec<u8> {
     let mut buf = Vec::with_capacity(flat.len() * 3);
     for &h in flat {
-        let [r, g, b] = height_to_rgb(h);
-        buf.extend_from_slice(&[r, g, b]);
+        buf.extend_from_slice(&palette.color(h));
     }
     buf
 }
 
+// Ambient occlusion: how much of the sky each cell can see
+// For every cell cast `num_rays` horizontal rays at evenly spaced azimuths
+// and march up to `max_distance` cells along each one, tracking the steepest
+// horizon angle. A ray contributes the sine of that angle as occlusion.
+// Result is in [0,1]: 0 = fully occluded crevice, 1 = exposed ridge / flat ground
+// z_scale - how many cell widths one unit of height represents
+pub fn to_ambient_occlusion(
+    map: &HeightMap2D,
+    num_rays: usize,
+    max_distance: usize,
+    z_scale: f32,
+) -> Vec<Vec<f32>> {
+    let h = map.len();
+    let w = if h > 0 { map[0].len() } else { 0 };
+    let mut ao = vec![vec![1.0f32; w]; h];
+    if num_rays == 0 || max_distance == 0 {
+        return ao;
+    }
+
+    // Precompute ray directions
+    let dirs: Vec<(f32, f32)> = (0..num_rays)
+        .map(|i| {
+            let angle = i as f32 / num_rays as f32 * std::f32::consts::TAU;
+            (angle.cos(), angle.sin())
+        })
+        .collect();
+
+    for (y, row) in ao.iter_mut().enumerate() {
+        for (x, val) in row.iter_mut().enumerate() {
+            let origin = map[y][x] * z_scale;
+            let mut occlusion = 0.0;
+
+            for &(dx, dy) in &dirs {
+                // Largest rise/run seen along the ray
+                let mut max_slope = 0.0f32;
+                for step in 1..=max_distance {
+                    let sx = (x as f32 + dx * step as f32).round();
+                    let sy = (y as f32 + dy * step as f32).round();
+                    // Stop at the map border
+                    if sx < 0.0 || sy < 0.0 || sx >= w as f32 || sy >= h as f32 {
+                        break;
+                    }
+                    let rise = map[sy as usize][sx as usize] * z_scale - origin;
+                    max_slope = max_slope.max(rise / step as f32);
+                }
+                // sin(atan(s)) = s / sqrt(1 + s^2)
+                occlusion += max_slope / (1.0 + max_slope * max_slope).sqrt();
+            }
+
+            *val = 1.0 - occlusion / num_rays as f32;
+        }
+    }
+    ao
+}
+
 // Normalize the final warped terrain
 pub fn normalize2(map: &mut HeightMap2D) {
     let mut min = f32::MAX;
     let mut max = f32::MIN;
 
-    for row in map.iter() {
-        for &val in row.iter() {
-            min = min.min(val);
-            max = max.max(val);
-        }
+    for (_, _, val) in iter2(map) {
+        min = min.min(val);
+        max = max.max(val);
     }
 
+    // A flat map has no range to stretch, keep it flat at mid height (land)
+    // rather than sending it all to 0.0 (deep water)
+    let flat = (max - min).abs() < f32::EPSILON;
     let range = (max - min).max(0.001); // prevent zero-division
     for row in map.iter_mut() {
         for val in row.iter_mut() {
             // normalize
-            *val = (*val - min) / range;
+            *val = if flat { 0.5 } else { (*val - min) / range };
 
             // Gamma curve for contrast boost
             *val = val.powf(GAMMA_CORRECTION);
         }
     }
 }
+
+// Clip to [low, high] and map that range linearly onto [0,1]
+// Unlike normalize2 outliers can't squash the rest of the map, and there is no gamma
+pub fn normalize2_clamped(map: &mut HeightMap2D, low: f32, high: f32) {
+    let range = (high - low).max(0.001); // prevent zero-division
+    for val in map.iter_mut().flatten() {
+        *val = (val.clamp(low, high) - low) / range;
+    }
+}
+
+// Map `sea_level_height` to exactly WATER_THRESHOLD and scale the rest around it,
+// as much as fits in [0,1], so the share of the map under water doesn't change
+pub fn normalize2_fixed_sea_level(map: &mut HeightMap2D, sea_level_height: f32) {
+    let mut min = f32::MAX;
+    let mut max = f32::MIN;
+    for &val in map.iter().flatten() {
+        min = min.min(val);
+        max = max.max(val);
+    }
+
+    // One scale for both sides, limited by whichever side runs out of room first
+    let below = (sea_level_height - min).max(0.0);
+    let above = (max - sea_level_height).max(0.0);
+    let scale = (WATER_THRESHOLD / below).min((1.0 - WATER_THRESHOLD) / above);
+    let scale = if scale.is_finite() { scale } else { 1.0 };
+    for val in map.iter_mut().flatten() {
+        *val = (WATER_THRESHOLD + (*val - sea_level_height) * scale).clamp(0.0, 1.0);
+    }
+}
+
+// Normalize a volume into [0,1]
+// Unlike normalize2 there is no gamma curve, volumes are usually thresholded
+pub fn normalize3(map: &mut HeightMap3D) {
+    let mut min = f32::MAX;
+    let mut max = f32::MIN;
+
+    for &val in map.iter().flatten().flatten() {
+        min = min.min(val);
+        max = max.max(val);
+    }
+
+    let range = (max - min).max(0.001); // prevent zero-division
+    for val in map.iter_mut().flatten().flatten() {
+        *val = (*val - min) / range;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BlendMode, GAMMA_CORRECTION, HeightMap2D, HeightMap3D, TerrainPalette, blend_maps,
+        blend_smooth, blend_weighted, flatten2, flatten3, flip_horizontal, flip_vertical,
+        from_flat, from_png_heightmap, gaussian_blur, hillshade, iter2, normalize2,
+        normalize2_clamped, normalize2_fixed_sea_level, normalize3, rotate90_ccw, rotate90_cw,
+        rotate180, sample_bilinear, slice_xy, slice_xz, slice_yz, sobel_magnitude,
+        to_ambient_occlusion, to_terrain_image, to_terrain_image_shaded,
+        to_terrain_image_with_palette, unsharp_mask,
+    };
+
+    // Non-square map with unique values, e.g. 2×3 = [[0, 1, 2], [3, 4, 5]]
+    fn ramp(w: usize, h: usize) -> HeightMap2D {
+        (0..h)
+            .map(|y| (0..w).map(|x| (y * w + x) as f32).collect())
+            .collect()
+    }
+
+    #[test]
+    fn heightmap_xy_indexing() {
+        let mut map = ramp(3, 2);
+        assert_eq!(map[(2, 0)], 2.0);
+        assert_eq!(map[(0, 1)], map[1][0]);
+        map[(1, 1)] = -1.0;
+        assert_eq!(map.raw()[1], vec![3.0, -1.0, 5.0]);
+        // Serialized like the plain nested Vec
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(json, "[[0.0,1.0,2.0],[3.0,-1.0,5.0]]");
+        assert_eq!(serde_json::from_str::<HeightMap2D>(&json).unwrap(), map);
+    }
+
+    #[test]
+    fn ascii_flat_map_is_one_character() {
+        let text = HeightMap2D::filled(100, 50, 0.5).to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        // Downsampled to fit 64×32
+        assert_eq!(lines.len(), 25);
+        assert!(lines.iter().all(|l| l.len() == 50));
+        assert!(text.chars().all(|c| c == '=' || c == '\n'));
+    }
+
+    #[test]
+    fn ascii_ramp_is_even_gradient() {
+        let ramp: HeightMap2D = vec![(0..10).map(|x| x as f32 / 9.0).collect()].into();
+        assert_eq!(ramp.to_ascii(10, 1), " .:-=+*#%@\n");
+        assert_eq!(ramp.to_ascii(5, 1), " :=*%\n");
+    }
+
+    #[test]
+    fn from_flat_roundtrips_flatten2() {
+        let square = ramp(5, 5);
+        assert_eq!(from_flat(flatten2(&square), 5, 5), square);
+        let wide = ramp(4, 2);
+        assert_eq!(from_flat(flatten2(&wide), 4, 2), wide);
+        assert_eq!(HeightMap2D::from_flat(flatten2(&wide), 4, 2), wide);
+        assert_eq!(HeightMap2D::from((flatten2(&square), 5)), square);
+    }
+
+    #[test]
+    fn iter2_row_major_order() {
+        let m = ramp(3, 2);
+        let cells: Vec<_> = iter2(&m).collect();
+        assert_eq!(cells.len(), 6);
+        assert_eq!(cells[0], (0, 0, 0.0));
+        assert_eq!(cells[4], (1, 1, 4.0));
+        assert!(m.iter2().eq(cells.iter().copied()));
+        // Empty rows are skipped rather than ending the walk
+        let ragged: HeightMap2D = vec![vec![1.0], vec![], vec![2.0]].into();
+        assert_eq!(iter2(&ragged).count(), 2);
+    }
+
+    #[test]
+    fn rotate_and_flip_known_values() {
+        let m = ramp(3, 2);
+        assert_eq!(
+            rotate90_cw(&m),
+            vec![vec![3.0, 0.0], vec![4.0, 1.0], vec![5.0, 2.0]]
+        );
+        assert_eq!(
+            rotate90_ccw(&m),
+            vec![vec![2.0, 5.0], vec![1.0, 4.0], vec![0.0, 3.0]]
+        );
+        assert_eq!(
+            flip_horizontal(&m),
+            vec![vec![2.0, 1.0, 0.0], vec![5.0, 4.0, 3.0]]
+        );
+        assert_eq!(
+            flip_vertical(&m),
+            vec![vec![3.0, 4.0, 5.0], vec![0.0, 1.0, 2.0]]
+        );
+        assert_eq!(
+            rotate180(&m),
+            vec![vec![5.0, 4.0, 3.0], vec![2.0, 1.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn rotate_and_flip_inverses() {
+        for &(w, h) in &[(1, 1), (1, 5), (4, 4), (7, 3), (2, 9)] {
+            let m = ramp(w, h);
+            assert_eq!(rotate90_cw(&rotate90_ccw(&m)), m);
+            assert_eq!(rotate90_ccw(&rotate90_cw(&m)), m);
+            assert_eq!(flip_horizontal(&flip_horizontal(&m)), m);
+            assert_eq!(flip_vertical(&flip_vertical(&m)), m);
+            assert_eq!(rotate180(&m), rotate90_cw(&rotate90_cw(&m)));
+        }
+    }
+
+    #[test]
+    fn ambient_occlusion_flat_is_unoccluded() {
+        let map = HeightMap2D::filled(9, 9, 0.5);
+        let ao = to_ambient_occlusion(&map, 8, 4, 10.0);
+        assert!(ao.iter().flatten().all(|&v| (v - 1.0).abs() < 1e-6));
+    }
+
+    #[test]
+    fn ambient_occlusion_pit_is_darker_than_rim() {
+        // Bowl: low in the middle, high on the outside
+        let map: HeightMap2D = (0..9)
+            .map(|y| {
+                (0..9)
+                    .map(|x| {
+                        let dx = x as f32 - 4.0;
+                        let dy = y as f32 - 4.0;
+                        (dx * dx + dy * dy).sqrt() / 6.0
+                    })
+                    .collect()
+            })
+            .collect();
+        let ao = to_ambient_occlusion(&map, 8, 4, 10.0);
+        assert!(ao[4][4] < ao[0][0]);
+        assert!(ao.iter().flatten().all(|&v| (0.0..=1.0).contains(&v)));
+    }
+
+    #[test]
+    fn png_heightmap_16bit_roundtrip() {
+        let (w, h) = (12u32, 7u32);
+        let buf = image::ImageBuffer::<image::Luma<u16>, _>::from_fn(w, h, |x, y| {
+            image::Luma([((x + y * w) * 700) as u16])
+        });
+        let path = std::env::temp_dir().join("core_png_heightmap_16bit.png");
+        buf.save(&path).unwrap();
+        let map = from_png_heightmap(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(map.len(), h as usize);
+        assert_eq!(map[0].len(), w as usize);
+        assert_eq!(map[0][0], 0.0);
+        assert!((map[1][2] - (14.0 * 700.0) / 65535.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn volume_slices_and_flatten() {
+        // value encodes its own coordinates: z*100 + y*10 + x
+        let vol: HeightMap3D = (0..3)
+            .map(|z| {
+                (0..4)
+                    .map(|y| (0..5).map(|x| (z * 100 + y * 10 + x) as f32).collect())
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(slice_xy(&vol, 2)[3][4], 234.0);
+        let xz = slice_xz(&vol, 1);
+        assert_eq!((xz.len(), xz[0].len()), (3, 5));
+        assert_eq!(xz[2][4], 214.0);
+        let yz = slice_yz(&vol, 3);
+        assert_eq!((yz.len(), yz[0].len()), (3, 4));
+        assert_eq!(yz[1][2], 123.0);
+
+        let flat = flatten3(&vol);
+        assert_eq!(flat.len(), 60);
+        assert_eq!(flat[20 + 2 * 5 + 3], 123.0);
+    }
+
+    #[test]
+    fn normalize3_range() {
+        let mut vol: HeightMap3D = vec![vec![vec![-2.0, 0.0], vec![1.0, 6.0]]; 2];
+        normalize3(&mut vol);
+        assert_eq!(vol[0][0][0], 0.0);
+        assert_eq!(vol[1][1][1], 1.0);
+        assert!(
+            vol.iter()
+                .flatten()
+                .flatten()
+                .all(|v| (0.0..=1.0).contains(v))
+        );
+    }
+
+    #[test]
+    fn default_palette_matches_terrain_image() {
+        let flat: Vec<f32> = (0..=1000).map(|i| i as f32 / 1000.0).collect();
+        let expected = to_terrain_image(&flat, flat.len(), 1, None);
+        let recolored = to_terrain_image_with_palette(&flat, &TerrainPalette::default());
+        assert_eq!(recolored, expected);
+    }
+
+    #[test]
+    fn palette_bands_follow_thresholds() {
+        let mut palette = TerrainPalette::default();
+        // Rock starts dark at its lower threshold and is halfway up at 0.7
+        assert_eq!(palette.color(0.6), [128, 128, 128]);
+        assert!((159..=160).contains(&palette.color(0.7)[0]));
+
+        // Moving the water line moves where sand begins
+        palette.thresholds[0] = 0.1;
+        assert_eq!(palette.color(0.1), [194, 178, 128]);
+        assert_eq!(palette.band(0.05), 0);
+        assert_eq!(palette.band(0.1), 1);
+        assert_eq!(palette.band(1.0), 4);
+    }
+
+    #[test]
+    fn blend_modes() {
+        let base: HeightMap2D = vec![vec![0.5, 0.25]].into();
+        let layer: HeightMap2D = vec![vec![0.5, 0.75]].into();
+        let blend = |mode, weight| {
+            let mut m = base.clone();
+            blend_maps(&mut m, &layer, mode, weight);
+            m
+        };
+        assert_eq!(blend(BlendMode::Add, 0.5), vec![vec![0.75, 0.625]]);
+        assert_eq!(blend(BlendMode::Multiply, 1.0), vec![vec![0.25, 0.1875]]);
+        assert_eq!(blend(BlendMode::Screen, 1.0), vec![vec![0.75, 0.8125]]);
+        assert_eq!(blend(BlendMode::Max, 1.0), vec![vec![0.5, 0.75]]);
+    }
+
+    #[test]
+    fn blend_zero_weight_is_identity() {
+        let base: HeightMap2D = vec![vec![0.1, 0.4], vec![0.9, 0.3]].into();
+        let layer: HeightMap2D = vec![vec![0.7, 0.2], vec![0.5, 1.0]].into();
+        for mode in [
+            BlendMode::Add,
+            BlendMode::Multiply,
+            BlendMode::Screen,
+            BlendMode::Max,
+        ] {
+            let mut m = base.clone();
+            blend_maps(&mut m, &layer, mode, 0.0);
+            assert_eq!(m, base);
+        }
+    }
+
+    #[test]
+    fn blend_weighted_constant_maps() {
+        let low = HeightMap2D::filled(3, 2, 1.0);
+        let high = HeightMap2D::filled(3, 2, 4.0);
+        // (1 × 1 + 4 × 3) / 4
+        assert_eq!(
+            blend_weighted(&[(&low, 1.0), (&high, 3.0)]),
+            vec![vec![3.25; 3]; 2]
+        );
+        assert!(blend_weighted(&[]).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "same size")]
+    fn blend_weighted_rejects_mismatched_sizes() {
+        let a = HeightMap2D::filled(3, 2, 0.0);
+        let b = HeightMap2D::filled(2, 2, 0.0);
+        blend_weighted(&[(&a, 1.0), (&b, 1.0)]);
+    }
+
+    #[test]
+    fn blend_smooth_feathers_the_mask() {
+        let a: HeightMap2D = vec![vec![0.0; 5]].into();
+        let b: HeightMap2D = vec![vec![1.0; 5]].into();
+        let mask: HeightMap2D = vec![vec![0.0, 0.4, 0.5, 0.6, 1.0]].into();
+        assert_eq!(
+            blend_smooth(&a, &b, &mask, 0.0),
+            vec![vec![0.0, 0.0, 1.0, 1.0, 1.0]]
+        );
+        let soft = blend_smooth(&a, &b, &mask, 0.4);
+        assert_eq!(soft[0][0], 0.0);
+        assert_eq!(soft[0][2], 0.5);
+        assert_eq!(soft[0][4], 1.0);
+        assert!(soft[0][1] > 0.0 && soft[0][1] < 0.5);
+        assert!((soft[0][1] + soft[0][3] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bilinear_sampling() {
+        let map: HeightMap2D = vec![vec![0.0, 1.0], vec![2.0, 3.0]].into();
+        assert_eq!(sample_bilinear(&map, 0.0, 0.0), 0.0);
+        assert_eq!(sample_bilinear(&map, 1.0, 1.0), 3.0);
+        assert_eq!(sample_bilinear(&map, 0.5, 0.0), 0.5);
+        assert_eq!(sample_bilinear(&map, 0.5, 0.5), 1.5);
+        // Clamped to the edges
+        assert_eq!(sample_bilinear(&map, -3.0, 5.0), 2.0);
+    }
+
+    #[test]
+    fn terrain_image_non_square() {
+        use crate::climate::BiomeType;
+
+        // 10 wide, 5 tall: a biome per row, so indexing by width is visible
+        let flat = vec![0.5; 10 * 5];
+        let rows = [
+            BiomeType::Ocean,
+            BiomeType::Desert,
+            BiomeType::Forest,
+            BiomeType::Tundra,
+            BiomeType::Snow,
+        ];
+        let biomes: Vec<Vec<BiomeType>> = rows.iter().map(|&b| vec![b; 10]).collect();
+        let img = to_terrain_image(&flat, 10, 5, Some(&biomes));
+        assert_eq!(img.len(), 10 * 5 * 3);
+        for (y, biome) in rows.iter().enumerate() {
+            let shaded = to_terrain_image(&[0.5], 1, 1, Some(&[vec![*biome]]));
+            let last = (y * 10 + 9) * 3;
+            assert_eq!(&img[y * 30..y * 30 + 3], &shaded[..]);
+            assert_eq!(&img[last..last + 3], &shaded[..]);
+        }
+        assert_eq!(to_terrain_image(&flat, 10, 5, None).len(), 150);
+    }
+
+    #[test]
+    fn terrain_image_with_biomes() {
+        use crate::climate::BiomeType;
+
+        let flat = [1.0, 1.0, 0.0, 0.0];
+        let biomes = vec![
+            vec![BiomeType::Forest, BiomeType::Desert],
+            vec![BiomeType::Ocean, BiomeType::Snow],
+        ];
+        let img = to_terrain_image(&flat, 2, 2, Some(&biomes));
+        assert_eq!(&img[0..3], &BiomeType::Forest.color());
+        assert_eq!(&img[3..6], &BiomeType::Desert.color());
+        // Low cells are darkened
+        assert!(img[11] < BiomeType::Snow.color()[2]);
+    }
+
+    #[test]
+    fn sobel_flat_and_step_edge() {
+        let flat = HeightMap2D::filled(6, 4, 0.5);
+        assert!(sobel_magnitude(&flat).iter().flatten().all(|&v| v == 0.0));
+
+        // Step between columns 2 and 3 of a non-square map
+        let step: HeightMap2D = (0..4)
+            .map(|_| (0..6).map(|x| if x < 3 { 0.0 } else { 1.0 }).collect())
+            .collect();
+        let edges = sobel_magnitude(&step);
+        for row in &edges {
+            assert_eq!(row[0], 0.0);
+            assert!(row[2] > 0.0 && row[3] > 0.0);
+            assert_eq!(row[5], 0.0);
+        }
+    }
+
+    #[test]
+    fn blur_and_unsharp_mask() {
+        let mut spike = HeightMap2D::filled(7, 5, 0.0);
+        spike[2][3] = 1.0;
+        let blurred = gaussian_blur(&spike, 1.0);
+        // Spread out evenly on both sides of the spike
+        assert!(blurred[2][3] < 1.0 && blurred[2][2] > 0.0);
+        assert!((blurred[2][2] - blurred[2][4]).abs() < 1e-6);
+
+        let sharp = unsharp_mask(&spike, 1.0, 1.0);
+        assert!(sharp[2][3] > 1.0);
+        assert!(sharp[2][2] < 0.0);
+        assert_eq!(unsharp_mask(&spike, 1.0, 0.0), spike);
+    }
+
+    #[test]
+    fn hillshade_flat_and_slopes() {
+        let flat = hillshade(&HeightMap2D::filled(5, 4, 0.3), 1.0, 315.0, 45.0);
+        assert!(flat.iter().flatten().all(|&v| (v - 0.5).abs() < 1e-6));
+
+        // Ground rising towards the east faces west, into a light from the west
+        let slope: HeightMap2D = (0..4).map(|_| (0..5).map(|x| x as f32).collect()).collect();
+        let from_west = hillshade(&slope, 0.5, 270.0, 45.0);
+        let from_east = hillshade(&slope, 0.5, 90.0, 45.0);
+        assert!(from_west[1][2] > 0.5 && from_east[1][2] < 0.5);
+    }
+
+    #[test]
+    fn shaded_image_full_shade_is_plain() {
+        let flat = [0.1, 0.35, 0.5, 0.7, 0.9, 1.0];
+        let plain = to_terrain_image(&flat, 6, 1, None);
+        assert_eq!(to_terrain_image_shaded(&flat, &[1.0; 6], 6, 1), plain);
+        let dark = to_terrain_image_shaded(&flat, &[0.5; 6], 6, 1);
+        assert!(dark.iter().zip(&plain).all(|(d, p)| d <= p));
+    }
+
+    #[test]
+    fn normalize2_flat_map_stays_flat() {
+        let mut m = HeightMap2D::filled(10, 10, 0.5);
+        normalize2(&mut m);
+        let expected = 0.5f32.powf(GAMMA_CORRECTION);
+        assert!(m.iter().flatten().all(|&v| v == expected));
+    }
+
+    #[test]
+    fn normalize2_clamped_clips() {
+        let mut m: HeightMap2D = vec![vec![-3.0, -1.0, 0.0, 1.0, 5.0]].into();
+        normalize2_clamped(&mut m, -1.0, 1.0);
+        assert_eq!(m, vec![vec![0.0, 0.0, 0.5, 1.0, 1.0]]);
+    }
+
+    #[test]
+    fn normalize2_fixed_sea_level_keeps_sea() {
+        let mut m: HeightMap2D = vec![vec![-0.8, -0.2, 0.1], vec![0.4, 0.9, -0.2]].into();
+        normalize2_fixed_sea_level(&mut m, -0.2);
+        assert_eq!(m[0][1], 0.3);
+        assert_eq!(m[1][2], 0.3);
+        // The lowest cell hits 0, the highest stays within range
+        assert!(m[0][0].abs() < 1e-6);
+        assert!(m.iter().flatten().all(|v| (0.0..=1.0).contains(v)));
+    }
+}
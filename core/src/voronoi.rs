@@ -0,0 +1,152 @@
+use crate::rng::XorShiftRng;
+use crate::utils::HeightMap2D;
+
+// Distance metric used to find the nearest site
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum DistanceMode {
+    #[default]
+    Euclidean, // round, natural looking regions
+    Manhattan, // diamond shaped regions with straight edges
+}
+
+// Voronoi diagram over a width×height grid, for biome regions
+pub struct VoronoiDiagram {
+    seed: u64,
+    num_cells: usize, // number of sites (regions)
+    width: usize,
+    height: usize,
+    mode: DistanceMode,
+}
+
+impl VoronoiDiagram {
+    pub fn new(
+        seed: u64,
+        num_cells: usize,
+        width: usize,
+        height: usize,
+        mode: DistanceMode,
+    ) -> Self {
+        assert!(num_cells > 0, "num_cells must be at least 1");
+        Self {
+            seed,
+            num_cells,
+            width,
+            height,
+            mode,
+        }
+    }
+
+    // Scatter the sites uniformly across the grid
+    fn sites(&self) -> Vec<(f64, f64)> {
+        let mut rng = XorShiftRng::new(self.seed ^ 0x9E37_79B9_7F4A_7C15_u64);
+        (0..self.num_cells)
+            .map(|_| {
+                (
+                    rng.next_f64() * self.width as f64,
+                    rng.next_f64() * self.height as f64,
+                )
+            })
+            .collect()
+    }
+
+    // Return a height×width grid, access as `grid[y][x]`,
+    // where every cell holds the index of its nearest site
+    pub fn generate(&self) -> Vec<Vec<usize>> {
+        let sites = self.sites();
+        let mut grid = vec![vec![0usize; self.width]; self.height];
+
+        for (y, row) in grid.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                // Sample at the centre of the cell
+                let px = x as f64 + 0.5;
+                let py = y as f64 + 0.5;
+
+                let mut best = f64::MAX;
+                for (i, &(sx, sy)) in sites.iter().enumerate() {
+                    let dx = px - sx;
+                    let dy = py - sy;
+                    // Squared distance is enough for comparing Euclidean distances
+                    let dist = match self.mode {
+                        DistanceMode::Euclidean => dx * dx + dy * dy,
+                        DistanceMode::Manhattan => dx.abs() + dy.abs(),
+                    };
+                    if dist < best {
+                        best = dist;
+                        *cell = i;
+                    }
+                }
+            }
+        }
+        grid
+    }
+}
+
+// Give every Voronoi region a single random height in [0, 1]
+// The flat regions make good mesa / plateau terrain
+pub fn voronoi_heights(diagram: &[Vec<usize>], rng_seed: u64) -> HeightMap2D {
+    let regions = diagram
+        .iter()
+        .flat_map(|row| row.iter())
+        .max()
+        .map_or(0, |&m| m + 1);
+
+    let mut rng = XorShiftRng::new(rng_seed ^ 0xA076_1D64_78BD_642F_u64);
+    let heights: Vec<f32> = (0..regions).map(|_| rng.next_f64() as f32).collect();
+
+    diagram
+        .iter()
+        .map(|row| row.iter().map(|&i| heights[i]).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DistanceMode, VoronoiDiagram, voronoi_heights};
+
+    #[test]
+    fn voronoi_dimensions_and_indices() {
+        let v = VoronoiDiagram::new(1, 10, 40, 25, DistanceMode::Euclidean);
+        let grid = v.generate();
+        assert_eq!(grid.len(), 25);
+        assert_eq!(grid[0].len(), 40);
+        assert!(grid.iter().flatten().all(|&i| i < 10));
+    }
+
+    #[test]
+    fn voronoi_determinism() {
+        for mode in [DistanceMode::Euclidean, DistanceMode::Manhattan] {
+            let a = VoronoiDiagram::new(77, 16, 32, 32, mode).generate();
+            let b = VoronoiDiagram::new(77, 16, 32, 32, mode).generate();
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn voronoi_seeds_cancelling_the_mix_constant() {
+        // These seeds zero the xorshift state, which used to stall it at 0
+        let grid = VoronoiDiagram::new(0x9E37_79B9_7F4A_7C15, 8, 32, 32, DistanceMode::Euclidean)
+            .generate();
+        let mut regions: Vec<usize> = grid.iter().flatten().copied().collect();
+        regions.sort_unstable();
+        regions.dedup();
+        assert!(regions.len() > 1);
+
+        let heights = voronoi_heights(&grid, 0xA076_1D64_78BD_642F);
+        assert!(heights.iter().flatten().any(|&h| h > 0.0));
+    }
+
+    #[test]
+    fn voronoi_heights_flat_per_region() {
+        let grid = VoronoiDiagram::new(5, 8, 32, 32, DistanceMode::Manhattan).generate();
+        let map = voronoi_heights(&grid, 3);
+        let mut seen = [None; 8];
+        for (y, row) in grid.iter().enumerate() {
+            for (x, &i) in row.iter().enumerate() {
+                let h = map[y][x];
+                assert!((0.0..=1.0).contains(&h));
+                // Every cell of a region shares the same height
+                assert_eq!(*seen[i].get_or_insert(h), h);
+            }
+        }
+    }
+}
@@ -0,0 +1,134 @@
+// Golden file regression tests for the noise generators
+//
+// Each test samples a fixed grid and compares it byte-for-byte with a raw
+// little-endian f32 file in tests/golden/. Run with UPDATE_GOLDEN=1 to
+// rewrite the files after an intentional change to the output
+
+use core::utils::flatten2;
+use core::{DomainWarp2D, Fractal2D, NoiseGenerator, Perlin2D, Simplex2D};
+use std::path::PathBuf;
+
+const GRID: usize = 64;
+
+// Samples `noise` on a GRID×GRID lattice with a fixed step
+fn sample_grid(noise: &dyn NoiseGenerator) -> Vec<f32> {
+    let mut out = Vec::with_capacity(GRID * GRID);
+    for y in 0..GRID {
+        for x in 0..GRID {
+            out.push(noise.get2(x as f64 * 0.37, y as f64 * 0.37) as f32);
+        }
+    }
+    out
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.bin", name))
+}
+
+fn to_bytes(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn from_bytes(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+fn check_golden(name: &str, actual: &[f32]) {
+    let path = golden_path(name);
+    if std::env::var("UPDATE_GOLDEN").is_ok_and(|v| v == "1") {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, to_bytes(actual)).unwrap();
+        return;
+    }
+
+    let bytes = std::fs::read(&path).unwrap_or_else(|e| {
+        panic!(
+            "{}: {} (run with UPDATE_GOLDEN=1 to create it)",
+            path.display(),
+            e
+        )
+    });
+    let expected = from_bytes(&bytes);
+    assert_eq!(
+        expected.len(),
+        actual.len(),
+        "{}: sample count changed",
+        name
+    );
+
+    // Report where the output drifted rather than dumping both grids
+    let diffs: Vec<usize> = (0..actual.len())
+        .filter(|&i| expected[i].to_bits() != actual[i].to_bits())
+        .collect();
+    if let Some(&first) = diffs.first() {
+        let max_delta = diffs
+            .iter()
+            .map(|&i| (expected[i] - actual[i]).abs())
+            .fold(0.0f32, f32::max);
+        panic!(
+            "{}: {} of {} samples differ, first at index {} (expected {}, got {}), max delta {}",
+            name,
+            diffs.len(),
+            actual.len(),
+            first,
+            expected[first],
+            actual[first],
+            max_delta
+        );
+    }
+}
+
+macro_rules! golden_test {
+    ($test:ident, $file:literal, $generate:expr) => {
+        #[test]
+        fn $test() {
+            let actual: Vec<f32> = $generate;
+            check_golden($file, &actual);
+        }
+    };
+}
+
+golden_test!(
+    perlin2_golden,
+    "perlin2",
+    sample_grid(&Perlin2D::new(42, 0.5, 0.5, 4))
+);
+
+golden_test!(
+    simplex2_golden,
+    "simplex2",
+    sample_grid(&Simplex2D::new(42, 0.5, 0.5, 4))
+);
+
+// Midpoint displacement needs 2^n + 1 cells, so this grid is 65×65
+golden_test!(
+    fractal2_golden,
+    "fractal2",
+    flatten2(&Fractal2D::new(GRID + 1, 42, 0.6).generate())
+);
+
+golden_test!(domain_warp2_golden, "domain_warp2", {
+    let base = Perlin2D::new(42, 4.0, 0.5, 4);
+    let warp = Simplex2D::new(43, 1.0, 0.5, 2);
+    let map = DomainWarp2D {
+        base: &base,
+        warp: &warp,
+        warp2: None,
+        size: GRID,
+        warp_strength: 0.3,
+        warp_iterations: 2,
+        warp_scale: core::domain_warp::DEFAULT_WARP_SCALE,
+        warp_offset_x: core::domain_warp::DEFAULT_WARP_OFFSET_X,
+        warp_offset_y: core::domain_warp::DEFAULT_WARP_OFFSET_Y,
+        // Clamp is the original behaviour this file was recorded with
+        boundary: core::BoundaryMode::Clamp,
+    }
+    .generate();
+    flatten2(&map)
+});
@@ -0,0 +1,71 @@
+// Property-based range checks for the noise generators
+//
+// The proptest! and prop_assert! macros expand to `::core::...` paths, which
+// resolve to this crate instead of the real `core` in an integration test.
+// The tests drive proptest's TestRunner directly instead
+
+use core::{Fractal2D, NoiseGenerator, Perlin2D, Simplex2D};
+use proptest::prelude::*;
+use proptest::test_runner::{TestCaseError, TestRunner};
+
+// Fails the case unless `v` is in [low, high], NaN included
+fn check_range(v: f64, low: f64, high: f64, what: String) -> Result<(), TestCaseError> {
+    if (low..=high).contains(&v) {
+        Ok(())
+    } else {
+        Err(TestCaseError::fail(format!("{} = {}", what, v)))
+    }
+}
+
+fn any_point() -> impl Strategy<Value = (u64, f64, f64)> {
+    (any::<u64>(), -1000.0f64..1000.0, -1000.0f64..1000.0)
+}
+
+#[test]
+fn perlin2_in_unit_range() {
+    TestRunner::default()
+        .run(&any_point(), |(seed, x, y)| {
+            let v = Perlin2D::new(seed, 1.0, 0.5, 4).get2(x, y);
+            check_range(
+                v,
+                -1.0,
+                1.0,
+                format!("perlin2 seed {} at ({}, {})", seed, x, y),
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+fn simplex2_in_unit_range() {
+    TestRunner::default()
+        .run(&any_point(), |(seed, x, y)| {
+            let v = Simplex2D::new(seed, 1.0, 0.5, 4).get2(x, y);
+            check_range(
+                v,
+                -1.0,
+                1.0,
+                format!("simplex2 seed {} at ({}, {})", seed, x, y),
+            )
+        })
+        .unwrap();
+}
+
+#[test]
+fn fractal2_bounded() {
+    // Sizes 3, 5, 9, 17, 33 and 65
+    TestRunner::default()
+        .run(&(any::<u64>(), 1u32..=6), |(seed, exp)| {
+            let size = (1 << exp) + 1;
+            let map = Fractal2D::new(size, seed, 1.0).generate();
+            map.iter().flatten().try_for_each(|&v| {
+                check_range(
+                    v as f64,
+                    -5.0,
+                    5.0,
+                    format!("fractal2 size {} seed {}", size, seed),
+                )
+            })
+        })
+        .unwrap();
+}
@@ -0,0 +1,54 @@
+/*
+ * C API for the terrain generators, built from the ffi crate
+ * (libffi.a / libffi.so in target/<profile>/).
+ *
+ * Ownership:
+ *   xxx_create() returns a new generator, or NULL for invalid arguments.
+ *   Release it with the matching xxx_destroy(), exactly once.
+ *   Passing NULL to xxx_destroy() is allowed and does nothing.
+ *   Every other function borrows the pointer. Don't use it after destroy,
+ *   and don't use the same generator from two threads at once.
+ *
+ * Buffers:
+ *   out_buf is owned by the caller and must hold size * size floats.
+ *   Heights are written row-major: out_buf[y * size + x].
+ *   A size of 0 writes nothing, out_buf may be NULL then.
+ */
+#ifndef TERRAIN_H
+#define TERRAIN_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Perlin2D Perlin2D;
+typedef struct Simplex2D Simplex2D;
+typedef struct Fractal2D Fractal2D;
+
+/* Perlin noise, get2() returns roughly [-1, 1]. NULL if octaves is 0 */
+Perlin2D *perlin2d_create(uint64_t seed, double frequency, double persistence, size_t octaves);
+double perlin2d_get2(const Perlin2D *ptr, double x, double y);
+void perlin2d_generate(const Perlin2D *ptr, size_t size, float *out_buf);
+void perlin2d_destroy(Perlin2D *ptr);
+
+/* Simplex noise, same conventions as Perlin2D (NULL if octaves is 0) */
+Simplex2D *simplex2d_create(uint64_t seed, double frequency, double persistence, size_t octaves);
+double simplex2d_get2(const Simplex2D *ptr, double x, double y);
+void simplex2d_generate(const Simplex2D *ptr, size_t size, float *out_buf);
+void simplex2d_destroy(Simplex2D *ptr);
+
+/* Diamond-Square, size must be 2^n + 1 (NULL otherwise).
+ * get2() samples the map from the last generate() call. */
+Fractal2D *fractal2d_create(size_t size, uint64_t seed, double roughness);
+double fractal2d_get2(const Fractal2D *ptr, double x, double y);
+void fractal2d_generate(Fractal2D *ptr, float *out_buf);
+void fractal2d_destroy(Fractal2D *ptr);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TERRAIN_H */
@@ -0,0 +1,154 @@
+//ffi exposes the generators through a C ABI, see include/terrain.h
+//
+// Pointer rules, for every generator type:
+// - *_create() returns an owned pointer (NULL for invalid arguments)
+//   that must be released with the matching *_destroy(), exactly once.
+// - All other functions borrow the pointer, it must not be used after
+//   *_destroy() and must not be shared between threads while in use.
+// - out_buf must have room for size × size floats, written row-major.
+//   With a size of 0 nothing is written and out_buf may be NULL.
+
+use std::ptr;
+
+use terrain::utils::HeightMap2D;
+use terrain::{Fractal2D, NoiseGenerator, Perlin2D, Simplex2D};
+
+// Copy a height-map into a caller-owned buffer
+unsafe fn write_map(map: &HeightMap2D, out_buf: *mut f32) {
+    let size = map.len();
+    let out = unsafe { std::slice::from_raw_parts_mut(out_buf, size * size) };
+    for (dst, src) in out.chunks_mut(size).zip(map) {
+        dst.copy_from_slice(src);
+    }
+}
+
+// NULL if octaves is 0
+#[unsafe(no_mangle)]
+pub extern "C" fn perlin2d_create(
+    seed: u64,
+    frequency: f64,
+    persistence: f64,
+    octaves: usize,
+) -> *mut Perlin2D {
+    if octaves == 0 {
+        return ptr::null_mut();
+    }
+    Box::into_raw(Box::new(Perlin2D::new(
+        seed,
+        frequency,
+        persistence,
+        octaves,
+    )))
+}
+
+/// # Safety
+/// `ptr` must come from perlin2d_create() and not be destroyed yet
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn perlin2d_get2(ptr: *const Perlin2D, x: f64, y: f64) -> f64 {
+    unsafe { &*ptr }.get2(x, y)
+}
+
+/// # Safety
+/// `ptr` as for perlin2d_get2(), `out_buf` must hold size × size floats.
+/// A size of 0 writes nothing, `out_buf` may be NULL then
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn perlin2d_generate(ptr: *const Perlin2D, size: usize, out_buf: *mut f32) {
+    // chunks_mut(0) would panic, and a panic here aborts the caller
+    if size == 0 {
+        return;
+    }
+    let map = unsafe { &*ptr }.generate(size);
+    unsafe { write_map(&map, out_buf) };
+}
+
+/// # Safety
+/// `ptr` must come from perlin2d_create() and is invalid afterwards. NULL is ignored
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn perlin2d_destroy(ptr: *mut Perlin2D) {
+    if !ptr.is_null() {
+        drop(unsafe { Box::from_raw(ptr) });
+    }
+}
+
+// NULL if octaves is 0
+#[unsafe(no_mangle)]
+pub extern "C" fn simplex2d_create(
+    seed: u64,
+    frequency: f64,
+    persistence: f64,
+    octaves: usize,
+) -> *mut Simplex2D {
+    if octaves == 0 {
+        return ptr::null_mut();
+    }
+    Box::into_raw(Box::new(Simplex2D::new(
+        seed,
+        frequency,
+        persistence,
+        octaves,
+    )))
+}
+
+/// # Safety
+/// `ptr` must come from simplex2d_create() and not be destroyed yet
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn simplex2d_get2(ptr: *const Simplex2D, x: f64, y: f64) -> f64 {
+    unsafe { &*ptr }.get2(x, y)
+}
+
+/// # Safety
+/// `ptr` as for simplex2d_get2(), `out_buf` must hold size × size floats.
+/// A size of 0 writes nothing, `out_buf` may be NULL then
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn simplex2d_generate(ptr: *const Simplex2D, size: usize, out_buf: *mut f32) {
+    // chunks_mut(0) would panic, and a panic here aborts the caller
+    if size == 0 {
+        return;
+    }
+    let map = unsafe { &*ptr }.generate(size);
+    unsafe { write_map(&map, out_buf) };
+}
+
+/// # Safety
+/// `ptr` must come from simplex2d_create() and is invalid afterwards. NULL is ignored
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn simplex2d_destroy(ptr: *mut Simplex2D) {
+    if !ptr.is_null() {
+        drop(unsafe { Box::from_raw(ptr) });
+    }
+}
+
+// NULL unless size is 2^n + 1
+#[unsafe(no_mangle)]
+pub extern "C" fn fractal2d_create(size: usize, seed: u64, roughness: f64) -> *mut Fractal2D {
+    if size < 3 || !(size - 1).is_power_of_two() {
+        return ptr::null_mut();
+    }
+    Box::into_raw(Box::new(Fractal2D::new(size, seed, roughness)))
+}
+
+// Samples the map from the last fractal2d_generate() call, 0 before that
+/// # Safety
+/// `ptr` must come from fractal2d_create() and not be destroyed yet
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fractal2d_get2(ptr: *const Fractal2D, x: f64, y: f64) -> f64 {
+    unsafe { &*ptr }.get2(x, y)
+}
+
+/// # Safety
+/// `ptr` as for fractal2d_get2(), `out_buf` must hold size × size floats
+/// for the size given to fractal2d_create()
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fractal2d_generate(ptr: *mut Fractal2D, out_buf: *mut f32) {
+    let map = unsafe { &mut *ptr }.generate();
+    unsafe { write_map(&map, out_buf) };
+}
+
+/// # Safety
+/// `ptr` must come from fractal2d_create() and is invalid afterwards. NULL is ignored
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fractal2d_destroy(ptr: *mut Fractal2D) {
+    if !ptr.is_null() {
+        drop(unsafe { Box::from_raw(ptr) });
+    }
+}
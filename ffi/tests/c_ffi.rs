@@ -0,0 +1,47 @@
+// Builds tests/c_ffi_test.c against libffi.a and runs it. Done here rather
+// than in a build script so the shipped library carries no test code and
+// needs no C compiler
+#![cfg(any(target_os = "linux", target_os = "macos"))]
+
+use std::path::Path;
+use std::process::Command;
+
+// What the Rust std inside a staticlib needs from the system,
+// see `rustc --print native-static-libs`
+#[cfg(target_os = "linux")]
+const NATIVE_LIBS: &[&str] = &[
+    "-lgcc_s",
+    "-lutil",
+    "-lrt",
+    "-lpthread",
+    "-lm",
+    "-ldl",
+    "-lc",
+];
+#[cfg(target_os = "macos")]
+const NATIVE_LIBS: &[&str] = &["-lSystem", "-lc", "-lm"];
+
+#[test]
+fn c_api_roundtrip() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // Cargo puts libffi.a in target/<profile>, one level above this test's deps/
+    let test_exe = std::env::current_exe().unwrap();
+    let profile_dir = test_exe.parent().unwrap().parent().unwrap();
+    let exe = Path::new(env!("CARGO_TARGET_TMPDIR")).join("c_ffi_test");
+
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".into());
+    let status = Command::new(compiler)
+        .args(["-Wall", "-Wextra", "-Werror", "-I"])
+        .arg(manifest_dir.join("include"))
+        .arg(manifest_dir.join("tests/c_ffi_test.c"))
+        .arg(profile_dir.join("libffi.a"))
+        .args(NATIVE_LIBS)
+        .arg("-o")
+        .arg(&exe)
+        .status()
+        .expect("no C compiler, set CC");
+    assert!(status.success(), "compiling c_ffi_test.c failed");
+
+    let status = Command::new(&exe).status().unwrap();
+    assert_eq!(status.code(), Some(0), "C check failed");
+}
@@ -0,0 +1,40 @@
+/* Exercises terrain.h from C, built and run by tests/c_ffi.rs.
+ * Exits with 0 on success, otherwise the number of the failed check. */
+#include <math.h>
+#include <stdlib.h>
+
+#include "terrain.h"
+
+#define SIZE 33
+
+int main(void) {
+    float buf[SIZE * SIZE];
+
+    Perlin2D *perlin = perlin2d_create(42, 4.0, 0.5, 4);
+    if (!perlin) return 1;
+    perlin2d_generate(perlin, SIZE, buf);
+    /* Row 3, column 7 is sampled at (7/SIZE, 3/SIZE) */
+    if (buf[3 * SIZE + 7] != (float)perlin2d_get2(perlin, 7.0 / SIZE, 3.0 / SIZE)) return 2;
+    /* size 0 is a no-op, even with a NULL buffer */
+    perlin2d_generate(perlin, 0, NULL);
+    perlin2d_destroy(perlin);
+
+    Simplex2D *simplex = simplex2d_create(42, 4.0, 0.5, 4);
+    if (!simplex) return 3;
+    simplex2d_generate(simplex, SIZE, buf);
+    for (int i = 0; i < SIZE * SIZE; i++) {
+        if (!isfinite(buf[i])) return 4;
+    }
+    simplex2d_generate(simplex, 0, NULL);
+    simplex2d_destroy(simplex);
+
+    if (fractal2d_create(SIZE + 1, 1, 1.0) != NULL) return 5;
+    Fractal2D *fractal = fractal2d_create(SIZE, 1, 1.0);
+    if (!fractal) return 6;
+    fractal2d_generate(fractal, buf);
+    if (buf[0] != (float)fractal2d_get2(fractal, 0.0, 0.0)) return 7;
+    fractal2d_destroy(fractal);
+
+    fractal2d_destroy(NULL);
+    return 0;
+}
@@ -0,0 +1,49 @@
+#![no_main]
+
+// Runs noise generation, thermal erosion and normalization on arbitrary
+// parameters. Inputs are first clamped into the ranges the app allows,
+// anything inside those ranges must neither panic nor produce NaN
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use terrain::utils::normalize2;
+use terrain::{Perlin2D, ThermalErosion2D};
+
+#[derive(Arbitrary, Debug)]
+struct FuzzInput {
+    seed: u64,
+    size: u8,
+    frequency: f64,
+    persistence: f64,
+    octaves: u8,
+    erosion_iterations: u8,
+    talus_angle: f32,
+}
+
+// Replaces non-finite values with `default`, then clamps into [low, high]
+fn finite_in(v: f64, low: f64, high: f64, default: f64) -> f64 {
+    if v.is_finite() { v.clamp(low, high) } else { default }
+}
+
+fuzz_target!(|input: FuzzInput| {
+    // Size 0 is kept on purpose, it used to panic in erosion
+    let size = input.size as usize % 129;
+    let frequency = finite_in(input.frequency, 0.001, 64.0, 1.0);
+    let persistence = finite_in(input.persistence, 0.0, 1.0, 0.5);
+    // Zero octaves is rejected by Perlin2D::new()
+    let octaves = input.octaves as usize % 12 + 1;
+    let iterations = input.erosion_iterations as usize % 32;
+    let talus = finite_in(input.talus_angle as f64, 0.0, 1.0, 0.01) as f32;
+
+    let mut map = Perlin2D::new(input.seed, frequency, persistence, octaves).generate(size);
+    assert!(map.iter().flatten().all(|v| !v.is_nan()), "noise produced NaN");
+
+    ThermalErosion2D::new(iterations, talus).apply(&mut map);
+    assert!(map.iter().flatten().all(|v| !v.is_nan()), "erosion produced NaN");
+
+    normalize2(&mut map);
+    assert!(
+        map.iter().flatten().all(|v| (0.0..=1.0).contains(v)),
+        "normalize2 left [0,1]"
+    );
+});
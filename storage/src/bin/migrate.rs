@@ -0,0 +1,31 @@
+// Upgrade stored terrains to the current schema:
+//   cargo run -p storage --bin migrate -- [URI] [DB] [COLLECTION]
+// Defaults match the app, mongodb://localhost:27017 terrain_db terrain2d
+use storage::Storage2D;
+use storage::migration::migrate_all;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let uri = args
+        .next()
+        .unwrap_or_else(|| "mongodb://localhost:27017".into());
+    let db = args.next().unwrap_or_else(|| "terrain_db".into());
+    let collection = args.next().unwrap_or_else(|| "terrain2d".into());
+
+    // Built by hand, #[tokio::main] trips over the workspace `core` crate
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+    let result = runtime.block_on(async {
+        let storage = Storage2D::init(&uri, &db, &collection).await?;
+        migrate_all(&storage).await
+    });
+    match result {
+        Ok(count) => println!("Migrated {} document(s) in {}.{}", count, db, collection),
+        Err(e) => {
+            eprintln!("Migration failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
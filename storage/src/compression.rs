@@ -0,0 +1,41 @@
+// zstd compression of height maps, keeps large terrains well under
+// MongoDB's 16 MB document limit
+
+use std::io;
+
+// Balanced speed / ratio, zstd's own default
+const ZSTD_LEVEL: i32 = 3;
+
+// Compress heights as little-endian f32 bytes
+pub fn compress_heights(heights: &[f32]) -> io::Result<Vec<u8>> {
+    let bytes: Vec<u8> = heights.iter().flat_map(|v| v.to_le_bytes()).collect();
+    zstd::encode_all(&bytes[..], ZSTD_LEVEL)
+}
+
+// Inverse of compress_heights()
+pub fn decompress_heights(data: &[u8]) -> io::Result<Vec<f32>> {
+    let bytes = zstd::decode_all(data)?;
+    if bytes.len() % 4 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "compressed height map is not a whole number of f32 values",
+        ));
+    }
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress_heights, decompress_heights};
+
+    #[test]
+    fn compression_roundtrip() {
+        let heights: Vec<f32> = (0..129 * 129).map(|i| (i as f32 * 0.01).sin()).collect();
+        let packed = compress_heights(&heights).unwrap();
+        assert!(packed.len() < heights.len() * 4);
+        assert_eq!(decompress_heights(&packed).unwrap(), heights);
+    }
+}
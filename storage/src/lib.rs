@@ -1,32 +1,227 @@
 //storage holds MongoDB schema & async CRUD
 
+pub mod compression;
+pub mod migration;
 pub mod models;
 
-use crate::models::TerrainDoc2D;
+use crate::compression::{compress_heights, decompress_heights};
+use crate::models::{
+    AggregateStats, TerrainDoc2D, TerrainMetadata, TerrainNameError, TerrainSummary,
+    validate_terrain_name,
+};
 use bson::doc;
 use futures_util::stream::TryStreamExt;
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::options::IndexOptions;
 use mongodb::{Client, Collection, options::ClientOptions};
+use std::future::Future;
+use std::time::Duration;
 
+// Wait before the first retry, doubled for every retry after it
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+
+// Longest wait between two retries, however many there are
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+// Server error code for an insert rejected by a unique index
+const DUPLICATE_KEY: i32 = 11000;
+
+// Server error code for an index that already exists with other options
+const INDEX_OPTIONS_CONFLICT: i32 = 85;
+
+// MongoDB's default name for the (name, seed, dimensions) index, also what
+// the non-unique version on older collections is called
+const UNIQUE_INDEX_NAME: &str = "name_1_seed_1_dimensions_1";
+
+// Connection settings applied on top of the URI
+#[derive(Debug, Clone, Copy)]
+pub struct ClientOptionsExt {
+    pub max_pool_size: u32,
+    pub connect_timeout_ms: u64,
+    // Attempts per operation in retry_create() and retry_read(), at least 1
+    pub max_retries: u32,
+}
+
+impl Default for ClientOptionsExt {
+    fn default() -> Self {
+        Self {
+            max_pool_size: 10,
+            connect_timeout_ms: 2000,
+            max_retries: 3,
+        }
+    }
+}
+
+// Errors from reaching the server at all, as opposed to the query failing
+fn is_connection_failure(err: &mongodb::error::Error) -> bool {
+    matches!(
+        *err.kind,
+        ErrorKind::Io(_)
+            | ErrorKind::ServerSelection { .. }
+            | ErrorKind::ConnectionPoolCleared { .. }
+    )
+}
+
+// Another document already holds this (name, seed, dimensions)
+fn is_duplicate_key(err: &mongodb::error::Error) -> bool {
+    matches!(
+        &*err.kind,
+        ErrorKind::Write(WriteFailure::WriteError(e)) if e.code == DUPLICATE_KEY
+    )
+}
+
+// The index already exists with different options
+fn is_index_conflict(err: &mongodb::error::Error) -> bool {
+    matches!(&*err.kind, ErrorKind::Command(e) if e.code == INDEX_OPTIONS_CONFLICT)
+}
+
+// The reason create() refused the document's name, if that's why it failed
+pub fn name_error(err: &mongodb::error::Error) -> Option<TerrainNameError> {
+    err.get_custom::<TerrainNameError>().copied()
+}
+
+// Wait after failed attempt number `attempt` (from 1): base_delay doubled
+// each time, capped at MAX_RETRY_DELAY
+fn backoff_delay(base_delay: Duration, attempt: u32) -> Duration {
+    let factor = 2u32.checked_pow(attempt - 1).unwrap_or(u32::MAX);
+    base_delay.saturating_mul(factor).min(MAX_RETRY_DELAY)
+}
+
+// Run `op` up to `max_attempts` times, backing off exponentially after each
+// connection failure. Any other error is returned straight away
+async fn with_retries<T, F, Fut>(
+    max_attempts: u32,
+    base_delay: Duration,
+    mut op: F,
+) -> mongodb::error::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = mongodb::error::Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if attempt < max_attempts && is_connection_failure(&e) => {
+                tokio::time::sleep(backoff_delay(base_delay, attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+// Cheap to share: the Client holds the connection pool, so keep one
+// Storage2D around (e.g. in an Arc) instead of calling init() per request
 pub struct Storage2D {
+    client: Client,
     col: Collection<TerrainDoc2D>,
+    compress: bool, // zstd compress height maps on insert
+    options: ClientOptionsExt,
 }
 
 impl Storage2D {
-    // Initialize the MongoDB collection
+    // Initialize the MongoDB collection with the default ClientOptionsExt
     pub async fn init(uri: &str, db_name: &str, col_name: &str) -> mongodb::error::Result<Self> {
+        Self::init_with_options(uri, db_name, col_name, None).await
+    }
+
+    pub async fn init_with_options(
+        uri: &str,
+        db_name: &str,
+        col_name: &str,
+        options: Option<ClientOptionsExt>,
+    ) -> mongodb::error::Result<Self> {
+        let options = options.unwrap_or_default();
         let mut opts = ClientOptions::parse(uri).await?;
         opts.app_name = Some("FYPStorage".to_string());
+        opts.max_pool_size = Some(options.max_pool_size);
+        // Without a server, operations wait for server selection rather than
+        // the connect, so cap both or a retry only comes after 30 s
+        let timeout = Duration::from_millis(options.connect_timeout_ms);
+        opts.connect_timeout = Some(timeout);
+        opts.server_selection_timeout = Some(timeout);
         let client = Client::with_options(opts)?;
         let col = client.database(db_name).collection(col_name);
 
+        with_retries(options.max_retries, RETRY_BASE_DELAY, || {
+            Self::create_indexes(&col)
+        })
+        .await?;
+
+        Ok(Self {
+            client,
+            col,
+            compress: false,
+            options,
+        })
+    }
+
+    async fn create_indexes(col: &Collection<TerrainDoc2D>) -> mongodb::error::Result<()> {
         // Create unique index on (name, seed, dimensions)
         let index_model = mongodb::IndexModel::builder()
             .keys(doc! { "name": 1, "seed": 1, "dimensions": 1 })
-            .options(None)
+            .options(
+                IndexOptions::builder()
+                    .name(UNIQUE_INDEX_NAME.to_string())
+                    .unique(true)
+                    .build(),
+            )
+            .build();
+        match col.create_index(index_model.clone()).await {
+            // Collections from before the index was unique have a non-unique
+            // one under the same name, and create()'s duplicate-key fallback
+            // never fires against it. Replace it; if the collection already
+            // holds duplicates this fails and they must be removed first
+            Err(e) if is_index_conflict(&e) => {
+                col.drop_index(UNIQUE_INDEX_NAME).await?;
+                col.create_index(index_model).await?;
+            }
+            result => {
+                result?;
+            }
+        }
+
+        // Multikey index so tag lookups don't scan the collection
+        let tags_index = mongodb::IndexModel::builder()
+            .keys(doc! { "tags": 1 })
             .build();
-        col.create_index(index_model).await?;
+        col.create_index(tags_index).await?;
 
-        Ok(Self { col })
+        // Seed index for find_by_seed_range()
+        let seed_index = mongodb::IndexModel::builder()
+            .keys(doc! { "seed": 1 })
+            .build();
+        col.create_index(seed_index).await?;
+        Ok(())
+    }
+
+    // The underlying client, shares this storage's connection pool
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    // Same as init(), but height maps are zstd compressed before insert.
+    // Reading works for both compressed and uncompressed documents either way
+    pub async fn init_compressed(
+        uri: &str,
+        db_name: &str,
+        col_name: &str,
+    ) -> mongodb::error::Result<Self> {
+        let mut storage = Self::init(uri, db_name, col_name).await?;
+        storage.compress = true;
+        Ok(storage)
+    }
+
+    // Restore `height_map` from its compressed form, if present
+    fn decompress(doc: Option<TerrainDoc2D>) -> mongodb::error::Result<Option<TerrainDoc2D>> {
+        let Some(mut doc) = doc else {
+            return Ok(None);
+        };
+        if let Some(packed) = doc.height_map_compressed.take() {
+            doc.height_map = decompress_heights(&packed)?;
+        }
+        doc.fill_missing_size();
+        Ok(Some(doc))
     }
 
     pub async fn list_names(&self) -> mongodb::error::Result<Vec<String>> {
@@ -38,32 +233,231 @@ impl Storage2D {
         Ok(names)
     }
 
-    pub async fn read_by_name(&self, name: &str) -> mongodb::error::Result<Option<TerrainDoc2D>> {
-        self.col
+    // Names of all terrains carrying `tag`
+    pub async fn find_by_tag(&self, tag: &str) -> mongodb::error::Result<Vec<String>> {
+        let mut cursor = self
+            .metadata_col()
+            .find(doc! { "tags": tag, "dimensions": 2i32 })
+            .projection(doc! { "height_map": 0, "height_map_compressed": 0 })
+            .await?;
+        let mut names = Vec::new();
+        while let Some(meta) = cursor.try_next().await? {
+            names.push(meta.name);
+        }
+        Ok(names)
+    }
+
+    // Names of all terrains generated with `noise_type`, e.g. "perlin2d"
+    pub async fn find_by_noise_type(
+        &self,
+        noise_type: &str,
+    ) -> mongodb::error::Result<Vec<String>> {
+        let mut cursor = self
+            .metadata_col()
+            .find(doc! { "params.noise_type": noise_type, "dimensions": 2i32 })
+            .projection(doc! { "height_map": 0, "height_map_compressed": 0 })
+            .await?;
+        let mut names = Vec::new();
+        while let Some(meta) = cursor.try_next().await? {
+            names.push(meta.name);
+        }
+        Ok(names)
+    }
+
+    // Leaves out both height map fields, which make up nearly all of a document
+    fn metadata_col(&self) -> Collection<TerrainMetadata> {
+        self.col.clone_with_type()
+    }
+
+    pub async fn read_metadata_by_name(
+        &self,
+        name: &str,
+    ) -> mongodb::error::Result<Option<TerrainMetadata>> {
+        self.metadata_col()
             .find_one(doc! { "name": name, "dimensions": 2i32 })
+            .projection(doc! { "height_map": 0, "height_map_compressed": 0 })
             .await
     }
 
-    // Insert a terrain document.
-    pub async fn create(&self, doc_obj: TerrainDoc2D) -> mongodb::error::Result<()> {
-        // Delete any existing document with same name+seed+dimensions
-        let filter = doc! {
+    pub async fn list_all_metadata(&self) -> mongodb::error::Result<Vec<TerrainMetadata>> {
+        let mut cursor = self
+            .metadata_col()
+            .find(doc! { "dimensions": 2i32 })
+            .projection(doc! { "height_map": 0, "height_map_compressed": 0 })
+            .await?;
+        let mut list = Vec::new();
+        while let Some(meta) = cursor.try_next().await? {
+            list.push(meta);
+        }
+        Ok(list)
+    }
+
+    // Name, seed, noise type, size, date and tags of every terrain, newest
+    // first. Only those fields leave the database
+    pub async fn list_summaries(&self) -> mongodb::error::Result<Vec<TerrainSummary>> {
+        let mut cursor = self
+            .col
+            .clone_with_type::<TerrainSummary>()
+            .find(doc! { "dimensions": 2i32 })
+            .projection(doc! {
+                "name": 1,
+                "seed": 1,
+                "params.noise_type": 1,
+                "width": 1,
+                "height": 1,
+                "created_at": 1,
+                "tags": 1,
+            })
+            .sort(doc! { "created_at": -1 })
+            .await?;
+        let mut list = Vec::new();
+        while let Some(summary) = cursor.try_next().await? {
+            list.push(summary);
+        }
+        Ok(list)
+    }
+
+    pub async fn read_by_name(&self, name: &str) -> mongodb::error::Result<Option<TerrainDoc2D>> {
+        let doc = self
+            .col
+            .find_one(doc! { "name": name, "dimensions": 2i32 })
+            .await?;
+        Self::decompress(doc)
+    }
+
+    // Height stats computed server-side with an aggregation pipeline, so no
+    // height map leaves the database. None if there are no terrains.
+    // Compressed documents are skipped, their heights are opaque to MongoDB
+    pub async fn aggregate_height_stats(&self) -> mongodb::error::Result<Option<AggregateStats>> {
+        let pipeline = vec![
+            doc! { "$match": { "dimensions": 2i32, "height_map.0": { "$exists": true } } },
+            doc! { "$project": {
+                "mean": { "$avg": "$height_map" },
+                "max": { "$max": "$height_map" },
+            } },
+            doc! { "$group": {
+                "_id": null,
+                "avg_mean_height": { "$avg": "$mean" },
+                "avg_max_height": { "$avg": "$max" },
+                "count": { "$sum": 1i64 },
+            } },
+        ];
+        let mut cursor = self.col.aggregate(pipeline).await?;
+        match cursor.try_next().await? {
+            Some(stats) => Ok(Some(bson::from_document(stats)?)),
+            None => Ok(None),
+        }
+    }
+
+    // The unique key of a terrain document
+    fn key_filter(doc_obj: &TerrainDoc2D) -> bson::Document {
+        doc! {
             "name": &doc_obj.name,
             "seed": doc_obj.seed,
             "dimensions": i32::from(doc_obj.dimensions),
-        };
+        }
+    }
+
+    // Fill in what the stored document needs before it is written
+    fn prepare(&self, mut doc_obj: TerrainDoc2D) -> mongodb::error::Result<TerrainDoc2D> {
+        if doc_obj.created_at.is_none() {
+            doc_obj.created_at = Some(bson::DateTime::now());
+        }
+        doc_obj.fill_missing_size();
+
+        // Swap the raw heights for their compressed form
+        if self.compress && doc_obj.height_map_compressed.is_none() {
+            doc_obj.height_map_compressed = Some(compress_heights(&doc_obj.height_map)?);
+            doc_obj.height_map = Vec::new();
+        }
+        Ok(doc_obj)
+    }
+
+    // Insert a terrain document.
+    // Fails with a custom TerrainNameError, see name_error(), for invalid names
+    pub async fn create(&self, doc_obj: TerrainDoc2D) -> mongodb::error::Result<()> {
+        validate_terrain_name(&doc_obj.name).map_err(mongodb::error::Error::custom)?;
+
+        // Delete any existing document with same name+seed+dimensions
+        let filter = Self::key_filter(&doc_obj);
         let _ = self.col.delete_one(filter.clone()).await;
 
+        let mut doc_obj = self.prepare(doc_obj)?;
+
         // Insert the new document
-        self.col.insert_one(doc_obj).await?;
+        match self.col.insert_one(&doc_obj).await {
+            Ok(_) => Ok(()),
+            // A concurrent create() for the same terrain got in between the
+            // delete and the insert. Replace its document instead, once
+            Err(e) if is_duplicate_key(&e) => {
+                // The replacement keeps the stored document's _id
+                doc_obj.id = None;
+                self.col
+                    .find_one_and_replace(filter, &doc_obj)
+                    .upsert(true)
+                    .await?;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Insert the document, or replace the one with the same (name, seed,
+    // dimensions), as a single atomic upsert. Safe to call concurrently
+    pub async fn create_idempotent(&self, doc_obj: TerrainDoc2D) -> mongodb::error::Result<()> {
+        validate_terrain_name(&doc_obj.name).map_err(mongodb::error::Error::custom)?;
+        let mut doc_obj = self.prepare(doc_obj)?;
+        doc_obj.id = None;
+        self.col
+            .replace_one(Self::key_filter(&doc_obj), &doc_obj)
+            .upsert(true)
+            .await?;
         Ok(())
     }
 
+    // create(), retried with backoff when the server can't be reached
+    pub async fn retry_create(&self, doc_obj: TerrainDoc2D) -> mongodb::error::Result<()> {
+        with_retries(self.options.max_retries, RETRY_BASE_DELAY, || {
+            self.create(doc_obj.clone())
+        })
+        .await
+    }
+
+    // read_by_name(), retried with backoff when the server can't be reached
+    pub async fn retry_read(&self, name: &str) -> mongodb::error::Result<Option<TerrainDoc2D>> {
+        with_retries(self.options.max_retries, RETRY_BASE_DELAY, || {
+            self.read_by_name(name)
+        })
+        .await
+    }
+
     // Read a terrain by seed.
     pub async fn read_by_seed(&self, seed: i64) -> mongodb::error::Result<Option<TerrainDoc2D>> {
-        self.col
+        let doc = self
+            .col
             .find_one(doc! { "seed": seed, "dimensions": 2i32 })
-            .await
+            .await?;
+        Self::decompress(doc)
+    }
+
+    // All terrains with min_seed <= seed <= max_seed
+    pub async fn find_by_seed_range(
+        &self,
+        min_seed: i64,
+        max_seed: i64,
+    ) -> mongodb::error::Result<Vec<TerrainDoc2D>> {
+        let mut cursor = self
+            .col
+            .find(doc! {
+                "seed": { "$gte": min_seed, "$lte": max_seed },
+                "dimensions": 2i32,
+            })
+            .await?;
+        let mut docs = Vec::new();
+        while let Some(doc) = cursor.try_next().await? {
+            docs.extend(Self::decompress(Some(doc))?);
+        }
+        Ok(docs)
     }
 
     // Delete by seed (for clean-up).
@@ -75,4 +469,131 @@ impl Storage2D {
         self.col.delete_one(filter).await?;
         Ok(())
     }
+
+    // Delete by name, returns false if no terrain had that name
+    pub async fn delete_by_name(&self, name: &str) -> mongodb::error::Result<bool> {
+        let filter = doc! {
+            "name": name,
+            "dimensions": 2i32,
+        };
+        let result = self.col.delete_one(filter).await?;
+        Ok(result.deleted_count > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        DUPLICATE_KEY, MAX_RETRY_DELAY, Storage2D, backoff_delay, is_duplicate_key, name_error,
+        with_retries,
+    };
+    use crate::models::{TerrainDoc2D, TerrainNameError};
+    use std::cell::Cell;
+    use std::time::{Duration, Instant};
+
+    // The tokio test macro can't be used here: the workspace `core` crate
+    // shadows `::core`, which the macro expansion relies on
+    fn block_on<F: std::future::Future>(f: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(f)
+    }
+
+    fn timeout_error() -> mongodb::error::Error {
+        std::io::Error::new(std::io::ErrorKind::TimedOut, "connection timed out").into()
+    }
+
+    #[test]
+    fn retries_connection_timeouts_with_backoff() {
+        let calls = Cell::new(0);
+        let start = Instant::now();
+        let result = block_on(with_retries(5, Duration::from_millis(10), || {
+            calls.set(calls.get() + 1);
+            let fail = calls.get() < 3;
+            async move { if fail { Err(timeout_error()) } else { Ok(42) } }
+        }));
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(calls.get(), 3);
+        // 10 ms then 20 ms of backoff
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let base = Duration::from_millis(100);
+        assert_eq!(backoff_delay(base, 1), base);
+        assert_eq!(backoff_delay(base, 3), base * 4);
+        // 100 ms × 2^19 would be over 14 h, and 2^40 overflows a u32
+        assert_eq!(backoff_delay(base, 20), MAX_RETRY_DELAY);
+        assert_eq!(backoff_delay(base, 41), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let calls = Cell::new(0);
+        let result: mongodb::error::Result<()> =
+            block_on(with_retries(3, Duration::from_millis(1), || {
+                calls.set(calls.get() + 1);
+                async { Err(timeout_error()) }
+            }));
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 3);
+
+        // Errors that aren't about the connection are not retried
+        calls.set(0);
+        let result: mongodb::error::Result<()> =
+            block_on(with_retries(3, Duration::from_millis(1), || {
+                calls.set(calls.get() + 1);
+                async { Err(mongodb::error::Error::custom("bad query")) }
+            }));
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn detects_duplicate_key_errors() {
+        let write_error = |code: i32| -> mongodb::error::Error {
+            let e = bson::from_document(bson::doc! { "code": code, "errmsg": "E" }).unwrap();
+            mongodb::error::ErrorKind::Write(mongodb::error::WriteFailure::WriteError(e)).into()
+        };
+        assert!(is_duplicate_key(&write_error(DUPLICATE_KEY)));
+        assert!(!is_duplicate_key(&write_error(121)));
+        assert!(!is_duplicate_key(&timeout_error()));
+    }
+
+    #[test]
+    fn name_errors_are_recoverable() {
+        let err = mongodb::error::Error::custom(TerrainNameError::TooLong);
+        assert_eq!(name_error(&err), Some(TerrainNameError::TooLong));
+        assert!(!is_duplicate_key(&err));
+        assert_eq!(name_error(&timeout_error()), None);
+    }
+
+    #[test]
+    fn legacy_documents_get_square_size() {
+        // Saved before width and height were stored
+        let legacy = bson::doc! {
+            "name": "old",
+            "seed": 1i64,
+            "params": {
+                "noise_type": "perlin2d",
+                "frequency": 1.0,
+                "persistence": 0.5,
+                "octaves": 4i64,
+                "amplitudes": null,
+                "roughness": null,
+                "erosion_iters": null,
+                "talus_angle": null,
+                "warp_strength": null,
+            },
+            "height_map": vec![0.5f32; 9],
+            "dimensions": 2i32,
+        };
+        let doc: TerrainDoc2D = bson::from_document(legacy).unwrap();
+        assert_eq!((doc.width, doc.height), (0, 0));
+        let doc = Storage2D::decompress(Some(doc)).unwrap().unwrap();
+        assert_eq!((doc.width, doc.height), (3, 3));
+    }
 }
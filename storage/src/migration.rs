@@ -0,0 +1,93 @@
+// Bring documents saved by older versions up to the current TerrainDoc2D layout
+use bson::{Bson, Document, doc};
+use futures_util::stream::TryStreamExt;
+
+use crate::Storage2D;
+use crate::compression::decompress_heights;
+use crate::models::SCHEMA_VERSION;
+
+// Fields to $set on a pre-versioning document: the version, plus the size
+// and creation time where they are missing
+fn version_1_update(old: &Document) -> mongodb::error::Result<Document> {
+    let mut set = doc! { "schema_version": SCHEMA_VERSION as i64 };
+
+    let has_size = |key: &str| match old.get(key) {
+        Some(Bson::Int32(n)) => *n > 0,
+        Some(Bson::Int64(n)) => *n > 0,
+        _ => false,
+    };
+    if !has_size("width") || !has_size("height") {
+        // Square maps only back then, same rule as fill_missing_size()
+        let cells = match old.get_binary_generic("height_map_compressed") {
+            Ok(bytes) => decompress_heights(bytes)?.len(),
+            Err(_) => old.get_array("height_map").map_or(0, Vec::len),
+        };
+        let side = (cells as f64).sqrt() as i64;
+        set.insert("width", side);
+        set.insert("height", side);
+    }
+
+    // The ObjectId holds the insert time to the second, close enough
+    if matches!(old.get("created_at"), None | Some(Bson::Null))
+        && let Ok(id) = old.get_object_id("_id")
+    {
+        set.insert("created_at", id.timestamp());
+    }
+    Ok(set)
+}
+
+// Upgrade every document without a schema_version, returning how many changed.
+// Safe to run again, upgraded documents are skipped
+pub async fn migrate_all(storage: &Storage2D) -> mongodb::error::Result<u64> {
+    let col = storage.col.clone_with_type::<Document>();
+    let mut cursor = col
+        .find(doc! { "schema_version": { "$exists": false } })
+        .await?;
+    let mut migrated = 0;
+    while let Some(old) = cursor.try_next().await? {
+        let set = version_1_update(&old)?;
+        let id = old.get("_id").cloned().unwrap_or(Bson::Null);
+        let result = col
+            .update_one(doc! { "_id": id }, doc! { "$set": set })
+            .await?;
+        migrated += result.modified_count;
+    }
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::version_1_update;
+    use crate::compression::compress_heights;
+    use bson::{doc, oid::ObjectId};
+
+    #[test]
+    fn old_document_gets_version_size_and_date() {
+        let id = ObjectId::new();
+        let old = doc! { "_id": id, "name": "old", "height_map": vec![0.5f64; 9] };
+        let set = version_1_update(&old).unwrap();
+        assert_eq!(set.get_i64("schema_version").unwrap(), 1);
+        assert_eq!(set.get_i64("width").unwrap(), 3);
+        assert_eq!(set.get_i64("height").unwrap(), 3);
+        assert_eq!(set.get_datetime("created_at").unwrap(), &id.timestamp());
+    }
+
+    #[test]
+    fn existing_fields_are_kept() {
+        let compressed = compress_heights(&[0.0; 16]).unwrap();
+        let old = doc! {
+            "_id": ObjectId::new(),
+            "height_map": [],
+            "height_map_compressed": bson::Binary {
+                subtype: bson::spec::BinarySubtype::Generic,
+                bytes: compressed,
+            },
+            "width": 8,
+            "height": 2,
+            "created_at": bson::DateTime::from_millis(0),
+        };
+        let set = version_1_update(&old).unwrap();
+        assert_eq!(set.len(), 1);
+        assert!(set.contains_key("schema_version"));
+    }
+}
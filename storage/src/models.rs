@@ -1,19 +1,28 @@
 use bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+// Bump when the stored layout of TerrainDoc2D changes, and teach
+// migration::migrate_all() how to bring older documents up to date
+pub const SCHEMA_VERSION: u32 = 1;
+
+fn default_schema_version() -> u32 {
+    SCHEMA_VERSION
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TerrainParams {
     pub noise_type: String, // e.g. "perlin2d", "fractal2d"
     pub frequency: f64,
     pub persistence: f64,
     pub octaves: usize,
-    pub roughness: Option<f64>, // for fractal
+    pub amplitudes: Option<Vec<f64>>, // explicit per-octave weights, if used
+    pub roughness: Option<f64>,       // for fractal
     pub erosion_iters: Option<u32>,
     pub talus_angle: Option<f32>,
     pub warp_strength: Option<f64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TerrainDoc2D {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none", default)]
     pub id: Option<ObjectId>,
@@ -21,6 +30,205 @@ pub struct TerrainDoc2D {
     pub seed: i64,
     pub params: TerrainParams,
     // Flattened row-major: length = size×size
+    // Empty when the document is stored compressed
     pub height_map: Vec<f32>,
+    // zstd compressed little-endian f32 heights, see Storage2D::init_compressed()
+    #[serde(with = "serde_bytes", skip_serializing_if = "Option::is_none", default)]
+    pub height_map_compressed: Option<Vec<u8>>,
     pub dimensions: u8, // should always be 2 here
+    // Grid size in cells, 0 in documents saved before these fields existed.
+    // Storage2D assumes a square map for those, see fill_missing_size()
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+    // Set by Storage2D::create() when left as None
+    #[serde(default)]
+    pub created_at: Option<bson::DateTime>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Missing in documents saved before versioning, see migration::migrate_all()
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+}
+
+impl TerrainDoc2D {
+    // Infer a square width × height from the height map when either is unset
+    pub fn fill_missing_size(&mut self) {
+        if self.width == 0 || self.height == 0 {
+            let side = (self.height_map.len() as f64).sqrt() as u32;
+            self.width = side;
+            self.height = side;
+        }
+    }
+}
+
+// TerrainDoc2D without the height data, for listing and searching
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TerrainMetadata {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none", default)]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    pub seed: i64,
+    pub params: TerrainParams,
+    pub dimensions: u8,
+    // 0 for documents saved before the size was stored
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+    #[serde(default)]
+    pub created_at: Option<bson::DateTime>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+// One row of the load table, from Storage2D::list_summaries()
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "StoredSummary")]
+pub struct TerrainSummary {
+    pub name: String,
+    pub seed: i64,
+    pub noise_type: String,
+    // 0 for documents saved before the size was stored
+    pub width: u32,
+    pub height: u32,
+    pub created_at: Option<bson::DateTime>,
+    pub tags: Vec<String>,
+}
+
+// The projected document, noise_type still nested under params
+#[derive(Deserialize)]
+struct StoredSummary {
+    name: String,
+    seed: i64,
+    params: StoredNoiseType,
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    #[serde(default)]
+    created_at: Option<bson::DateTime>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct StoredNoiseType {
+    noise_type: String,
+}
+
+impl From<StoredSummary> for TerrainSummary {
+    fn from(stored: StoredSummary) -> Self {
+        Self {
+            name: stored.name,
+            seed: stored.seed,
+            noise_type: stored.params.noise_type,
+            width: stored.width,
+            height: stored.height,
+            created_at: stored.created_at,
+            tags: stored.tags,
+        }
+    }
+}
+
+// Height statistics over all stored 2D terrains, see Storage2D::aggregate_height_stats()
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregateStats {
+    // Mean height of each terrain, averaged over terrains
+    pub avg_mean_height: f64,
+    // Highest point of each terrain, averaged over terrains
+    pub avg_max_height: f64,
+    pub count: u64,
+}
+
+// Longest terrain name create() accepts, in bytes
+pub const MAX_NAME_LEN: usize = 128;
+
+// Why validate_terrain_name() rejected a name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerrainNameError {
+    // Empty or only whitespace
+    Empty,
+    TooLong,
+    // Control characters (null included) or invisible zero-width ones
+    InvalidCharacters,
+}
+
+impl std::fmt::Display for TerrainNameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TerrainNameError::Empty => write!(f, "Terrain name is required"),
+            TerrainNameError::TooLong => {
+                write!(f, "Terrain name must be at most {} bytes", MAX_NAME_LEN)
+            }
+            TerrainNameError::InvalidCharacters => write!(
+                f,
+                "Terrain name can't contain control or invisible characters"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TerrainNameError {}
+
+// Zero-width characters that would make two names look the same
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}')
+}
+
+// Names are shown in the UI and used in query filters, so keep them printable
+pub fn validate_terrain_name(name: &str) -> Result<(), TerrainNameError> {
+    if name.trim().is_empty() {
+        Err(TerrainNameError::Empty)
+    } else if name.len() > MAX_NAME_LEN {
+        Err(TerrainNameError::TooLong)
+    } else if name.chars().any(|c| c.is_control() || is_invisible(c)) {
+        Err(TerrainNameError::InvalidCharacters)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_NAME_LEN, TerrainNameError, TerrainSummary, validate_terrain_name};
+
+    #[test]
+    fn summary_from_projected_document() {
+        let projected = bson::doc! {
+            "_id": bson::oid::ObjectId::new(),
+            "name": "ridge",
+            "seed": 7i64,
+            "params": { "noise_type": "simplex2d" },
+            "width": 129,
+            "height": 65,
+        };
+        let summary: TerrainSummary = bson::from_document(projected).unwrap();
+        assert_eq!(summary.noise_type, "simplex2d");
+        assert_eq!((summary.width, summary.height), (129, 65));
+        assert_eq!(summary.created_at, None);
+        assert!(summary.tags.is_empty());
+    }
+
+    #[test]
+    fn accepts_ordinary_names() {
+        assert_eq!(validate_terrain_name("Alps 2"), Ok(()));
+        assert_eq!(validate_terrain_name("Gebirge Süd"), Ok(()));
+        assert_eq!(validate_terrain_name(&"a".repeat(MAX_NAME_LEN)), Ok(()));
+    }
+
+    #[test]
+    fn rejects_bad_names() {
+        use TerrainNameError::*;
+        assert_eq!(validate_terrain_name(""), Err(Empty));
+        assert_eq!(validate_terrain_name(" \t "), Err(Empty));
+        assert_eq!(validate_terrain_name(&"ü".repeat(65)), Err(TooLong));
+        assert_eq!(validate_terrain_name("a\0b"), Err(InvalidCharacters));
+        assert_eq!(validate_terrain_name("line\nbreak"), Err(InvalidCharacters));
+        assert_eq!(
+            validate_terrain_name("zero\u{200B}width"),
+            Err(InvalidCharacters)
+        );
+    }
 }
@@ -0,0 +1,323 @@
+// Integration tests against a live MongoDB on localhost:27017
+// Run with: cargo test -p storage -- --ignored
+
+use storage::Storage2D;
+use storage::models::{SCHEMA_VERSION, TerrainDoc2D, TerrainParams};
+
+const URI: &str = "mongodb://localhost:27017";
+const DB: &str = "terrain_db_test";
+
+// The tokio test macro can't be used here: the workspace `core` crate
+// shadows `::core`, which the macro expansion relies on
+fn block_on<F: std::future::Future>(f: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(f)
+}
+
+fn sample_doc(name: &str, seed: i64, size: usize) -> TerrainDoc2D {
+    TerrainDoc2D {
+        id: None,
+        name: name.to_string(),
+        seed,
+        params: TerrainParams {
+            noise_type: "perlin2d".to_string(),
+            frequency: 1.0,
+            persistence: 0.5,
+            octaves: 4,
+            amplitudes: None,
+            roughness: None,
+            erosion_iters: None,
+            talus_angle: None,
+            warp_strength: None,
+        },
+        height_map: (0..size * size).map(|i| (i as f32 * 0.37).sin()).collect(),
+        height_map_compressed: None,
+        dimensions: 2,
+        width: size as u32,
+        height: size as u32,
+        created_at: None,
+        tags: vec!["roundtrip".to_string(), name.to_string()],
+        schema_version: SCHEMA_VERSION,
+    }
+}
+
+#[test]
+#[ignore = "requires MongoDB on localhost:27017"]
+fn roundtrip_uncompressed() {
+    block_on(async {
+        let storage = Storage2D::init(URI, DB, "roundtrip_plain").await.unwrap();
+        let doc = sample_doc("roundtrip_plain", 901, 65);
+        let expected = doc.height_map.clone();
+
+        storage.create(doc).await.unwrap();
+        let read = storage.read_by_seed(901).await.unwrap().unwrap();
+        storage.delete_by_seed(901).await.unwrap();
+
+        assert_eq!(read.height_map, expected);
+    });
+}
+
+#[test]
+#[ignore = "requires MongoDB on localhost:27017"]
+fn roundtrip_compressed() {
+    block_on(async {
+        let storage = Storage2D::init_compressed(URI, DB, "roundtrip_zstd")
+            .await
+            .unwrap();
+        let doc = sample_doc("roundtrip_zstd", 902, 129);
+        let expected = doc.height_map.clone();
+
+        storage.create(doc).await.unwrap();
+        let read = storage
+            .read_by_name("roundtrip_zstd")
+            .await
+            .unwrap()
+            .unwrap();
+        storage.delete_by_seed(902).await.unwrap();
+
+        assert!(read.height_map_compressed.is_none());
+        assert_eq!(read.height_map, expected);
+    });
+}
+
+#[test]
+#[ignore = "requires MongoDB on localhost:27017"]
+fn find_by_tag_and_created_at() {
+    block_on(async {
+        let storage = Storage2D::init(URI, DB, "roundtrip_tags").await.unwrap();
+        storage
+            .create(sample_doc("tagged_a", 903, 9))
+            .await
+            .unwrap();
+        storage
+            .create(sample_doc("tagged_b", 904, 9))
+            .await
+            .unwrap();
+
+        let mut shared = storage.find_by_tag("roundtrip").await.unwrap();
+        shared.sort();
+        let only_a = storage.find_by_tag("tagged_a").await.unwrap();
+        let none = storage.find_by_tag("no_such_tag").await.unwrap();
+        let read = storage.read_by_seed(903).await.unwrap().unwrap();
+        storage.delete_by_seed(903).await.unwrap();
+        storage.delete_by_seed(904).await.unwrap();
+
+        assert_eq!(shared, vec!["tagged_a", "tagged_b"]);
+        assert_eq!(only_a, vec!["tagged_a"]);
+        assert!(none.is_empty());
+        assert!(read.created_at.is_some());
+    });
+}
+
+#[test]
+#[ignore = "requires MongoDB on localhost:27017"]
+fn find_by_seed_range_inclusive() {
+    block_on(async {
+        let storage = Storage2D::init(URI, DB, "roundtrip_seed_range")
+            .await
+            .unwrap();
+        for seed in [100, 200, 300] {
+            storage
+                .create(sample_doc(&format!("range_{}", seed), seed, 9))
+                .await
+                .unwrap();
+        }
+
+        let found = storage.find_by_seed_range(150, 250).await.unwrap();
+        for seed in [100, 200, 300] {
+            storage.delete_by_seed(seed).await.unwrap();
+        }
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].seed, 200);
+        assert_eq!(found[0].name, "range_200");
+    });
+}
+
+#[test]
+#[ignore = "requires MongoDB on localhost:27017"]
+fn find_by_noise_type_matches_params() {
+    block_on(async {
+        let storage = Storage2D::init(URI, DB, "roundtrip_noise_type")
+            .await
+            .unwrap();
+        let mut simplex = sample_doc("noise_simplex", 905, 9);
+        simplex.params.noise_type = "simplex2d".to_string();
+        storage.create(simplex).await.unwrap();
+        storage
+            .create(sample_doc("noise_perlin", 906, 9))
+            .await
+            .unwrap();
+
+        let found = storage.find_by_noise_type("simplex2d").await.unwrap();
+        storage.delete_by_seed(905).await.unwrap();
+        storage.delete_by_seed(906).await.unwrap();
+
+        assert_eq!(found, vec!["noise_simplex"]);
+    });
+}
+
+#[test]
+#[ignore = "requires MongoDB on localhost:27017"]
+fn aggregate_height_stats_over_two_docs() {
+    block_on(async {
+        let storage = Storage2D::init(URI, DB, "roundtrip_aggregate")
+            .await
+            .unwrap();
+        let mut low = sample_doc("aggregate_low", 907, 2);
+        low.height_map = vec![0.0, 0.1, 0.2, 0.3];
+        let mut high = sample_doc("aggregate_high", 908, 2);
+        high.height_map = vec![0.5, 0.5, 0.5, 1.0];
+        storage.create(low).await.unwrap();
+        storage.create(high).await.unwrap();
+
+        let stats = storage.aggregate_height_stats().await.unwrap().unwrap();
+        storage.delete_by_seed(907).await.unwrap();
+        storage.delete_by_seed(908).await.unwrap();
+
+        // Means are 0.15 and 0.625, maxima 0.3 and 1.0 (as f32, so not exact)
+        assert_eq!(stats.count, 2);
+        assert!((stats.avg_mean_height - 0.3875).abs() < 1e-6);
+        assert!((stats.avg_max_height - 0.65).abs() < 1e-6);
+    });
+}
+
+#[test]
+#[ignore = "requires MongoDB on localhost:27017"]
+fn roundtrip_non_square() {
+    block_on(async {
+        let storage = Storage2D::init(URI, DB, "roundtrip_non_square")
+            .await
+            .unwrap();
+        let mut doc = sample_doc("non_square", 909, 0);
+        doc.width = 128;
+        doc.height = 64;
+        doc.height_map = (0..128 * 64).map(|i| i as f32 / 8192.0).collect();
+        let expected = doc.height_map.clone();
+
+        storage.create(doc).await.unwrap();
+        let read = storage.read_by_name("non_square").await.unwrap().unwrap();
+        let meta = storage
+            .read_metadata_by_name("non_square")
+            .await
+            .unwrap()
+            .unwrap();
+        storage.delete_by_seed(909).await.unwrap();
+
+        assert_eq!((read.width, read.height), (128, 64));
+        assert_eq!((meta.width, meta.height), (128, 64));
+        assert_eq!(read.height_map, expected);
+    });
+}
+
+#[test]
+#[ignore = "requires MongoDB on localhost:27017"]
+fn delete_by_name_removes_only_that_terrain() {
+    block_on(async {
+        let storage = Storage2D::init(URI, DB, "roundtrip_delete").await.unwrap();
+        storage
+            .create(sample_doc("delete_me", 910, 9))
+            .await
+            .unwrap();
+        storage.create(sample_doc("keep_me", 911, 9)).await.unwrap();
+
+        assert!(storage.delete_by_name("delete_me").await.unwrap());
+        assert!(!storage.delete_by_name("delete_me").await.unwrap());
+        let gone = storage.read_by_name("delete_me").await.unwrap();
+        let kept = storage.read_by_name("keep_me").await.unwrap();
+        storage.delete_by_seed(911).await.unwrap();
+
+        assert!(gone.is_none());
+        assert!(kept.is_some());
+    });
+}
+
+#[test]
+#[ignore = "requires MongoDB on localhost:27017"]
+fn concurrent_creates_leave_one_document() {
+    block_on(async {
+        let storage = Storage2D::init(URI, DB, "roundtrip_concurrent")
+            .await
+            .unwrap();
+        let (a, b) = tokio::join!(
+            storage.create(sample_doc("concurrent", 912, 9)),
+            storage.create(sample_doc("concurrent", 912, 9)),
+        );
+        let (c, d) = tokio::join!(
+            storage.create_idempotent(sample_doc("concurrent", 912, 9)),
+            storage.create_idempotent(sample_doc("concurrent", 912, 9)),
+        );
+        let found = storage.find_by_seed_range(912, 912).await.unwrap();
+        storage.delete_by_seed(912).await.unwrap();
+
+        a.unwrap();
+        b.unwrap();
+        c.unwrap();
+        d.unwrap();
+        assert_eq!(found.len(), 1);
+    });
+}
+
+#[test]
+#[ignore = "requires MongoDB on localhost:27017"]
+fn migrate_all_upgrades_old_documents() {
+    block_on(async {
+        let storage = Storage2D::init(URI, DB, "roundtrip_migration")
+            .await
+            .unwrap();
+        let raw = storage
+            .client()
+            .database(DB)
+            .collection::<bson::Document>("roundtrip_migration");
+
+        // Saved before schema_version, width/height and created_at existed
+        let mut old = bson::to_document(&sample_doc("migrate_old", 913, 5)).unwrap();
+        for key in ["schema_version", "width", "height", "created_at"] {
+            old.remove(key);
+        }
+        let id = raw.insert_one(old).await.unwrap().inserted_id;
+
+        let migrated = storage::migration::migrate_all(&storage).await.unwrap();
+        let again = storage::migration::migrate_all(&storage).await.unwrap();
+        let doc = raw
+            .find_one(bson::doc! { "_id": id.clone() })
+            .await
+            .unwrap()
+            .unwrap();
+        raw.delete_one(bson::doc! { "_id": id }).await.unwrap();
+
+        assert_eq!(migrated, 1);
+        assert_eq!(again, 0);
+        assert_eq!(doc.get_i64("schema_version").unwrap(), 1);
+        assert_eq!(doc.get_i64("width").unwrap(), 5);
+        assert!(doc.get_datetime("created_at").is_ok());
+    });
+}
+
+#[test]
+#[ignore = "requires MongoDB on localhost:27017"]
+fn list_summaries_newest_first() {
+    block_on(async {
+        let storage = Storage2D::init(URI, DB, "roundtrip_summaries")
+            .await
+            .unwrap();
+        let mut older = sample_doc("summary_older", 914, 9);
+        older.created_at = Some(bson::DateTime::from_millis(1_000));
+        let mut newer = sample_doc("summary_newer", 915, 17);
+        newer.created_at = Some(bson::DateTime::from_millis(2_000));
+        storage.create(older).await.unwrap();
+        storage.create(newer).await.unwrap();
+
+        let list = storage.list_summaries().await.unwrap();
+        storage.delete_by_seed(914).await.unwrap();
+        storage.delete_by_seed(915).await.unwrap();
+
+        let names: Vec<&str> = list.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["summary_newer", "summary_older"]);
+        assert_eq!(list[0].noise_type, "perlin2d");
+        assert_eq!((list[0].width, list[0].height), (17, 17));
+    });
+}
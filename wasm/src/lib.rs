@@ -0,0 +1,150 @@
+//wasm exposes the generators to JavaScript through wasm-bindgen
+// Build with `make -C wasm pkg`, see www/index.js for usage
+
+use js_sys::Float32Array;
+use terrain::utils::{HeightMap2D, flatten2};
+use terrain::{DomainWarp2D, Fractal2D, NoiseGenerator, Perlin2D, Simplex2D, ThermalErosion2D};
+use wasm_bindgen::prelude::*;
+
+// Seeds are u32 on the JS side, a u64 would have to be passed as a BigInt
+
+// Row-major copy of a height-map for JS
+fn to_array(map: &HeightMap2D) -> Float32Array {
+    Float32Array::from(&flatten2(map)[..])
+}
+
+#[wasm_bindgen]
+pub struct Perlin2DWasm {
+    inner: Perlin2D,
+}
+
+#[wasm_bindgen]
+impl Perlin2DWasm {
+    // octaves must be at least 1
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        seed: u32,
+        frequency: f64,
+        persistence: f64,
+        octaves: usize,
+    ) -> Result<Perlin2DWasm, JsError> {
+        if octaves == 0 {
+            return Err(JsError::new("octaves must be at least 1"));
+        }
+        Ok(Self {
+            inner: Perlin2D::new(seed.into(), frequency, persistence, octaves),
+        })
+    }
+
+    pub fn get2(&self, x: f64, y: f64) -> f64 {
+        self.inner.get2(x, y)
+    }
+
+    // size×size heights, row-major
+    pub fn generate(&self, size: u32) -> Float32Array {
+        to_array(&self.inner.generate(size as usize))
+    }
+}
+
+#[wasm_bindgen]
+pub struct Simplex2DWasm {
+    inner: Simplex2D,
+}
+
+#[wasm_bindgen]
+impl Simplex2DWasm {
+    // octaves must be at least 1
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        seed: u32,
+        frequency: f64,
+        persistence: f64,
+        octaves: usize,
+    ) -> Result<Simplex2DWasm, JsError> {
+        if octaves == 0 {
+            return Err(JsError::new("octaves must be at least 1"));
+        }
+        Ok(Self {
+            inner: Simplex2D::new(seed.into(), frequency, persistence, octaves),
+        })
+    }
+
+    pub fn get2(&self, x: f64, y: f64) -> f64 {
+        self.inner.get2(x, y)
+    }
+
+    // size×size heights, row-major
+    pub fn generate(&self, size: u32) -> Float32Array {
+        to_array(&self.inner.generate(size as usize))
+    }
+}
+
+#[wasm_bindgen]
+pub struct Fractal2DWasm {
+    inner: Fractal2D,
+}
+
+#[wasm_bindgen]
+impl Fractal2DWasm {
+    // size must be 2^n + 1, e.g. 129 or 257
+    #[wasm_bindgen(constructor)]
+    pub fn new(size: u32, seed: u32, roughness: f64) -> Result<Fractal2DWasm, JsError> {
+        let size = size as usize;
+        if size < 3 || !(size - 1).is_power_of_two() {
+            return Err(JsError::new("size must be 2^n+1"));
+        }
+        Ok(Self {
+            inner: Fractal2D::new(size, seed.into(), roughness),
+        })
+    }
+
+    // size×size heights, row-major
+    pub fn generate(&mut self) -> Float32Array {
+        to_array(&self.inner.generate())
+    }
+}
+
+// Erode a row-major size×size height-map in place
+#[wasm_bindgen]
+pub fn thermal_erosion(
+    heights: &mut [f32],
+    size: u32,
+    iterations: u32,
+    talus_angle: f32,
+) -> Result<(), JsError> {
+    let size = size as usize;
+    if size == 0 {
+        return Err(JsError::new("size must be at least 1"));
+    }
+    if size.checked_mul(size) != Some(heights.len()) {
+        return Err(JsError::new("heights must hold size×size values"));
+    }
+    let mut map: HeightMap2D = heights.chunks(size).map(|row| row.to_vec()).collect();
+    ThermalErosion2D::new(iterations as usize, talus_angle).apply(&mut map);
+    heights.copy_from_slice(&flatten2(&map));
+    Ok(())
+}
+
+// Perlin `base` sampled through a single-pass domain warp driven by `warp`
+#[wasm_bindgen]
+pub fn domain_warp(
+    base: &Perlin2DWasm,
+    warp: &Perlin2DWasm,
+    size: u32,
+    warp_strength: f64,
+) -> Float32Array {
+    let map = DomainWarp2D {
+        base: &base.inner,
+        warp: &warp.inner,
+        warp2: None,
+        size: size as usize,
+        warp_strength,
+        warp_iterations: 1,
+        warp_scale: terrain::domain_warp::DEFAULT_WARP_SCALE,
+        warp_offset_x: terrain::domain_warp::DEFAULT_WARP_OFFSET_X,
+        warp_offset_y: terrain::domain_warp::DEFAULT_WARP_OFFSET_Y,
+        boundary: terrain::BoundaryMode::default(),
+    }
+    .generate();
+    to_array(&map)
+}
@@ -0,0 +1,39 @@
+// Run with: wasm-pack test --headless --firefox wasm
+#![cfg(target_arch = "wasm32")]
+
+use wasm::{Fractal2DWasm, Perlin2DWasm, thermal_erosion};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn perlin_generate_matches_get2() {
+    let perlin = Perlin2DWasm::new(7, 4.0, 0.5, 4).unwrap();
+    let heights = perlin.generate(16).to_vec();
+    assert_eq!(heights.len(), 16 * 16);
+    // Row 2, column 5 sits at (5/16, 2/16)
+    assert_eq!(
+        heights[2 * 16 + 5],
+        perlin.get2(5.0 / 16.0, 2.0 / 16.0) as f32
+    );
+}
+
+#[wasm_bindgen_test]
+fn fractal_size_is_checked() {
+    assert!(Fractal2DWasm::new(100, 1, 1.0).is_err());
+    let mut fractal = Fractal2DWasm::new(33, 1, 1.0).unwrap();
+    assert_eq!(fractal.generate().length(), 33 * 33);
+}
+
+#[wasm_bindgen_test]
+fn erosion_lowers_a_spike() {
+    let mut heights = vec![0.0; 9];
+    heights[4] = 5.0;
+    thermal_erosion(&mut heights, 3, 1, 0.5).unwrap();
+    assert!(heights[4] < 5.0);
+}
+
+#[wasm_bindgen_test]
+fn erosion_size_is_checked() {
+    let mut heights = vec![0.0; 9];
+    assert!(thermal_erosion(&mut heights, 0, 1, 0.5).is_err());
+    assert!(thermal_erosion(&mut heights, 4, 1, 0.5).is_err());
+}
@@ -0,0 +1,18 @@
+// Minimal browser example: draw a Perlin height-map as grayscale
+import init, { Perlin2DWasm } from "../pkg/wasm.js";
+
+const SIZE = 257;
+
+await init();
+const perlin = new Perlin2DWasm(2025, 4.0, 0.5, 6);
+const heights = perlin.generate(SIZE); // Float32Array, row-major, roughly [-1, 1]
+perlin.free();
+
+const canvas = document.getElementById("terrain");
+const ctx = canvas.getContext("2d");
+const image = ctx.createImageData(SIZE, SIZE);
+heights.forEach((h, i) => {
+  const v = Math.round((h * 0.5 + 0.5) * 255);
+  image.data.set([v, v, v, 255], i * 4);
+});
+ctx.putImageData(image, 0, 0);